@@ -0,0 +1,67 @@
+//! Compares the hand-rolled scan of the built-in `SystemTime` records against
+//! matching them with the schema's regex.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use blocks::model::Context;
+use blocks::parse::{Schema, TimeField, parse};
+use regex::Regex;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Entries per record, like a busy eviction section.
+const ENTRIES: u64 = 256;
+
+fn record() -> String {
+    let entries: Vec<_> = (0..ENTRIES)
+        .map(|i| {
+            format!(
+                "(SstableBlockIndex {{ sst_id: {}, block_idx: {i} }}, SystemTime {{ tv_sec: {}, tv_nsec: {} }})",
+                i % 17,
+                1_700_000_000 + i,
+                i * 7_919 % 1_000_000_000,
+            )
+        })
+        .collect();
+    format!(
+        "========== EVICTED DATA BLOCKS ==========\n[{}]",
+        entries.join(", ")
+    )
+}
+
+/// Mean time of parsing `record` with `schema`, over about a second.
+fn measure(record: &str, schema: &Schema, context: &Context) -> Duration {
+    let mut runs = 0;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(parse(black_box(record), schema, context).unwrap());
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    let record = record();
+    let context = Context::default();
+    let fast = Schema::builtin(TimeField::Systime);
+    // Any other pattern text, even matching the same, takes the regex.
+    let mut slow = Schema::builtin(TimeField::Systime);
+    slow.pattern = Regex::new(&format!("(?:{})", slow.pattern.as_str())).unwrap();
+    assert_eq!(
+        parse(&record, &fast, &context),
+        parse(&record, &slow, &context)
+    );
+
+    let fast = measure(&record, &fast, &context);
+    let slow = measure(&record, &slow, &context);
+    let per_entry = |time: Duration| time.as_nanos() as f64 / ENTRIES as f64;
+    println!(
+        "scan:  {fast:?} per record, {:.1}ns per entry",
+        per_entry(fast)
+    );
+    println!(
+        "regex: {slow:?} per record, {:.1}ns per entry",
+        per_entry(slow)
+    );
+    println!("speedup: {:.1}x", slow.as_secs_f64() / fast.as_secs_f64());
+}
@@ -0,0 +1,35 @@
+//! Parse throughput and analysis time over the synthetic trace of
+//! `blocks bench`, with criterion's statistics and comparison against the
+//! last run.
+//!
+//! Run with `cargo bench --bench pipeline`.
+
+use blocks::bench::{analyze, parse, write_trace};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+/// Fewer than `blocks bench` defaults to, as criterion runs each stage many
+/// times.
+const ACCESSES: u64 = 100_000;
+
+const FILES: usize = 8;
+
+fn pipeline(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let records = write_trace(dir.path(), ACCESSES, FILES).unwrap();
+    let events = parse(dir.path(), None).unwrap();
+    assert_eq!(events.len() as u64, records);
+
+    let mut group = c.benchmark_group("pipeline");
+    group.throughput(Throughput::Elements(records));
+    group.bench_function("parse", |b| {
+        b.iter(|| parse(black_box(dir.path()), None).unwrap())
+    });
+    group.bench_function("analyze", |b| {
+        b.iter_batched(|| events.clone(), analyze, BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, pipeline);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+//! Python bindings of the parser and the miss matching, as the `blocks_py`
+//! module. Results are dicts of equally long column lists, which
+//! `pandas.DataFrame` takes as they are.
+
+use blocks::analysis::durations::{Match, Matching, match_misses};
+use blocks::format::ts_nanos;
+use blocks::model::{Context, Event};
+use blocks::parse::{ParseOptions, Schema, TimeField};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Parses the trace files in `path`, or the single file at `path`, sorted
+/// newest first like the CLI writes them. Each call has a context of its
+/// own, so the names of one trace don't outlive it.
+fn parse(path: &Path, schema: Option<&Path>, recursive: bool) -> PyResult<Vec<Event>> {
+    let context = Context::default();
+    let schema = match schema {
+        Some(schema) => {
+            Schema::load(schema, &context).map_err(|e| PyValueError::new_err(format!("{e:#}")))?
+        }
+        None => Schema::builtin(TimeField::Systime),
+    };
+    let options = ParseOptions {
+        recursive,
+        ..ParseOptions::default()
+    };
+    let mut events = blocks::parse::parse_dir(path, &schema, &options, &context, &mut ())
+        .map_err(|e| PyValueError::new_err(e.to_string()))?
+        .events;
+    events.sort_by_key(|event| (Reverse(event.time), event.data, event.op));
+    Ok(events)
+}
+
+/// Parses a trace directory or file into columns `sst_id`, `block_idx`,
+/// `ts_nanos` and `op`, newest first. `schema` is the path of a schema file
+/// like the CLI's `--schema`.
+#[pyfunction]
+#[pyo3(signature = (path, schema = None, recursive = false))]
+fn parse_dir<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    schema: Option<PathBuf>,
+    recursive: bool,
+) -> PyResult<Bound<'py, PyDict>> {
+    let events = parse(&path, schema.as_deref(), recursive)?;
+    let columns = PyDict::new(py);
+    let column = |f: fn(&Event) -> u64| events.iter().map(f).collect::<Vec<_>>();
+    columns.set_item("sst_id", column(|event| event.data.sst))?;
+    columns.set_item("block_idx", column(|event| event.data.blk))?;
+    columns.set_item("ts_nanos", column(|event| ts_nanos(event.time)))?;
+    let ops: Vec<_> = events.iter().map(|event| event.op.name()).collect();
+    columns.set_item("op", ops)?;
+    Ok(columns)
+}
+
+/// Matches each miss of a trace against its block's eviction like `blocks
+/// analyze`, into its duration file's columns `sst_id`, `block_idx`, `delta_ns`
+/// (negative if the eviction came later, `None` without one), `miss_ts` and
+/// `category` (`long`, `short`, `none` or `negative`).
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    short_threshold_secs = 10.0,
+    max_lookback_secs = None,
+    schema = None,
+    recursive = false,
+))]
+fn durations<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    short_threshold_secs: f64,
+    max_lookback_secs: Option<u64>,
+    schema: Option<PathBuf>,
+    recursive: bool,
+) -> PyResult<Bound<'py, PyDict>> {
+    let events = parse(&path, schema.as_deref(), recursive)?;
+    let matching = Matching {
+        lookback: max_lookback_secs.map(Duration::from_secs),
+        short_threshold: Duration::try_from_secs_f64(short_threshold_secs)
+            .map_err(|e| PyValueError::new_err(format!("short_threshold_secs: {e}")))?,
+        ..Matching::default()
+    };
+    let (mut sst_id, mut block_idx, mut ts, mut delta_nanos, mut bucket) =
+        (vec![], vec![], vec![], vec![], vec![]);
+    for (data, time, matched) in match_misses(&events, matching) {
+        let (name, delta) = match matched {
+            Match::Negative(delta) => ("negative", Some(-(delta.as_nanos() as i64))),
+            Match::Evicted(delta, true) => ("short", Some(delta.as_nanos() as i64)),
+            Match::Evicted(delta, false) => ("long", Some(delta.as_nanos() as i64)),
+            Match::None => ("none", None),
+        };
+        sst_id.push(data.sst);
+        block_idx.push(data.blk);
+        ts.push(ts_nanos(time));
+        delta_nanos.push(delta);
+        bucket.push(name);
+    }
+    let columns = PyDict::new(py);
+    columns.set_item("sst_id", sst_id)?;
+    columns.set_item("block_idx", block_idx)?;
+    columns.set_item("delta_ns", delta_nanos)?;
+    columns.set_item("miss_ts", ts)?;
+    columns.set_item("category", bucket)?;
+    Ok(columns)
+}
+
+/// Block cache trace parsing and eviction-to-miss analysis.
+#[pymodule]
+fn blocks_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse_dir, module)?)?;
+    module.add_function(wrap_pyfunction!(durations, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::model::{Event, Op};
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+/// Returns every second, as Unix seconds, whose trailing `window`-second
+/// average miss rate exceeds `threshold`, along with that rate.
+pub fn miss_rate_alerts(events: &[Event], threshold: f64, window: u64) -> Vec<(u64, f64)> {
+    // Seconds without misses are left out, however far apart the rest are.
+    let mut per_sec = BTreeMap::new();
+    for event in events.iter().filter(|event| event.op == Op::Missed) {
+        let sec = event.time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        *per_sec.entry(sec).or_insert(0i64) += 1;
+    }
+    let Some(&last) = per_sec.keys().next_back() else {
+        return vec![];
+    };
+
+    // The trailing sum only changes where a second with misses enters or
+    // leaves the window, so only those seconds are visited.
+    let window = window.max(1);
+    let mut changes = BTreeMap::new();
+    for (&sec, &count) in &per_sec {
+        *changes.entry(sec).or_insert(0) += count;
+        *changes.entry(sec.saturating_add(window)).or_insert(0) -= count;
+    }
+    let mut changes = changes.into_iter().peekable();
+    let mut alerts = vec![];
+    let mut sum = 0;
+    while let Some((start, change)) = changes.next() {
+        sum += change;
+        let end = changes.peek().map_or(last, |&(next, _)| next - 1).min(last);
+        let rate = sum as f64 / window as f64;
+        if rate > threshold {
+            alerts.extend((start..=end).map(|sec| (sec, rate)));
+        }
+    }
+    alerts
+}
@@ -0,0 +1,34 @@
+//! Analyses fed the events one at a time, so that any selection of them runs
+//! in a single pass over the sorted records: a new statistic is a new
+//! [`Analyzer`] rather than another loop over them.
+
+use crate::model::{Context, Event};
+use std::io::{self, Write};
+
+/// One analysis, seeing the events newest first.
+pub trait Analyzer {
+    fn on_event(&mut self, event: &Event);
+
+    /// Writes the result, once every event was seen, showing the events as
+    /// `context` does.
+    fn finish(&mut self, context: &Context, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Feeds each of `events` to every one of `analyzers`, then has them write
+/// their results to `out`, in order.
+pub fn run_analyzers(
+    events: &[Event],
+    analyzers: &mut [Box<dyn Analyzer>],
+    context: &Context,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for event in events {
+        for analyzer in analyzers.iter_mut() {
+            analyzer.on_event(event);
+        }
+    }
+    for analyzer in analyzers {
+        analyzer.finish(context, out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Bursts of evictions, such as those of compaction storms: runs of seconds
+//! each with more evictions than a threshold.
+
+use crate::analysis::topk::{Hot, top_evicted_ssts};
+use crate::model::{Event, Op};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One run of seconds over the eviction rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Burst {
+    /// The start of its first second.
+    pub start: SystemTime,
+    /// The end of its last second.
+    pub end: SystemTime,
+    pub evictions: u64,
+    /// The most evictions within one of its seconds.
+    pub peak: u64,
+    /// The SSTs with the most evictions in it.
+    pub ssts: Vec<Hot<u64>>,
+}
+
+/// Finds the runs of whole Unix seconds with more than `rate` evictions each
+/// that last longer than `min`, oldest first, with their `top` SSTs.
+pub fn find_bursts(events: &[Event], rate: u64, min: Duration, top: usize) -> Vec<Burst> {
+    let secs: Vec<u64> = (events.iter())
+        .filter(|event| event.op == Op::Evicted)
+        .map(|event| event.time.duration_since(UNIX_EPOCH).unwrap().as_secs())
+        .collect();
+    let (Some(&first), Some(&last)) = (secs.iter().min(), secs.iter().max()) else {
+        return vec![];
+    };
+    let mut per_sec = vec![0u64; (last - first + 1) as usize];
+    for sec in secs {
+        per_sec[(sec - first) as usize] += 1;
+    }
+
+    let mut bursts = vec![];
+    let mut i = 0;
+    while i < per_sec.len() {
+        if per_sec[i] <= rate {
+            i += 1;
+            continue;
+        }
+        let run = i..(i..per_sec.len())
+            .find(|&j| per_sec[j] <= rate)
+            .unwrap_or(per_sec.len());
+        i = run.end;
+        if Duration::from_secs(run.len() as u64) <= min {
+            continue;
+        }
+        let start = UNIX_EPOCH + Duration::from_secs(first + run.start as u64);
+        let end = UNIX_EPOCH + Duration::from_secs(first + run.end as u64);
+        // Newest first, so the burst's events are the ones between these.
+        let newest = events.partition_point(|event| event.time >= end);
+        let oldest = events.partition_point(|event| event.time >= start);
+        bursts.push(Burst {
+            start,
+            end,
+            evictions: per_sec[run.clone()].iter().sum(),
+            peak: per_sec[run].iter().copied().max().unwrap_or_default(),
+            ssts: top_evicted_ssts(&events[newest..oldest], top),
+        });
+    }
+    bursts
+}
@@ -0,0 +1,87 @@
+//! Eviction and miss volumes in bytes, for sizing a cache in bytes rather
+//! than blocks.
+
+use crate::analysis::durations::{Match, Matching, match_misses};
+use crate::model::{Context, Event, Op};
+use std::ops::AddAssign;
+
+/// Bytes evicted and missed, the missed ones split like
+/// [`crate::analysis::durations::Counts`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Volumes {
+    pub evicted: u64,
+    pub missed: u64,
+    pub long: u64,
+    /// Bytes read again within the short threshold of their eviction.
+    pub short: u64,
+    pub none: u64,
+    /// Evictions and misses of blocks of unknown size, left out of the sums.
+    pub unknown: u64,
+}
+
+impl AddAssign for Volumes {
+    fn add_assign(&mut self, rhs: Self) {
+        self.evicted += rhs.evicted;
+        self.missed += rhs.missed;
+        self.long += rhs.long;
+        self.short += rhs.short;
+        self.none += rhs.none;
+        self.unknown += rhs.unknown;
+    }
+}
+
+/// The size of the blocks of SST `sst`: its file size over its block count
+/// from [`crate::sst_meta`], or `default` without both.
+pub fn block_size(sst: u64, default: Option<u64>, context: &Context) -> Option<u64> {
+    let meta = context.sst_meta(sst);
+    let size = meta.and_then(|meta| Some(meta.file_size? / meta.block_count.filter(|&n| n > 0)?));
+    size.or(default)
+}
+
+/// The [`Volumes`] of `events`, matching misses like [`match_misses`] and
+/// sizing blocks by [`block_size`].
+pub fn volumes(
+    events: &[Event],
+    matching: Matching,
+    default: Option<u64>,
+    context: &Context,
+) -> Volumes {
+    let mut volumes = Volumes::default();
+    for event in events {
+        if !matches!(event.op, Op::Evicted | Op::Missed) {
+            continue;
+        }
+        match block_size(event.data.sst, default, context) {
+            Some(size) if event.op == Op::Evicted => volumes.evicted += size,
+            Some(size) => volumes.missed += size,
+            None => volumes.unknown += 1,
+        }
+    }
+    for (data, _, matched) in match_misses(events, matching) {
+        let Some(size) = block_size(data.sst, default, context) else {
+            continue;
+        };
+        match matched {
+            Match::Evicted(_, true) => volumes.short += size,
+            Match::Evicted(_, false) => volumes.long += size,
+            Match::None => volumes.none += size,
+            Match::Negative(_) => {}
+        }
+    }
+    volumes
+}
+
+/// `bytes` in the largest binary unit keeping it at least 1, e.g. `1.50 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.2} {}", UNITS[unit])
+}
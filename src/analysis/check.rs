@@ -0,0 +1,292 @@
+//! Sanity checks of a trace, for problems that otherwise only show up as
+//! odd numbers in the reports.
+
+use crate::analysis::summary::summarize;
+use crate::model::{Context, Data, Event, Op};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// Shortest main time range [`outliers`] measures the distance to it by.
+const MIN_RANGE: Duration = Duration::from_secs(1);
+
+/// Times earlier than the one before in one input file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Backwards {
+    /// Steps back by more than the tolerance.
+    pub steps: u64,
+    pub largest: Duration,
+}
+
+/// The steps back in time of `events`, in the order they were parsed from
+/// one file, by more than `tolerance`.
+pub fn backwards(events: &[Event], tolerance: Duration) -> Backwards {
+    let mut backwards = Backwards::default();
+    for pair in events.windows(2) {
+        if let Ok(step) = pair[0].time.duration_since(pair[1].time)
+            && step > tolerance
+        {
+            backwards.steps += 1;
+            backwards.largest = backwards.largest.max(step);
+        }
+    }
+    backwards
+}
+
+/// Misses without an eviction or insertion of their block before them.
+/// Those of blocks first accessed when the trace starts are among them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Unexplained {
+    pub misses: u64,
+    pub unexplained: u64,
+    /// The earliest one.
+    pub example: Option<(Data, SystemTime)>,
+}
+
+/// The [`Unexplained`] misses of `events`, which are sorted newest first.
+pub fn unexplained_misses(events: &[Event]) -> Unexplained {
+    let mut unexplained = Unexplained::default();
+    let mut cached: HashSet<Data> = HashSet::new();
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Evicted | Op::Inserted => {
+                cached.insert(event.data);
+            }
+            Op::Missed => {
+                unexplained.misses += 1;
+                if !cached.contains(&event.data) {
+                    unexplained.unexplained += 1;
+                    unexplained.example.get_or_insert((event.data, event.time));
+                }
+            }
+            Op::Hit => {}
+        }
+    }
+    unexplained
+}
+
+/// Events far outside the main time range of a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outliers {
+    /// From the 1st to the 99th percentile of the event times.
+    pub range: (SystemTime, SystemTime),
+    /// Events further before or after the range than it is long.
+    pub before: u64,
+    pub after: u64,
+    /// The earliest and latest event.
+    pub extremes: (SystemTime, SystemTime),
+}
+
+/// The [`Outliers`] of `events`, which are sorted newest first, or `None`
+/// without any.
+pub fn outliers(events: &[Event]) -> Option<Outliers> {
+    let (latest, earliest) = (events.first()?.time, events.last()?.time);
+    // Newest first, so the 99th percentile comes first.
+    let at = |quantile: f64| events[((events.len() - 1) as f64 * quantile) as usize].time;
+    let range = (at(0.99), at(0.01));
+    let far = (range.1.duration_since(range.0).unwrap_or_default()).max(MIN_RANGE);
+    let before = (events.iter())
+        .filter(|event| {
+            range
+                .0
+                .duration_since(event.time)
+                .is_ok_and(|gap| gap > far)
+        })
+        .count() as u64;
+    let after = (events.iter())
+        .filter(|event| {
+            event
+                .time
+                .duration_since(range.1)
+                .is_ok_and(|gap| gap > far)
+        })
+        .count() as u64;
+    Some(Outliers {
+        range,
+        before,
+        after,
+        extremes: (earliest, latest),
+    })
+}
+
+/// Counters the cache exported itself, such as foyer's statistics, for the
+/// same time as the trace. Any of them may be missing.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ExpectedStats {
+    #[serde(alias = "evictions", alias = "evicted")]
+    pub evict: Option<u64>,
+    #[serde(alias = "misses", alias = "missed")]
+    pub miss: Option<u64>,
+    #[serde(alias = "inserts", alias = "insertions", alias = "inserted")]
+    pub insert: Option<u64>,
+    #[serde(alias = "hits")]
+    pub hit: Option<u64>,
+}
+
+/// Loads the [`ExpectedStats`] from a JSON object with any of their fields.
+pub fn load_expected_stats(path: &str) -> Result<ExpectedStats, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("{path}: {e}"))
+}
+
+/// One counter of the trace next to the one the cache exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counter {
+    pub op: Op,
+    pub traced: u64,
+    pub expected: u64,
+}
+
+impl Counter {
+    /// How far the traced count is off, in percent of the expected one.
+    pub fn off(&self) -> f64 {
+        let difference = self.traced as f64 - self.expected as f64;
+        if self.expected == 0 {
+            if self.traced == 0 { 0.0 } else { f64::INFINITY }
+        } else {
+            difference * 100.0 / self.expected as f64
+        }
+    }
+}
+
+/// The counters of `events` that `expected` has too. Dropped log lines and
+/// truncated files show up as fewer traced than expected.
+pub fn counters(events: &[Event], expected: &ExpectedStats) -> Vec<Counter> {
+    let summary = summarize(events);
+    [
+        (Op::Evicted, summary.evicted, expected.evict),
+        (Op::Missed, summary.missed, expected.miss),
+        (Op::Inserted, summary.inserted, expected.insert),
+        (Op::Hit, summary.hit, expected.hit),
+    ]
+    .into_iter()
+    .filter_map(|(op, traced, expected)| {
+        Some(Counter {
+            op,
+            traced,
+            expected: expected?,
+        })
+    })
+    .collect()
+}
+
+/// Everything [`write_check`] reports.
+#[derive(Debug, Default)]
+pub struct Check {
+    /// Per input file with any steps back, in parse order.
+    pub backwards: Vec<(String, Backwards)>,
+    pub tolerance: Duration,
+    pub unexplained: Unexplained,
+    pub outliers: Option<Outliers>,
+    /// File, line and op of each section banner without entries.
+    pub empty_sections: Vec<(String, u64, Op)>,
+    /// Against the expected stats, if given.
+    pub counters: Vec<Counter>,
+    /// How far in percent counters may be off without being a problem.
+    pub counter_tolerance: f64,
+}
+
+impl Check {
+    /// Number of problems found.
+    pub fn anomalies(&self) -> u64 {
+        let backwards: u64 = self.backwards.iter().map(|(_, b)| b.steps).sum();
+        let outliers = (self.outliers.as_ref()).map_or(0, |o| o.before + o.after);
+        let counters = (self.counters.iter())
+            .filter(|counter| counter.off().abs() > self.counter_tolerance)
+            .count() as u64;
+        backwards
+            + self.unexplained.unexplained
+            + outliers
+            + self.empty_sections.len() as u64
+            + counters
+    }
+}
+
+/// Writes one section per kind of problem in `check`, and the total.
+pub fn write_check(check: &Check, context: &Context, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        writer,
+        "Times going back within a file by more than {:?}: {}",
+        check.tolerance,
+        check.backwards.iter().map(|(_, b)| b.steps).sum::<u64>()
+    )?;
+    for (file, backwards) in &check.backwards {
+        writeln!(
+            writer,
+            "    {file}: {}, largest: {:?}",
+            backwards.steps, backwards.largest
+        )?;
+    }
+
+    let Unexplained {
+        misses,
+        unexplained,
+        example,
+    } = &check.unexplained;
+    write!(
+        writer,
+        "Misses without an earlier eviction or insertion: {unexplained} of {misses} ({:.2}%)",
+        *unexplained as f64 * 100.0 / (*misses).max(1) as f64
+    )?;
+    match example {
+        Some((data, time)) => writeln!(
+            writer,
+            ", earliest: {} at {}",
+            context.data(*data),
+            context.format_time(*time)
+        )?,
+        None => writeln!(writer)?,
+    }
+
+    if let Some(outliers) = &check.outliers {
+        let (start, end) = outliers.range;
+        writeln!(
+            writer,
+            "Main time range (p1 to p99): {} to {}",
+            context.format_time(start),
+            context.format_time(end)
+        )?;
+        let (earliest, latest) = outliers.extremes;
+        writeln!(
+            writer,
+            "Events far before it: {}, earliest: {}",
+            outliers.before,
+            context.format_time(earliest)
+        )?;
+        writeln!(
+            writer,
+            "Events far after it: {}, latest: {}",
+            outliers.after,
+            context.format_time(latest)
+        )?;
+    }
+
+    writeln!(writer, "Empty sections: {}", check.empty_sections.len())?;
+    for (file, line, op) in &check.empty_sections {
+        writeln!(writer, "    {file}, line {line}: {}", op.name())?;
+    }
+    if !check.counters.is_empty() {
+        writeln!(
+            writer,
+            "Counters off from the expected stats by more than {}%: {}",
+            check.counter_tolerance,
+            (check.counters.iter())
+                .filter(|counter| counter.off().abs() > check.counter_tolerance)
+                .count()
+        )?;
+        for counter in &check.counters {
+            writeln!(
+                writer,
+                "    {}: traced {}, expected {} ({:+.2}%)",
+                counter.op.name(),
+                counter.traced,
+                counter.expected,
+                counter.off()
+            )?;
+        }
+    }
+    writeln!(writer, "Anomalies: {}", check.anomalies())
+}
@@ -0,0 +1,49 @@
+use crate::model::{Context, Event};
+use sha2::{Digest, Sha256};
+use std::time::UNIX_EPOCH;
+
+/// SHA-256 over the events in canonical `(time, sst, blk, op, source, kind)`
+/// order, so the result only depends on the logical content of the run.
+/// Sources and kinds of keys are only hashed if there are any, by their
+/// name in `context`.
+pub fn checksum(events: &[Event], context: &Context) -> String {
+    let mut canonical: Vec<_> = events
+        .iter()
+        .map(|event| {
+            let nanos = event.time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+            let source = context.source_name(event.data.source);
+            let kind = context.kind_name(event.data.kind);
+            (
+                nanos,
+                event.data.sst,
+                event.data.blk,
+                event.op as u8,
+                source,
+                kind,
+            )
+        })
+        .collect();
+    canonical.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for (nanos, sst, blk, op, source, kind) in canonical {
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(sst.to_le_bytes());
+        hasher.update(blk.to_le_bytes());
+        hasher.update([op]);
+        if let Some(source) = source {
+            hasher.update(source.as_bytes());
+            hasher.update([0]);
+        }
+        if let Some(kind) = kind {
+            hasher.update(b"kind:");
+            hasher.update(kind.as_bytes());
+            hasher.update([0]);
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
@@ -0,0 +1,45 @@
+use crate::model::{Context, Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// Reports blocks evicted twice in a row without a miss re-admitting them in
+/// between, which points at a logging or cache bug.
+pub fn write_duplicate_evictions(
+    events: &[Event],
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut last: HashMap<Data, (Op, SystemTime)> = HashMap::new();
+    // Per block: number of repeated evictions and the first example.
+    let mut found: HashMap<Data, (u64, SystemTime, SystemTime)> = HashMap::new();
+
+    // Events are sorted newest first, walk them oldest first.
+    for event in events.iter().rev() {
+        if let Some((Op::Evicted, prev)) = last.insert(event.data, (event.op, event.time))
+            && event.op == Op::Evicted
+        {
+            found.entry(event.data).or_insert((0, prev, event.time)).0 += 1;
+        }
+    }
+
+    let mut found: Vec<_> = found.into_iter().collect();
+    found.sort_by_key(|&(data, (count, ..))| (std::cmp::Reverse(count), data));
+
+    let mut total = 0;
+    for (data, (count, prev, next)) in &found {
+        total += count;
+        writeln!(
+            writer,
+            "{}, duplicate evictions: {count}, example: {} -> {}",
+            context.data(*data),
+            context.format_time(*prev),
+            context.format_time(*next),
+        )?;
+    }
+    writeln!(
+        writer,
+        "blocks: {}, duplicate evictions: {total}",
+        found.len()
+    )
+}
@@ -0,0 +1,58 @@
+use crate::model::{Data, Event, Op};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The `(op, block)` keys seen in only one of two event sets.
+#[derive(Debug, Default)]
+pub struct BlockDiff {
+    pub only_ours: Vec<(Op, Data)>,
+    pub only_theirs: Vec<(Op, Data)>,
+}
+
+pub fn block_diff(ours: &[Event], theirs: &[Event]) -> BlockDiff {
+    let keys = |events: &[Event]| -> BTreeSet<(Op, Data)> {
+        events.iter().map(|event| (event.op, event.data)).collect()
+    };
+    let (ours, theirs) = (keys(ours), keys(theirs));
+
+    BlockDiff {
+        only_ours: ours.difference(&theirs).copied().collect(),
+        only_theirs: theirs.difference(&ours).copied().collect(),
+    }
+}
+
+/// How often one key was missed in two traces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MissDiff<K> {
+    pub key: K,
+    pub ours: u64,
+    pub theirs: u64,
+}
+
+impl<K> MissDiff<K> {
+    /// Misses gained in theirs, negative if they lost some.
+    pub fn change(&self) -> i64 {
+        self.theirs as i64 - self.ours as i64
+    }
+}
+
+/// Miss counts per `key` in both traces, largest change first.
+pub fn miss_diff<K: Copy + Ord>(
+    ours: &[Event],
+    theirs: &[Event],
+    key: impl Fn(&Event) -> K,
+) -> Vec<MissDiff<K>> {
+    let mut counts: BTreeMap<K, (u64, u64)> = BTreeMap::new();
+    for event in ours.iter().filter(|event| event.op == Op::Missed) {
+        counts.entry(key(event)).or_default().0 += 1;
+    }
+    for event in theirs.iter().filter(|event| event.op == Op::Missed) {
+        counts.entry(key(event)).or_default().1 += 1;
+    }
+    let mut diff: Vec<_> = counts
+        .into_iter()
+        .map(|(key, (ours, theirs))| MissDiff { key, ours, theirs })
+        .collect();
+    diff.sort_by_key(|diff| (Reverse(diff.change().unsigned_abs()), diff.key));
+    diff
+}
@@ -0,0 +1,530 @@
+//! Eviction-to-miss durations, the tool's main analysis.
+
+use crate::analysis::analyzer::Analyzer;
+use crate::analysis::stats::percentile;
+use crate::format::{Encoder, Format, ts_nanos};
+use crate::model::{Context, Data, Event, Op};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::ops::{AddAssign, SubAssign};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How misses are matched against evictions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matching {
+    /// Ignore evictions further back than this before a miss.
+    pub lookback: Option<Duration>,
+    /// Misses this soon after their eviction count as short.
+    pub short_threshold: Duration,
+    /// Evictions less than this long after a miss count as right at it, a
+    /// short delta of zero rather than a negative one. Those are mostly
+    /// entries logged out of order.
+    pub negative_epsilon: Duration,
+}
+
+impl Default for Matching {
+    fn default() -> Self {
+        Self {
+            lookback: None,
+            short_threshold: Duration::from_secs(10),
+            negative_epsilon: Duration::ZERO,
+        }
+    }
+}
+
+impl Matching {
+    /// Matches a miss at `miss` against the ascending eviction times of its
+    /// block: the latest eviction at or before the miss, or, if there is none,
+    /// the first one after it as a negative delta, or a zero one within the
+    /// negative epsilon.
+    pub fn match_miss(&self, evictions: &[SystemTime], miss: SystemTime) -> Match {
+        let preceding = evictions.partition_point(|&evicted| evicted <= miss);
+        match preceding.checked_sub(1).map(|i| evictions[i]) {
+            Some(evicted) => {
+                let delta = miss.duration_since(evicted).unwrap();
+                // Evictions further back than the lookback window don't count as a match.
+                if self.lookback.is_some_and(|max| delta > max) {
+                    Match::None
+                } else {
+                    Match::Evicted(delta, delta < self.short_threshold)
+                }
+            }
+            None => match evictions.first() {
+                Some(evicted) => match evicted.duration_since(miss).unwrap() {
+                    delta if delta < self.negative_epsilon => {
+                        Match::Evicted(Duration::ZERO, Duration::ZERO < self.short_threshold)
+                    }
+                    delta => Match::Negative(delta),
+                },
+                None => Match::None,
+            },
+        }
+    }
+}
+
+/// Number of misses in each duration bucket. Misses only matched to an
+/// eviction that happened after them are not counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Counts {
+    pub long: u64,
+    pub short: u64,
+    /// Misses without a matching eviction.
+    pub none: u64,
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.long += rhs.long;
+        self.short += rhs.short;
+        self.none += rhs.none;
+    }
+}
+
+impl SubAssign for Counts {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.long -= rhs.long;
+        self.short -= rhs.short;
+        self.none -= rhs.none;
+    }
+}
+
+/// Per block: number of premature misses and the smallest delta seen.
+pub type Contention = HashMap<Data, (u64, Duration)>;
+
+/// A miss with the stable field names and order of the structured formats.
+/// Every field is always written, empty when the feature filling it is off.
+#[derive(Debug, Serialize)]
+struct DurationRecord<'a> {
+    sst_id: u64,
+    block_idx: u64,
+    /// Negative when the matched eviction came after the miss.
+    #[serde(rename = "delta_ns")]
+    delta_nanos: Option<i64>,
+    /// When the miss happened, in nanoseconds since the Unix epoch.
+    #[serde(rename = "miss_ts")]
+    ts_nanos: u64,
+    /// `long`, `short`, `none` or `negative`.
+    #[serde(rename = "category")]
+    bucket: &'static str,
+    /// With --per-file-matching.
+    file: Option<&'a str>,
+    /// With --source-regex or --source-from-file.
+    source: Option<Arc<str>>,
+    /// With more kinds of keys than data blocks.
+    kind: Option<Arc<str>>,
+    /// With --sst-meta.
+    table_id: Option<u64>,
+    level: Option<u32>,
+    file_size: Option<u64>,
+    block_count: Option<u64>,
+}
+
+/// How a miss was matched against its block's eviction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Match {
+    /// The delta since the eviction, and whether it counts as short.
+    Evicted(Duration, bool),
+    /// The eviction came this long after the miss.
+    Negative(Duration),
+    None,
+}
+
+/// Which of the matched misses a [`DurationWriter`] writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cap {
+    /// Only the first this many.
+    First(usize),
+    /// Only the last this many.
+    Last(usize),
+}
+
+/// Writes the matched misses of [`write_durations`] in a [`Format`].
+pub struct DurationWriter<'a, W> {
+    writer: W,
+    context: &'a Context,
+    encoder: Option<Encoder>,
+    buf: Vec<u8>,
+    file: Option<String>,
+    cap: Option<Cap>,
+    /// Misses seen since the last [`DurationWriter::set_file`].
+    rows: usize,
+    /// The latest misses with [`Cap::Last`], written before any other
+    /// output.
+    pending: VecDeque<(Data, SystemTime, Match)>,
+}
+
+impl<'a, W: Write> DurationWriter<'a, W> {
+    pub fn new(writer: W, format: Format, context: &'a Context) -> Self {
+        Self {
+            writer,
+            context,
+            encoder: Encoder::new(format),
+            buf: vec![],
+            file: None,
+            cap: None,
+            rows: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Only writes some of the misses, of each file with
+    /// [`DurationWriter::set_file`].
+    pub fn with_cap(self, cap: Option<Cap>) -> Self {
+        Self { cap, ..self }
+    }
+
+    /// Whether the output is the human readable layout, which may be
+    /// interleaved with more text through [`DurationWriter::text`].
+    pub fn is_text(&self) -> bool {
+        self.encoder.is_none()
+    }
+
+    /// The underlying writer, for extra lines of the text layout.
+    pub fn text(&mut self) -> io::Result<&mut W> {
+        self.flush_pending()?;
+        Ok(&mut self.writer)
+    }
+
+    /// Tags the following rows of structured formats with their input file.
+    pub fn set_file(&mut self, file: Option<String>) -> io::Result<()> {
+        self.flush_pending()?;
+        self.file = file;
+        self.rows = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, data: Data, miss: SystemTime, matched: Match) -> io::Result<()> {
+        self.rows += 1;
+        match self.cap {
+            Some(Cap::First(n)) if self.rows > n => Ok(()),
+            Some(Cap::Last(n)) => {
+                if self.pending.len() == n {
+                    self.pending.pop_front();
+                }
+                if n > 0 {
+                    self.pending.push_back((data, miss, matched));
+                }
+                Ok(())
+            }
+            _ => self.emit(data, miss, matched),
+        }
+    }
+
+    fn flush_pending(&mut self) -> io::Result<()> {
+        while let Some((data, miss, matched)) = self.pending.pop_front() {
+            self.emit(data, miss, matched)?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, data: Data, miss: SystemTime, matched: Match) -> io::Result<()> {
+        let Some(encoder) = &mut self.encoder else {
+            let (data, miss) = (self.context.data(data), self.context.format_time(miss));
+            return match matched {
+                Match::Negative(duration) => writeln!(
+                    self.writer,
+                    "{data}, delta: -{duration:?}, miss time: {miss}"
+                ),
+                Match::Evicted(duration, short) => {
+                    let suffix = if short { "!!!!!!!!!!" } else { "" };
+                    writeln!(
+                        self.writer,
+                        "{data}, delta: {duration:?}, miss time: {miss} {suffix}"
+                    )
+                }
+                Match::None => writeln!(
+                    self.writer,
+                    "{data}, miss time: {miss}, No evicted time found"
+                ),
+            };
+        };
+
+        let (bucket, delta_nanos) = match matched {
+            Match::Negative(duration) => ("negative", Some(-(duration.as_nanos() as i64))),
+            Match::Evicted(duration, true) => ("short", Some(duration.as_nanos() as i64)),
+            Match::Evicted(duration, false) => ("long", Some(duration.as_nanos() as i64)),
+            Match::None => ("none", None),
+        };
+        let meta = self.context.sst_meta(data.sst);
+        let record = DurationRecord {
+            sst_id: data.sst,
+            block_idx: data.blk,
+            delta_nanos,
+            ts_nanos: ts_nanos(miss),
+            bucket,
+            file: self.file.as_deref(),
+            source: self.context.source_name(data.source),
+            kind: (self.context.has_kinds()).then(|| self.context.kind_label(data.kind)),
+            table_id: meta.and_then(|meta| meta.table_id),
+            level: meta.and_then(|meta| meta.level),
+            file_size: meta.and_then(|meta| meta.file_size),
+            block_count: meta.and_then(|meta| meta.block_count),
+        };
+        self.buf.clear();
+        encoder.encode(&record, &mut self.buf)?;
+        self.writer.write_all(&self.buf)
+    }
+
+    /// Ends the output, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_pending()?;
+        if let Some(encoder) = &self.encoder {
+            self.buf.clear();
+            encoder.finish(&mut self.buf);
+            self.writer.write_all(&self.buf)?;
+        }
+        Ok(self.writer)
+    }
+}
+
+/// Matches each miss in `events` with [`Matching::match_miss`], yielding the
+/// block, the miss time and the match.
+pub fn match_misses(
+    events: &[Event],
+    matching: Matching,
+) -> impl Iterator<Item = (Data, SystemTime, Match)> {
+    let mut evicted_times: HashMap<Data, Vec<SystemTime>> = HashMap::new();
+
+    // Store evicted times
+    for event in events {
+        if event.op == Op::Evicted {
+            evicted_times
+                .entry(event.data)
+                .or_default()
+                .push(event.time);
+        }
+    }
+    for times in evicted_times.values_mut() {
+        times.sort_unstable();
+    }
+
+    events.iter().filter(|event| event.op == Op::Missed).map(
+        move |&Event { data, time, .. }| {
+            let evictions = evicted_times.get(&data).map_or(&[][..], Vec::as_slice);
+            (data, time, matching.match_miss(evictions, time))
+        },
+    )
+}
+
+/// Matches each miss in `events` against its block's nearest preceding
+/// eviction and writes the delta, recording premature misses in `contention`.
+pub fn write_durations(
+    events: &[Event],
+    matching: Matching,
+    duration_writer: &mut DurationWriter<'_, impl Write>,
+    contention: &mut Contention,
+) -> io::Result<Counts> {
+    let mut counts = Counts::default();
+
+    for (data, time, matched) in match_misses(events, matching) {
+        match matched {
+            Match::Negative(_) => {}
+            Match::Evicted(duration, true) => {
+                counts.short += 1;
+                let (count, min) = contention.entry(data).or_insert((0, duration));
+                *count += 1;
+                *min = (*min).min(duration);
+            }
+            Match::Evicted(_, false) => counts.long += 1,
+            Match::None => counts.none += 1,
+        }
+        duration_writer.write(data, time, matched)?;
+    }
+
+    Ok(counts)
+}
+
+/// The long/short/none counts of the misses in `events` and the ascending
+/// deltas of those matched to an eviction before them.
+pub fn intervals(events: &[Event], matching: Matching) -> (Counts, Vec<Duration>) {
+    let mut counts = Counts::default();
+    let mut deltas = vec![];
+    for (_, _, matched) in match_misses(events, matching) {
+        count_match(matched, &mut counts, &mut deltas);
+    }
+    deltas.sort();
+    (counts, deltas)
+}
+
+/// Counts `matched` in `counts`, and its delta in `deltas` if it has one.
+fn count_match(matched: Match, counts: &mut Counts, deltas: &mut Vec<Duration>) {
+    match matched {
+        Match::Evicted(delta, short) => {
+            if short {
+                counts.short += 1;
+            } else {
+                counts.long += 1;
+            }
+            deltas.push(delta);
+        }
+        Match::Negative(_) => {}
+        Match::None => counts.none += 1,
+    }
+}
+
+/// [`intervals`] of events seen one at a time, for the [`Analyzer`]s that
+/// need the misses matched.
+#[derive(Debug, Default)]
+pub struct MissMatcher {
+    matching: Matching,
+    /// Eviction times of each block, newest first until finished.
+    evictions: HashMap<Data, Vec<SystemTime>>,
+    misses: Vec<(Data, SystemTime)>,
+}
+
+impl MissMatcher {
+    pub fn new(matching: Matching) -> Self {
+        Self {
+            matching,
+            ..Self::default()
+        }
+    }
+
+    pub fn on_event(&mut self, event: &Event) {
+        match event.op {
+            Op::Evicted => (self.evictions.entry(event.data).or_default()).push(event.time),
+            Op::Missed => self.misses.push((event.data, event.time)),
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+
+    /// Matches the misses seen, once every event was.
+    pub fn finish(&mut self) -> (Counts, Vec<Duration>) {
+        for times in self.evictions.values_mut() {
+            times.sort_unstable();
+        }
+        let mut counts = Counts::default();
+        let mut deltas = vec![];
+        for &(data, miss) in &self.misses {
+            let evictions = self.evictions.get(&data).map_or(&[][..], Vec::as_slice);
+            count_match(
+                self.matching.match_miss(evictions, miss),
+                &mut counts,
+                &mut deltas,
+            );
+        }
+        (counts, deltas)
+    }
+}
+
+/// [`intervals`] as an [`Analyzer`]: writes the long/short/none counts and
+/// percentiles of the deltas.
+#[derive(Debug, Default)]
+pub struct DurationsAnalyzer {
+    matcher: MissMatcher,
+}
+
+impl DurationsAnalyzer {
+    pub fn new(matching: Matching) -> Self {
+        Self {
+            matcher: MissMatcher::new(matching),
+        }
+    }
+}
+
+impl Analyzer for DurationsAnalyzer {
+    fn on_event(&mut self, event: &Event) {
+        self.matcher.on_event(event);
+    }
+
+    fn finish(&mut self, _: &Context, out: &mut dyn Write) -> io::Result<()> {
+        let (counts, mut deltas) = self.matcher.finish();
+        let Counts { long, short, none } = counts;
+        writeln!(out, "long: {long}, short: {short}, none: {none}")?;
+        deltas.sort();
+        if let Some(max) = deltas.last() {
+            writeln!(
+                out,
+                "deltas: count: {}, p50: {:?}, p90: {:?}, p99: {:?}, max: {max:?}",
+                deltas.len(),
+                percentile(&deltas, 50.0),
+                percentile(&deltas, 90.0),
+                percentile(&deltas, 99.0),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How long each evicted block was cached, from its latest insert before the
+/// eviction. With `infer_inserts`, a miss since the last eviction counts as
+/// the insert of a block for traces that don't log inserts. Evictions of
+/// blocks not inserted since their last one are left out.
+pub fn residencies(events: &[Event], infer_inserts: bool) -> Vec<Duration> {
+    let mut inserted: HashMap<Data, SystemTime> = HashMap::new();
+    let mut residencies = vec![];
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Inserted => {
+                inserted.insert(event.data, event.time);
+            }
+            Op::Evicted => {
+                if let Some(time) = inserted.remove(&event.data) {
+                    residencies.push(event.time.duration_since(time).unwrap_or_default());
+                }
+            }
+            // The block is inserted right after its first miss.
+            Op::Missed if infer_inserts => {
+                inserted.entry(event.data).or_insert(event.time);
+            }
+            Op::Missed | Op::Hit => {}
+        }
+    }
+    residencies
+}
+
+/// Long/short/none counts kept up to date as files are parsed. They match
+/// what [`write_durations`] reports for the events seen so far.
+#[derive(Debug, Default)]
+pub struct RunningSummary {
+    blocks: HashMap<Data, BlockSummary>,
+    counts: Counts,
+}
+
+#[derive(Debug, Default)]
+struct BlockSummary {
+    /// Eviction times in ascending order.
+    evictions: Vec<SystemTime>,
+    misses: Vec<SystemTime>,
+    counts: Counts,
+}
+
+impl RunningSummary {
+    pub fn counts(&self) -> Counts {
+        self.counts
+    }
+
+    pub fn update(&mut self, events: &[Event], matching: Matching) {
+        let mut touched = vec![];
+        for event in events {
+            let block = self.blocks.entry(event.data).or_default();
+            match event.op {
+                Op::Evicted => block.evictions.push(event.time),
+                Op::Missed => block.misses.push(event.time),
+                Op::Inserted | Op::Hit => {}
+            }
+            touched.push(event.data);
+        }
+        touched.sort_unstable();
+        touched.dedup();
+
+        // Only blocks with new events need their misses reclassified.
+        for data in touched {
+            let block = self.blocks.get_mut(&data).unwrap();
+            block.evictions.sort_unstable();
+            self.counts -= block.counts;
+            block.counts = Counts::default();
+            for &miss in &block.misses {
+                match matching.match_miss(&block.evictions, miss) {
+                    Match::Evicted(_, true) => block.counts.short += 1,
+                    Match::Evicted(_, false) => block.counts.long += 1,
+                    Match::Negative(_) => {}
+                    Match::None => block.counts.none += 1,
+                }
+            }
+            self.counts += block.counts;
+        }
+    }
+}
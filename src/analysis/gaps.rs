@@ -0,0 +1,110 @@
+use crate::analysis::durations::{Counts, Match, Matching, match_misses};
+use crate::model::{Context, Data, Event, Op};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// Returns `(start, end)` of every period longer than `min` without events.
+pub fn find_gaps(events: &[Event], min: Duration) -> Vec<(SystemTime, SystemTime)> {
+    // Events are sorted newest first.
+    events
+        .windows(2)
+        .rev()
+        .filter_map(|pair| {
+            let (end, start) = (pair[0].time, pair[1].time);
+            (end.duration_since(start).unwrap_or_default() > min).then_some((start, end))
+        })
+        .collect()
+}
+
+/// A stretch of events without gaps, see [`phases`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub events: u64,
+    /// Distinct blocks with any event.
+    pub blocks: u64,
+    pub evictions: u64,
+    pub misses: u64,
+    /// Of the misses, matched against evictions of any phase.
+    pub counts: Counts,
+}
+
+impl Phase {
+    /// Misses per second, over at least a second.
+    pub fn miss_rate(&self) -> f64 {
+        let secs = self.end.duration_since(self.start).unwrap_or_default();
+        self.misses as f64 / secs.as_secs_f64().max(1.0)
+    }
+}
+
+/// Splits `events` at every gap longer than `min`, returning the phases in
+/// between oldest first.
+pub fn phases(events: &[Event], min: Duration, matching: Matching) -> Vec<Phase> {
+    let mut phases: Vec<Phase> = vec![];
+    let mut blocks: HashSet<Data> = HashSet::new();
+    // Events are sorted newest first, walk them oldest first.
+    for event in events.iter().rev() {
+        let phase = match phases.last_mut() {
+            Some(phase) if event.time.duration_since(phase.end).unwrap_or_default() <= min => phase,
+            _ => {
+                blocks.clear();
+                phases.push(Phase {
+                    start: event.time,
+                    end: event.time,
+                    events: 0,
+                    blocks: 0,
+                    evictions: 0,
+                    misses: 0,
+                    counts: Counts::default(),
+                });
+                phases.last_mut().unwrap()
+            }
+        };
+        phase.end = event.time;
+        phase.events += 1;
+        phase.blocks += blocks.insert(event.data) as u64;
+        match event.op {
+            Op::Evicted => phase.evictions += 1,
+            Op::Missed => phase.misses += 1,
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+    for (_, time, matched) in match_misses(events, matching) {
+        let i = phases.partition_point(|phase| phase.start <= time) - 1;
+        let counts = &mut phases[i].counts;
+        match matched {
+            Match::Evicted(_, true) => counts.short += 1,
+            Match::Evicted(_, false) => counts.long += 1,
+            Match::None => counts.none += 1,
+            Match::Negative(_) => {}
+        }
+    }
+    phases
+}
+
+/// Writes one line of stats per phase of [`phases`].
+pub fn write_phases(
+    phases: &[Phase],
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    for (i, phase) in phases.iter().enumerate() {
+        let Counts { long, short, none } = phase.counts;
+        writeln!(
+            writer,
+            "phase {}: {} to {} ({:?}), events: {}, blocks: {}, evictions: {}, misses: {}, miss rate: {:.2}/s, long: {long}, short: {short}, none: {none}",
+            i + 1,
+            context.format_time(phase.start),
+            context.format_time(phase.end),
+            phase.end.duration_since(phase.start).unwrap_or_default(),
+            phase.events,
+            phase.blocks,
+            phase.evictions,
+            phase.misses,
+            phase.miss_rate()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Misses a larger cache would have hit, from ghost lists holding the blocks
+//! the traced cache evicted last, as many as the extra capacity.
+//!
+//! A miss of a block still in a ghost list would have been a hit, assuming
+//! the larger cache evicts in the same order as the traced one.
+
+use crate::cache::Queue;
+use crate::model::{Event, Op};
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// The hits of one ghost list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ghost {
+    /// The extra capacity, in percent of the cache's.
+    pub percent: u32,
+    pub blocks: u64,
+    /// Misses of blocks in the list.
+    pub hits: u64,
+}
+
+/// The result of [`ghosts`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ghosts {
+    /// Of the traced cache, in blocks.
+    pub capacity: u64,
+    pub misses: u64,
+    pub ghosts: Vec<Ghost>,
+}
+
+/// The most blocks cached at once: missed or inserted and not evicted
+/// since. An estimate of the capacity of the traced cache.
+pub fn resident_peak(events: &[Event]) -> u64 {
+    let mut resident = HashSet::new();
+    let mut peak = 0;
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Missed | Op::Inserted => {
+                resident.insert(event.data);
+                peak = peak.max(resident.len());
+            }
+            Op::Evicted => {
+                resident.remove(&event.data);
+            }
+            Op::Hit => {}
+        }
+    }
+    peak as u64
+}
+
+/// Replays the evictions and misses of `events` through one ghost list per
+/// percentage of `capacity` in `percents`.
+pub fn ghosts(events: &[Event], capacity: u64, percents: &[u32]) -> Ghosts {
+    let mut lists: Vec<(Ghost, Queue)> = (percents.iter())
+        .map(|&percent| {
+            let blocks = capacity * u64::from(percent) / 100;
+            let ghost = Ghost {
+                percent,
+                blocks,
+                hits: 0,
+            };
+            (ghost, Queue::default())
+        })
+        .collect();
+    let mut misses = 0;
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Evicted => {
+                for (ghost, list) in &mut lists {
+                    list.push(event.data);
+                    if list.len() as u64 > ghost.blocks {
+                        list.pop_oldest();
+                    }
+                }
+            }
+            Op::Missed => {
+                misses += 1;
+                for (ghost, list) in &mut lists {
+                    ghost.hits += list.remove(&event.data) as u64;
+                }
+            }
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+    Ghosts {
+        capacity,
+        misses,
+        ghosts: lists.into_iter().map(|(ghost, _)| ghost).collect(),
+    }
+}
+
+/// Writes one line per ghost list.
+pub fn write_ghosts(ghosts: &Ghosts, writer: &mut impl Write) -> io::Result<()> {
+    for ghost in &ghosts.ghosts {
+        let share = ghost.hits as f64 / ghosts.misses.max(1) as f64;
+        writeln!(
+            writer,
+            "+{}% ({} blocks): {} of {} misses would have hit ({:.1}%)",
+            ghost.percent,
+            ghost.blocks,
+            ghost.hits,
+            ghosts.misses,
+            share * 100.0
+        )?;
+    }
+    Ok(())
+}
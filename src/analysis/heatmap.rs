@@ -0,0 +1,136 @@
+//! Misses per range of SST ids and time bucket, to see which SSTs get
+//! hammered when: as CSV with a row per range and a column per bucket, or
+//! as a PNG image with a square of pixels per cell.
+
+use crate::model::{Context, Event, Op};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The result of [`heatmap`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    /// SST ids per row.
+    pub ssts: u64,
+    /// Width of each column.
+    pub width: Duration,
+    /// Start of the first column.
+    pub start: SystemTime,
+    /// Columns from the first miss to the last, with misses or not.
+    pub columns: usize,
+    /// The first SST id of each row with misses, ascending, and the misses
+    /// of each column.
+    pub rows: Vec<(u64, Vec<u64>)>,
+}
+
+/// Counts the misses of `events` per `ssts` SST ids, aligned to multiples of
+/// it, and `width` wide time bucket, aligned to multiples of it since the
+/// Unix epoch. Rows without misses are left out.
+pub fn heatmap(events: &[Event], ssts: u64, width: Duration) -> Heatmap {
+    let ssts = ssts.max(1);
+    let nanos = width.as_nanos().max(1);
+    let index = |event: &Event| event.time.duration_since(UNIX_EPOCH).unwrap().as_nanos() / nanos;
+    let misses = || events.iter().filter(|event| event.op == Op::Missed);
+    let (Some(first), Some(last)) = (misses().map(index).min(), misses().map(index).max()) else {
+        return Heatmap {
+            ssts,
+            width,
+            start: UNIX_EPOCH,
+            columns: 0,
+            rows: vec![],
+        };
+    };
+
+    let columns = (last - first + 1) as usize;
+    let mut rows: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for event in misses() {
+        let row = (rows.entry(event.data.sst / ssts * ssts)).or_insert_with(|| vec![0; columns]);
+        row[(index(event) - first) as usize] += 1;
+    }
+    Heatmap {
+        ssts,
+        width,
+        start: UNIX_EPOCH + Duration::from_nanos((first * nanos) as u64),
+        columns,
+        rows: rows.into_iter().collect(),
+    }
+}
+
+/// Writes a row per SST range, its first and last id followed by its misses,
+/// under a header with the start of each column.
+pub fn write_heatmap_csv(
+    heatmap: &Heatmap,
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    write!(writer, "sst_start,sst_end")?;
+    for column in 0..heatmap.columns {
+        write!(
+            writer,
+            ",{}",
+            context.format_time(heatmap.start + heatmap.width * column as u32)
+        )?;
+    }
+    writeln!(writer)?;
+    for (sst, misses) in &heatmap.rows {
+        write!(writer, "{sst},{}", sst + heatmap.ssts - 1)?;
+        for count in misses {
+            write!(writer, ",{count}")?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Colors from no misses to the most, which those in between are blended
+/// from on a log scale, as few cells hold most misses.
+const GRADIENT: [[f64; 3]; 3] = [
+    [255.0, 255.0, 255.0],
+    [253.0, 141.0, 60.0],
+    [128.0, 0.0, 38.0],
+];
+
+/// Renders a `cell` pixels square per cell, rows of SST ranges ascending
+/// from the top and columns of time from the left, as a PNG image.
+pub fn write_heatmap_png(heatmap: &Heatmap, cell: u32, writer: impl Write) -> io::Result<()> {
+    let cell = cell.max(1) as usize;
+    let (width, height) = (
+        (heatmap.columns * cell).max(1),
+        (heatmap.rows.len() * cell).max(1),
+    );
+    let max = (heatmap.rows.iter())
+        .flat_map(|(_, misses)| misses)
+        .max()
+        .copied()
+        .unwrap_or(0);
+    let mut pixels = vec![255; width * height * 3];
+    for (row, (_, misses)) in heatmap.rows.iter().enumerate() {
+        for (column, &count) in misses.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let color = color((count as f64).ln_1p() / (max as f64).ln_1p());
+            for y in row * cell..(row + 1) * cell {
+                let start = (y * width + column * cell) * 3;
+                for pixel in pixels[start..start + cell * 3].chunks_exact_mut(3) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png = encoder.write_header().map_err(io::Error::other)?;
+    png.write_image_data(&pixels).map_err(io::Error::other)?;
+    png.finish().map_err(io::Error::other)
+}
+
+/// The [`GRADIENT`] color at `share` of the way from the first to the last.
+fn color(share: f64) -> [u8; 3] {
+    let position = share.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let low = (position.floor() as usize).min(GRADIENT.len() - 2);
+    let blend = position - low as f64;
+    let (from, to) = (GRADIENT[low], GRADIENT[low + 1]);
+    [0, 1, 2].map(|i| (from[i] + (to[i] - from[i]) * blend).round() as u8)
+}
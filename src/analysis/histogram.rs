@@ -0,0 +1,112 @@
+use crate::analysis::analyzer::Analyzer;
+use crate::analysis::durations::{Matching, MissMatcher};
+use crate::analysis::stats::percentile;
+use crate::model::{Context, Event};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// How many of `deltas` fall into each bucket between consecutive `bounds`,
+/// labelled like `[1s, 10s)`.
+pub fn buckets(deltas: &[Duration], bounds: &[Duration]) -> Vec<(String, u64)> {
+    let mut bounds = bounds.to_vec();
+    bounds.sort();
+    bounds.dedup();
+
+    let mut counts = vec![0u64; bounds.len() + 1];
+    for delta in deltas {
+        counts[bounds.partition_point(|bound| bound <= delta)] += 1;
+    }
+
+    let label = |bound: &Duration| humantime::format_duration(*bound).to_string();
+    (counts.into_iter().enumerate())
+        .map(|(i, count)| {
+            let lower = i
+                .checked_sub(1)
+                .map_or("0s".to_string(), |i| label(&bounds[i]));
+            let upper = bounds.get(i).map_or("inf".to_string(), label);
+            (format!("[{lower}, {upper})"), count)
+        })
+        .collect()
+}
+
+/// Writes the [`buckets`] of `deltas` under a `title` line, followed by
+/// their percentiles.
+pub fn write_histogram(
+    title: &str,
+    mut deltas: Vec<Duration>,
+    bounds: &[Duration],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "{title}:")?;
+    for (label, count) in buckets(&deltas, bounds) {
+        let share = count as f64 * 100.0 / deltas.len().max(1) as f64;
+        writeln!(writer, "{label}: {count} ({share:.1}%)")?;
+    }
+
+    deltas.sort();
+    if deltas.is_empty() {
+        writeln!(writer, "count: 0")
+    } else {
+        writeln!(
+            writer,
+            "count: {}, p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+            deltas.len(),
+            percentile(&deltas, 50.0),
+            percentile(&deltas, 90.0),
+            percentile(&deltas, 99.0),
+            deltas[deltas.len() - 1],
+        )
+    }
+}
+
+/// Writes the cumulative distribution of `deltas` as CSV: how many and which
+/// fraction of them are at most each multiple of `step`, up to the largest.
+pub fn write_cdf(
+    mut deltas: Vec<Duration>,
+    step: Duration,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    deltas.sort();
+    writeln!(writer, "delta_secs,count,fraction")?;
+    let Some(&max) = deltas.last() else {
+        return Ok(());
+    };
+    let step = step.max(Duration::from_nanos(1));
+    let mut bound = Duration::ZERO;
+    loop {
+        let count = deltas.partition_point(|delta| *delta <= bound);
+        let fraction = count as f64 / deltas.len() as f64;
+        writeln!(writer, "{},{count},{fraction:.6}", bound.as_secs_f64())?;
+        if bound >= max {
+            return Ok(());
+        }
+        bound += step;
+    }
+}
+
+/// The re-miss interval [`write_histogram`] as an [`Analyzer`].
+#[derive(Debug)]
+pub struct HistogramAnalyzer {
+    matcher: MissMatcher,
+    bounds: Vec<Duration>,
+}
+
+impl HistogramAnalyzer {
+    pub fn new(matching: Matching, bounds: &[Duration]) -> Self {
+        Self {
+            matcher: MissMatcher::new(matching),
+            bounds: bounds.to_vec(),
+        }
+    }
+}
+
+impl Analyzer for HistogramAnalyzer {
+    fn on_event(&mut self, event: &Event) {
+        self.matcher.on_event(event);
+    }
+
+    fn finish(&mut self, _: &Context, mut out: &mut dyn Write) -> io::Result<()> {
+        let (_, deltas) = self.matcher.finish();
+        write_histogram("re-miss intervals", deltas, &self.bounds, &mut out)
+    }
+}
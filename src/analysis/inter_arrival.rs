@@ -0,0 +1,76 @@
+use crate::analysis::stats::percentile;
+use crate::model::{Context, Data, Event, Op};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// Times between consecutive misses of each block with at least two misses,
+/// oldest first.
+pub fn inter_arrivals(events: &[Event]) -> HashMap<Data, Vec<Duration>> {
+    let mut last_miss: HashMap<Data, SystemTime> = HashMap::new();
+    let mut gaps: HashMap<Data, Vec<Duration>> = HashMap::new();
+    // Events are sorted newest first, walk them oldest first.
+    for event in events.iter().rev().filter(|event| event.op == Op::Missed) {
+        if let Some(last) = last_miss.insert(event.data, event.time) {
+            let gap = event.time.duration_since(last).unwrap_or_default();
+            gaps.entry(event.data).or_default().push(gap);
+        }
+    }
+    gaps
+}
+
+/// Coefficient of variation of `gaps`: close to 0 for blocks missed at a
+/// fixed period, around 1 for random arrivals.
+pub fn variation(gaps: &[Duration]) -> f64 {
+    let secs: Vec<_> = gaps.iter().map(Duration::as_secs_f64).collect();
+    let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+    if mean == 0.0 {
+        return 0.0;
+    }
+    let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+    variance.sqrt() / mean
+}
+
+/// Writes one line per block with its number of inter-arrival times, their
+/// percentiles and [`variation`], most missed first, then the percentiles of
+/// all of them together.
+pub fn write_inter_arrivals(
+    events: &[Event],
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut blocks: Vec<_> = inter_arrivals(events).into_iter().collect();
+    blocks.sort_by_key(|(data, gaps)| (Reverse(gaps.len()), *data));
+
+    let mut all = vec![];
+    for (data, mut gaps) in blocks {
+        gaps.sort();
+        writeln!(
+            writer,
+            "{}, count: {}, p50: {:?}, p90: {:?}, max: {:?}, cv: {:.2}",
+            context.data(data),
+            gaps.len(),
+            percentile(&gaps, 50.0),
+            percentile(&gaps, 90.0),
+            gaps[gaps.len() - 1],
+            variation(&gaps),
+        )?;
+        all.extend(gaps);
+    }
+
+    all.sort();
+    if all.is_empty() {
+        writeln!(writer, "count: 0")
+    } else {
+        writeln!(
+            writer,
+            "count: {}, p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+            all.len(),
+            percentile(&all, 50.0),
+            percentile(&all, 90.0),
+            percentile(&all, 99.0),
+            all[all.len() - 1],
+        )
+    }
+}
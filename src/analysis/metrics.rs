@@ -0,0 +1,58 @@
+use crate::analysis::durations::Counts;
+use crate::model::{Event, Op};
+use std::io::{self, Write};
+
+/// Event and miss matching totals, exported in the Prometheus text format.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub evicted: u64,
+    pub missed: u64,
+    pub inserted: u64,
+    pub hit: u64,
+    pub counts: Counts,
+}
+
+impl Metrics {
+    /// Adds the ops of `events` to the totals.
+    pub fn observe(&mut self, events: &[Event]) {
+        for event in events {
+            match event.op {
+                Op::Evicted => self.evicted += 1,
+                Op::Missed => self.missed += 1,
+                Op::Inserted => self.inserted += 1,
+                Op::Hit => self.hit += 1,
+            }
+        }
+    }
+}
+
+/// Writes `metrics` as counters in the Prometheus text exposition format.
+pub fn write_metrics(metrics: &Metrics, writer: &mut impl Write) -> io::Result<()> {
+    let Counts { long, short, none } = metrics.counts;
+    for (name, help, value) in [
+        ("blocks_evict_total", "Evicted blocks.", metrics.evicted),
+        ("blocks_miss_total", "Missed blocks.", metrics.missed),
+        ("blocks_insert_total", "Inserted blocks.", metrics.inserted),
+        ("blocks_hit_total", "Block cache hits.", metrics.hit),
+        (
+            "blocks_short_remiss_total",
+            "Misses shortly after the block's eviction.",
+            short,
+        ),
+        (
+            "blocks_long_remiss_total",
+            "Misses long after the block's eviction.",
+            long,
+        ),
+        (
+            "blocks_unmatched_miss_total",
+            "Misses without an earlier eviction of the block.",
+            none,
+        ),
+    ] {
+        writeln!(writer, "# HELP {name} {help}")?;
+        writeln!(writer, "# TYPE {name} counter")?;
+        writeln!(writer, "{name} {value}")?;
+    }
+    Ok(())
+}
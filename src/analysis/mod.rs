@@ -0,0 +1,41 @@
+//! Analyses over parsed events.
+//!
+//! Unless stated otherwise, functions taking `&[Event]` expect the events
+//! sorted newest first, which is the order the CLI writes them in.
+
+pub mod alerts;
+pub mod analyzer;
+pub mod bursts;
+pub mod bytes;
+pub mod check;
+pub mod checksum;
+pub mod diagnostics;
+pub mod diff;
+pub mod durations;
+pub mod gaps;
+pub mod ghost;
+pub mod heatmap;
+pub mod histogram;
+pub mod inter_arrival;
+pub mod metrics;
+pub mod mrc;
+pub mod negatives;
+pub mod per_group;
+pub mod per_sst;
+pub mod plot;
+pub mod popularity;
+pub mod recency;
+pub mod report;
+pub mod simulate;
+pub mod sketch;
+pub mod skew;
+pub mod sparkline;
+pub mod stats;
+pub mod summary;
+pub mod survival;
+pub mod thrashing;
+pub mod timeline;
+pub mod timeseries;
+pub mod topk;
+pub mod warmup;
+pub mod working_set;
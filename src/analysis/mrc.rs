@@ -0,0 +1,116 @@
+use crate::model::{Context, Data, Event};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Reuse distances of a trace's block accesses, which give the miss ratio of
+/// an LRU cache of any size at once.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mrc {
+    /// Number of accesses in the trace, sampled or not.
+    pub accesses: u64,
+    /// First accesses of a block, which miss at any size.
+    pub cold: u64,
+    /// Reuse distance of every other access, ascending: the number of distinct
+    /// blocks accessed since the block's previous access.
+    pub distances: Vec<u64>,
+    /// Number of distinct blocks.
+    pub blocks: u64,
+}
+
+impl Mrc {
+    /// Miss ratio of an LRU cache of `size` blocks, which hits exactly the
+    /// accesses with a reuse distance below `size`.
+    pub fn miss_ratio(&self, size: u64) -> f64 {
+        let hits = self.distances.partition_point(|&distance| distance < size) as u64;
+        let sampled = self.cold + self.distances.len() as u64;
+        1.0 - hits as f64 / sampled.max(1) as f64
+    }
+}
+
+/// Computes the reuse distances of the block accesses in `events`: misses,
+/// and hits for traces that log those.
+///
+/// With a `rate` below 1, only that share of the blocks is tracked, picked by
+/// hashing as in SHARDS, and distances are scaled up accordingly. That bounds
+/// the memory for traces with many distinct blocks.
+pub fn mrc(events: &[Event], rate: f64, context: &Context) -> Mrc {
+    let accesses: Vec<_> = (events.iter().rev())
+        .filter(|event| event.op.is_access())
+        .map(|event| event.data)
+        .collect();
+    let scale = |n: u64| (n as f64 / rate) as u64;
+
+    // Marks the latest access of each block, so the marks after a block's
+    // previous access count the distinct blocks since.
+    let mut marks = Fenwick::new(accesses.len());
+    let mut last: HashMap<Data, usize> = HashMap::new();
+    let mut mrc = Mrc {
+        accesses: accesses.len() as u64,
+        ..Mrc::default()
+    };
+    for (i, data) in accesses.iter().enumerate() {
+        if !data.sampled(rate, context) {
+            continue;
+        }
+        match last.insert(*data, i) {
+            Some(previous) => {
+                let distance = marks.sum(i) - marks.sum(previous + 1);
+                mrc.distances.push(scale(distance));
+                marks.add(previous, -1);
+            }
+            None => mrc.cold += 1,
+        }
+        marks.add(i, 1);
+    }
+    mrc.distances.sort_unstable();
+    mrc.blocks = scale(last.len() as u64);
+    mrc
+}
+
+/// Writes the miss ratio at each of `sizes` as CSV, or at powers of two up to
+/// the number of distinct blocks if `sizes` is empty.
+pub fn write_mrc(mrc: &Mrc, sizes: &[u64], writer: &mut impl Write) -> io::Result<()> {
+    let sizes = if sizes.is_empty() {
+        let mut sizes: Vec<_> = (0..)
+            .map(|shift| 1u64 << shift)
+            .take_while(|&size| size < mrc.blocks)
+            .collect();
+        sizes.push(mrc.blocks);
+        sizes
+    } else {
+        sizes.to_vec()
+    };
+    writeln!(writer, "cache_size,miss_ratio")?;
+    for size in sizes {
+        writeln!(writer, "{size},{:.6}", mrc.miss_ratio(size))?;
+    }
+    Ok(())
+}
+
+/// Prefix sums over a fixed number of counters.
+struct Fenwick(Vec<i64>);
+
+impl Fenwick {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len + 1])
+    }
+
+    fn add(&mut self, index: usize, delta: i64) {
+        let mut i = index + 1;
+        while i < self.0.len() {
+            self.0[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Sum of the counters before `end`.
+    fn sum(&self, end: usize) -> u64 {
+        let mut sum = 0;
+        let mut i = end;
+        while i > 0 {
+            sum += self.0[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum as u64
+    }
+}
@@ -0,0 +1,102 @@
+//! Misses whose block was only evicted after them, which the duration
+//! output shows with a `-` delta. Small ones are mostly entries logged out of
+//! order, such as by racing threads, rather than real cache behavior.
+
+use crate::analysis::durations::{Match, Matching, match_misses};
+use crate::analysis::histogram::write_histogram;
+use crate::model::{Data, Event};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// The negative deltas of a trace.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Negatives {
+    pub misses: u64,
+    /// How long after each miss its block was evicted, ascending, including
+    /// those under the epsilon.
+    pub deltas: Vec<Duration>,
+    /// Misses and negative deltas per input file, in input order.
+    pub per_file: Vec<(String, u64, u64)>,
+    /// See [`Matching::negative_epsilon`].
+    pub epsilon: Duration,
+}
+
+impl Negatives {
+    /// Deltas counted as zero by the epsilon.
+    pub fn reclassified(&self) -> usize {
+        self.deltas.partition_point(|&delta| delta < self.epsilon)
+    }
+}
+
+/// The [`Negatives`] of `events`, attributing each miss to one of `files`
+/// with `file_of`. Misses it has no file for only count in the totals.
+pub fn negatives(
+    events: &[Event],
+    matching: Matching,
+    files: &[String],
+    file_of: impl Fn(Data, SystemTime) -> Option<usize>,
+) -> Negatives {
+    let raw = Matching {
+        negative_epsilon: Duration::ZERO,
+        ..matching
+    };
+    let mut negatives = Negatives {
+        per_file: files.iter().map(|file| (file.clone(), 0, 0)).collect(),
+        epsilon: matching.negative_epsilon,
+        ..Negatives::default()
+    };
+    for (data, time, matched) in match_misses(events, raw) {
+        negatives.misses += 1;
+        let file = file_of(data, time).and_then(|i| negatives.per_file.get_mut(i));
+        let negative = match matched {
+            Match::Negative(delta) => Some(delta),
+            _ => None,
+        };
+        if let Some((_, misses, count)) = file {
+            *misses += 1;
+            *count += negative.is_some() as u64;
+        }
+        negatives.deltas.extend(negative);
+    }
+    negatives.deltas.sort();
+    negatives
+}
+
+/// Writes the totals, the counts per input file and a histogram of the
+/// magnitudes over `bounds`.
+pub fn write_negatives(
+    negatives: &Negatives,
+    bounds: &[Duration],
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let share = |count: u64, of: u64| count as f64 * 100.0 / of.max(1) as f64;
+    let count = negatives.deltas.len() as u64;
+    writeln!(
+        writer,
+        "Negative deltas: {count} of {} misses ({:.2}%)",
+        negatives.misses,
+        share(count, negatives.misses)
+    )?;
+    if !negatives.epsilon.is_zero() {
+        writeln!(
+            writer,
+            "Under {:?}, counted as zero: {}",
+            negatives.epsilon,
+            negatives.reclassified()
+        )?;
+    }
+    writeln!(writer, "Per file:")?;
+    for (file, misses, count) in &negatives.per_file {
+        writeln!(
+            writer,
+            "    {file}: {count} of {misses} misses ({:.2}%)",
+            share(*count, *misses)
+        )?;
+    }
+    write_histogram(
+        "Negative delta histogram",
+        negatives.deltas.clone(),
+        bounds,
+        writer,
+    )
+}
@@ -0,0 +1,132 @@
+use crate::analysis::durations::{Counts, Match, Matching, match_misses};
+use crate::model::{Context, Data, Event, Op};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// What happened to the blocks of one group, such as a source.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GroupStats {
+    /// Distinct blocks with any event.
+    pub blocks: u64,
+    pub evictions: u64,
+    pub misses: u64,
+    pub counts: Counts,
+}
+
+/// Aggregates `events` per group `group` puts their block in, in group
+/// order, matching misses like [`match_misses`].
+pub fn per_group<K: Hash + Ord + Copy>(
+    events: &[Event],
+    matching: Matching,
+    group: impl Fn(&Data) -> K,
+) -> Vec<(K, GroupStats)> {
+    let mut groups: HashMap<K, GroupStats> = HashMap::new();
+    let mut blocks: HashSet<Data> = HashSet::new();
+    for event in events {
+        let stats = groups.entry(group(&event.data)).or_default();
+        match event.op {
+            Op::Evicted => stats.evictions += 1,
+            Op::Missed => stats.misses += 1,
+            Op::Inserted | Op::Hit => {}
+        }
+        if blocks.insert(event.data) {
+            stats.blocks += 1;
+        }
+    }
+    for (data, _, matched) in match_misses(events, matching) {
+        let counts = &mut groups.get_mut(&group(&data)).unwrap().counts;
+        match matched {
+            Match::Evicted(_, true) => counts.short += 1,
+            Match::Evicted(_, false) => counts.long += 1,
+            Match::None => counts.none += 1,
+            Match::Negative(_) => {}
+        }
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by_key(|&(key, _)| key);
+    groups
+}
+
+/// [`per_group`] by source, see [`Data::source`], by name with `-` for
+/// events without one. Blocks of different sources are distinct, so a block
+/// cached by two shards counts in both.
+pub fn per_source(
+    events: &[Event],
+    matching: Matching,
+    context: &Context,
+) -> Vec<(Arc<str>, GroupStats)> {
+    by_name(per_group(events, matching, |data| data.source), |source| {
+        context.source_name(source).unwrap_or_else(|| "-".into())
+    })
+}
+
+/// [`per_group`] by key kind, see [`Data::kind`], by name.
+pub fn per_kind(
+    events: &[Event],
+    matching: Matching,
+    context: &Context,
+) -> Vec<(Arc<str>, GroupStats)> {
+    by_name(per_group(events, matching, |data| data.kind), |kind| {
+        context.kind_label(kind)
+    })
+}
+
+/// Names the groups by id with `name`, in name order.
+fn by_name(
+    groups: Vec<(u32, GroupStats)>,
+    name: impl Fn(u32) -> Arc<str>,
+) -> Vec<(Arc<str>, GroupStats)> {
+    let mut groups: Vec<_> = (groups.into_iter())
+        .map(|(id, stats)| (name(id), stats))
+        .collect();
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    groups
+}
+
+/// [`per_group`] by the LSM level of the SST, see [`crate::sst_meta`], with
+/// `None` for SSTs of unknown level.
+pub fn per_level(
+    events: &[Event],
+    matching: Matching,
+    context: &Context,
+) -> Vec<(Option<u32>, GroupStats)> {
+    per_group(events, matching, |data| context.sst_meta(data.sst)?.level)
+}
+
+/// [`per_group`] by the table of the SST, like [`per_level`].
+pub fn per_table(
+    events: &[Event],
+    matching: Matching,
+    context: &Context,
+) -> Vec<(Option<u64>, GroupStats)> {
+    per_group(events, matching, |data| {
+        context.sst_meta(data.sst)?.table_id
+    })
+}
+
+/// Names the groups of [`per_level`] or [`per_table`], `-` for `None`.
+pub fn named<K: Display>(groups: Vec<(Option<K>, GroupStats)>) -> Vec<(String, GroupStats)> {
+    (groups.into_iter())
+        .map(|(key, stats)| (key.map_or("-".to_string(), |key| key.to_string()), stats))
+        .collect()
+}
+
+/// Writes one line of stats per group, naming it as `label: <group>`.
+pub fn write_groups<K: Display>(
+    groups: &[(K, GroupStats)],
+    label: &str,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    for (group, stats) in groups {
+        let Counts { long, short, none } = stats.counts;
+        writeln!(
+            writer,
+            "{label}: {group}, blocks: {}, evictions: {}, misses: {}, long: {long}, short: {short}, none: {none}",
+            stats.blocks, stats.evictions, stats.misses
+        )?;
+    }
+    Ok(())
+}
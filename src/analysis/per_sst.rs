@@ -0,0 +1,103 @@
+use crate::analysis::durations::{Match, Matching, match_misses};
+use crate::model::{Context, Data, Event, Op};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// What happened to the blocks of one SST.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SstStats {
+    pub sst: u64,
+    /// Distinct blocks with any event.
+    pub blocks: u64,
+    pub evictions: u64,
+    pub misses: u64,
+    /// Misses matched to an earlier eviction of their block.
+    pub re_misses: u64,
+    /// Sum of the re-miss intervals.
+    pub re_miss_total: Duration,
+}
+
+impl SstStats {
+    pub fn mean_re_miss(&self) -> Option<Duration> {
+        (self.re_misses > 0).then(|| {
+            Duration::from_nanos((self.re_miss_total.as_nanos() / self.re_misses as u128) as u64)
+        })
+    }
+}
+
+/// Aggregates `events` per SST, in SST order, matching misses like
+/// [`match_misses`].
+pub fn per_sst(events: &[Event], matching: Matching) -> Vec<SstStats> {
+    let mut ssts: BTreeMap<u64, SstStats> = BTreeMap::new();
+    let mut blocks: HashSet<Data> = HashSet::new();
+    for event in events {
+        let sst = ssts.entry(event.data.sst).or_insert_with(|| SstStats {
+            sst: event.data.sst,
+            ..SstStats::default()
+        });
+        match event.op {
+            Op::Evicted => sst.evictions += 1,
+            Op::Missed => sst.misses += 1,
+            Op::Inserted | Op::Hit => {}
+        }
+        if blocks.insert(event.data) {
+            sst.blocks += 1;
+        }
+    }
+    for (data, _, matched) in match_misses(events, matching) {
+        if let Match::Evicted(delta, _) = matched {
+            let sst = ssts.get_mut(&data.sst).unwrap();
+            sst.re_misses += 1;
+            sst.re_miss_total += delta;
+        }
+    }
+    ssts.into_values().collect()
+}
+
+/// Writes [`per_sst`] as CSV, with the mean re-miss interval in seconds and
+/// the [`crate::sst_meta`] of each SST if loaded.
+pub fn write_per_sst(
+    events: &[Event],
+    matching: Matching,
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let with_meta = context.has_sst_meta();
+    write!(
+        writer,
+        "sst_id,blocks,evictions,misses,re_misses,mean_re_miss_secs"
+    )?;
+    if with_meta {
+        write!(writer, ",table_id,level,file_size,block_count")?;
+    }
+    writeln!(writer)?;
+    for sst in per_sst(events, matching) {
+        let mean = sst
+            .mean_re_miss()
+            .map_or(String::new(), |mean| format!("{:.6}", mean.as_secs_f64()));
+        write!(
+            writer,
+            "{},{},{},{},{},{mean}",
+            sst.sst, sst.blocks, sst.evictions, sst.misses, sst.re_misses
+        )?;
+        if with_meta {
+            let meta = context.sst_meta(sst.sst).cloned().unwrap_or_default();
+            write!(
+                writer,
+                ",{},{},{},{}",
+                cell(meta.table_id),
+                cell(meta.level),
+                cell(meta.file_size),
+                cell(meta.block_count)
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// An optional CSV field, empty for `None`.
+fn cell(value: Option<impl ToString>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
@@ -0,0 +1,145 @@
+//! SVG charts of the reports, through plotters. Each is drawn on an
+//! [`SVGBackend`] of [`SIZE`], writing to a file or a string.
+
+use crate::analysis::histogram::buckets;
+use crate::analysis::timeseries::Bucket;
+use crate::analysis::topk::Hot;
+use crate::model::Context;
+use plotters::coord::ranged1d::SegmentValue;
+use plotters::prelude::*;
+use std::io;
+use std::time::Duration;
+
+/// Width and height of the charts.
+pub const SIZE: (u32, u32) = (960, 540);
+
+fn other(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+/// A bar per labelled count, in the order given.
+fn bars(svg: SVGBackend, caption: &str, x_desc: &str, bars: &[(String, u64)]) -> io::Result<()> {
+    let root = svg.into_drawing_area();
+    root.fill(&WHITE).map_err(other)?;
+    let max = bars.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            (0..bars.len().max(1)).into_segmented(),
+            0..max + max / 10 + 1,
+        )
+        .map_err(other)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(bars.len().max(1))
+        .x_label_formatter(&|value| match value {
+            SegmentValue::CenterOf(i) => bars.get(*i).map_or(String::new(), |(l, _)| l.clone()),
+            _ => String::new(),
+        })
+        .x_desc(x_desc)
+        .y_desc("count")
+        .draw()
+        .map_err(other)?;
+    chart
+        .draw_series(bars.iter().enumerate().map(|(i, (_, count))| {
+            let corners = [
+                (SegmentValue::Exact(i), 0),
+                (SegmentValue::Exact(i + 1), *count),
+            ];
+            let mut bar = Rectangle::new(corners, BLUE.mix(0.6).filled());
+            bar.set_margin(0, 0, 4, 4);
+            bar
+        }))
+        .map_err(other)?;
+    root.present().map_err(other)
+}
+
+/// Bar chart of how many `deltas` fall into each bucket between `bounds`.
+pub fn plot_histogram(svg: SVGBackend, deltas: &[Duration], bounds: &[Duration]) -> io::Result<()> {
+    let caption = format!("re-miss intervals ({} misses)", deltas.len());
+    bars(svg, &caption, "eviction to miss", &buckets(deltas, bounds))
+}
+
+/// Misses and evictions per second of each of `buckets`, oldest first.
+pub fn plot_rates(
+    svg: SVGBackend,
+    buckets: &[Bucket],
+    width: Duration,
+    context: &Context,
+) -> io::Result<()> {
+    let root = svg.into_drawing_area();
+    root.fill(&WHITE).map_err(other)?;
+    let secs = width.as_secs_f64().max(f64::MIN_POSITIVE);
+    let rate = |count: u64| count as f64 / secs;
+    let max = (buckets.iter())
+        .map(|bucket| rate(bucket.missed.max(bucket.evicted)))
+        .fold(0.0, f64::max);
+    let start = buckets.first().map(|bucket| bucket.start);
+    let offset = |bucket: &Bucket| {
+        bucket
+            .start
+            .duration_since(start.unwrap())
+            .unwrap()
+            .as_secs_f64()
+    };
+    let caption = match start {
+        Some(start) => format!("miss rate, from {}", context.format_time(start)),
+        None => "miss rate".to_string(),
+    };
+    let end = buckets.last().map_or(0.0, offset) + secs;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..end, 0.0..max * 1.1 + 1.0)
+        .map_err(other)?;
+    chart
+        .configure_mesh()
+        .x_desc("seconds")
+        .y_desc("per second")
+        .draw()
+        .map_err(other)?;
+    for (label, color, counts) in [
+        (
+            "misses",
+            RED,
+            buckets
+                .iter()
+                .map(|bucket| bucket.missed)
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "evictions",
+            BLUE,
+            buckets.iter().map(|bucket| bucket.evicted).collect(),
+        ),
+    ] {
+        let points =
+            (buckets.iter().zip(counts)).map(|(bucket, count)| (offset(bucket), rate(count)));
+        chart
+            .draw_series(LineSeries::new(points, color))
+            .map_err(other)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color));
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(other)?;
+    root.present().map_err(other)
+}
+
+/// Bar chart of the SSTs with the most evictions.
+pub fn plot_top_ssts(svg: SVGBackend, top: &[Hot<u64>]) -> io::Result<()> {
+    let counts: Vec<_> = (top.iter())
+        .map(|hot| (hot.key.to_string(), hot.count))
+        .collect();
+    bars(svg, "SSTs by evictions", "sst", &counts)
+}
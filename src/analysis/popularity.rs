@@ -0,0 +1,123 @@
+//! How often blocks are accessed, binned by powers of ten: a large share of
+//! blocks accessed only once means an admission filter such as TinyLFU
+//! would keep a lot out of the cache.
+
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The blocks accessed `min..=max` times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bin {
+    pub min: u64,
+    pub max: u64,
+    pub blocks: u64,
+    pub accesses: u64,
+    pub misses: u64,
+}
+
+/// The result of [`popularity`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Popularity {
+    /// 1, 2 to 10, 11 to 100 and so on, up to the most accessed block.
+    pub bins: Vec<Bin>,
+    pub blocks: u64,
+    pub misses: u64,
+}
+
+impl Popularity {
+    /// The share of the blocks accessed only once.
+    pub fn one_hit_wonders(&self) -> f64 {
+        let once = self.bins.first().map_or(0, |bin| bin.blocks);
+        share(once, self.blocks)
+    }
+}
+
+/// Counts the accesses of every block, its misses and hits, and bins the
+/// blocks by them. Traces without hits count misses only.
+pub fn popularity(events: &[Event]) -> Popularity {
+    // Accesses and misses of each block.
+    let mut blocks: HashMap<Data, (u64, u64)> = HashMap::new();
+    for event in events {
+        match event.op {
+            Op::Missed => {
+                let block = blocks.entry(event.data).or_default();
+                block.0 += 1;
+                block.1 += 1;
+            }
+            Op::Hit => blocks.entry(event.data).or_default().0 += 1,
+            Op::Evicted | Op::Inserted => {}
+        }
+    }
+    let mut popularity = Popularity::default();
+    for (accesses, misses) in blocks.into_values() {
+        let index = bin_index(accesses);
+        while popularity.bins.len() <= index {
+            let (min, max) = bin_bounds(popularity.bins.len());
+            popularity.bins.push(Bin {
+                min,
+                max,
+                blocks: 0,
+                accesses: 0,
+                misses: 0,
+            });
+        }
+        let bin = &mut popularity.bins[index];
+        bin.blocks += 1;
+        bin.accesses += accesses;
+        bin.misses += misses;
+        popularity.blocks += 1;
+        popularity.misses += misses;
+    }
+    popularity
+}
+
+/// The bin of blocks accessed `accesses` times, at least once.
+fn bin_index(accesses: u64) -> usize {
+    (accesses - 1)
+        .checked_ilog10()
+        .map_or(0, |log| log as usize + 1)
+}
+
+/// The fewest and most accesses of the bin at `index`.
+fn bin_bounds(index: usize) -> (u64, u64) {
+    match index {
+        0 => (1, 1),
+        _ => (10u64.pow(index as u32 - 1) + 1, 10u64.pow(index as u32)),
+    }
+}
+
+fn share(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// Writes one line per bin with its share of the blocks and of the misses,
+/// then the one-hit-wonder ratio.
+pub fn write_popularity(popularity: &Popularity, writer: &mut impl Write) -> io::Result<()> {
+    for bin in &popularity.bins {
+        let accessed = if bin.min == bin.max {
+            bin.min.to_string()
+        } else {
+            format!("{}-{}", bin.min, bin.max)
+        };
+        writeln!(
+            writer,
+            "accessed: {accessed}, blocks: {} ({:.1}%), accesses: {}, misses: {} ({:.1}%)",
+            bin.blocks,
+            share(bin.blocks, popularity.blocks) * 100.0,
+            bin.accesses,
+            bin.misses,
+            share(bin.misses, popularity.misses) * 100.0
+        )?;
+    }
+    writeln!(
+        writer,
+        "one-hit wonders: {:.1}% of {} blocks",
+        popularity.one_hit_wonders() * 100.0,
+        popularity.blocks
+    )
+}
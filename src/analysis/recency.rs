@@ -0,0 +1,57 @@
+use crate::analysis::stats::percentile;
+use crate::model::{Context, Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// For each eviction, reports how long ago the block was last missed or hit,
+/// then summarizes the distribution.
+pub fn write_recency(
+    events: &[Event],
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut last_access: HashMap<Data, SystemTime> = HashMap::new();
+    let mut recencies = vec![];
+    let mut none = 0;
+
+    // Events are sorted newest first, walk them oldest first.
+    for &Event { data, time, op, .. } in events.iter().rev() {
+        match op {
+            Op::Missed | Op::Hit => {
+                last_access.insert(data, time);
+            }
+            Op::Evicted => {
+                let (block, evict) = (context.data(data), context.format_time(time));
+                if let Some(&accessed) = last_access.get(&data) {
+                    let recency = time.duration_since(accessed).unwrap_or_default();
+                    recencies.push(recency);
+                    writeln!(writer, "{block}, recency: {recency:?}, evict time: {evict}")?;
+                } else {
+                    none += 1;
+                    writeln!(
+                        writer,
+                        "{block}, evict time: {evict}, No prior access found"
+                    )?;
+                }
+            }
+            Op::Inserted => {}
+        }
+    }
+
+    recencies.sort();
+    if recencies.is_empty() {
+        writeln!(writer, "count: 0, none: {none}")
+    } else {
+        writeln!(
+            writer,
+            "count: {}, none: {none}, min: {:?}, p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+            recencies.len(),
+            recencies[0],
+            percentile(&recencies, 50.0),
+            percentile(&recencies, 90.0),
+            percentile(&recencies, 99.0),
+            recencies[recencies.len() - 1],
+        )
+    }
+}
@@ -0,0 +1,173 @@
+//! A single static HTML page of the main reports, with inline SVG charts.
+
+use crate::analysis::durations::{Matching, intervals};
+use crate::analysis::histogram::buckets;
+use crate::analysis::plot::{SIZE, plot_histogram, plot_rates, plot_top_ssts};
+use crate::analysis::stats::percentile;
+use crate::analysis::summary::summarize;
+use crate::analysis::timeseries;
+use crate::analysis::topk::{Hot, top_evicted_ssts, top_missed_blocks};
+use crate::model::{Context, Event};
+use plotters::prelude::SVGBackend;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// What goes into the report besides the events.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub matching: Matching,
+    /// Upper bounds of the re-miss interval histogram buckets.
+    pub bounds: Vec<Duration>,
+    /// Width of the miss rate time buckets.
+    pub bucket: Duration,
+    /// Number of rows of the top-K tables.
+    pub top: usize,
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+table { border-collapse: collapse; margin-bottom: 1em; } \
+td, th { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; } \
+th { background: #eee; }";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes a table with a `header` row and `rows`, escaping every cell.
+fn table(writer: &mut impl Write, header: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    writeln!(writer, "<table>")?;
+    let cells: String = header
+        .iter()
+        .map(|cell| format!("<th>{cell}</th>"))
+        .collect();
+    writeln!(writer, "<tr>{cells}</tr>")?;
+    for row in rows {
+        let cells: String = (row.iter())
+            .map(|cell| format!("<td>{}</td>", escape(cell)))
+            .collect();
+        writeln!(writer, "<tr>{cells}</tr>")?;
+    }
+    writeln!(writer, "</table>")
+}
+
+fn svg(draw: impl FnOnce(SVGBackend) -> io::Result<()>) -> io::Result<String> {
+    let mut svg = String::new();
+    draw(SVGBackend::with_string(&mut svg, SIZE))?;
+    Ok(svg)
+}
+
+fn hot_rows<K: Copy>(
+    hot: &[Hot<K>],
+    key: impl Fn(K) -> String,
+    context: &Context,
+) -> Vec<Vec<String>> {
+    (hot.iter())
+        .map(|hot| {
+            vec![
+                key(hot.key),
+                hot.count.to_string(),
+                context.format_time(hot.first).to_string(),
+                context.format_time(hot.last).to_string(),
+            ]
+        })
+        .collect()
+}
+
+/// Writes the summary counts, the re-miss interval histogram and
+/// percentiles, the miss rate over time and the top-K tables of `events` as
+/// one HTML page titled `title`.
+pub fn write_html(
+    events: &[Event],
+    title: &str,
+    options: &ReportOptions,
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let title = escape(title);
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{title}</title><style>{STYLE}</style>")?;
+    writeln!(writer, "</head><body>")?;
+    writeln!(writer, "<h1>{title}</h1>")?;
+
+    let summary = summarize(events);
+    let (counts, deltas) = intervals(events, options.matching);
+    let mut rows = vec![
+        ("events", summary.events.to_string()),
+        ("evicted", summary.evicted.to_string()),
+        ("missed", summary.missed.to_string()),
+        ("inserted", summary.inserted.to_string()),
+        ("hit", summary.hit.to_string()),
+        ("blocks", summary.blocks.to_string()),
+        ("ssts", summary.ssts.to_string()),
+    ];
+    if let Some((first, last)) = summary.range {
+        rows.push(("first", context.format_time(first).to_string()));
+        rows.push(("last", context.format_time(last).to_string()));
+        let span = last.duration_since(first).unwrap();
+        rows.push(("span", format!("{span:?}")));
+    }
+    rows.push(("long misses", counts.long.to_string()));
+    rows.push(("short misses", counts.short.to_string()));
+    rows.push(("unmatched misses", counts.none.to_string()));
+    writeln!(writer, "<h2>Summary</h2>")?;
+    let rows: Vec<_> = (rows.into_iter())
+        .map(|(name, value)| vec![name.to_string(), value])
+        .collect();
+    table(writer, &["", "value"], &rows)?;
+
+    writeln!(writer, "<h2>Re-miss intervals</h2>")?;
+    writeln!(
+        writer,
+        "{}",
+        svg(|svg| plot_histogram(svg, &deltas, &options.bounds))?
+    )?;
+    let rows: Vec<_> = (buckets(&deltas, &options.bounds).into_iter())
+        .map(|(label, count)| {
+            let share = count as f64 * 100.0 / deltas.len().max(1) as f64;
+            vec![label, count.to_string(), format!("{share:.1}%")]
+        })
+        .collect();
+    table(writer, &["interval", "misses", "share"], &rows)?;
+    if !deltas.is_empty() {
+        let rows = vec![
+            [50.0, 90.0, 99.0]
+                .iter()
+                .map(|p| format!("{:?}", percentile(&deltas, *p)))
+                .chain([format!("{:?}", deltas[deltas.len() - 1])])
+                .collect(),
+        ];
+        table(writer, &["p50", "p90", "p99", "max"], &rows)?;
+    }
+
+    writeln!(writer, "<h2>Miss rate</h2>")?;
+    let buckets = timeseries::buckets(events, options.bucket);
+    writeln!(
+        writer,
+        "{}",
+        svg(|svg| plot_rates(svg, &buckets, options.bucket, context))?
+    )?;
+
+    writeln!(writer, "<h2>Top SSTs by evictions</h2>")?;
+    let ssts = top_evicted_ssts(events, options.top);
+    writeln!(writer, "{}", svg(|svg| plot_top_ssts(svg, &ssts))?)?;
+    table(
+        writer,
+        &["sst", "evictions", "first", "last"],
+        &hot_rows(&ssts, |sst| sst.to_string(), context),
+    )?;
+    writeln!(writer, "<h2>Top blocks by misses</h2>")?;
+    table(
+        writer,
+        &["block", "misses", "first", "last"],
+        &hot_rows(
+            &top_missed_blocks(events, options.top),
+            |data| context.data(data).to_string(),
+            context,
+        ),
+    )?;
+
+    writeln!(writer, "</body></html>")
+}
@@ -0,0 +1,83 @@
+use crate::cache::Cache;
+use crate::model::{Data, Event};
+use std::collections::{BTreeSet, HashMap};
+
+/// Hits and misses of a cache replaying a trace.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Replay {
+    pub accesses: u64,
+    pub hits: u64,
+}
+
+impl Replay {
+    pub fn hit_ratio(&self) -> f64 {
+        self.hits as f64 / self.accesses.max(1) as f64
+    }
+}
+
+/// Replays the block accesses of `events` against `cache`, oldest first.
+///
+/// The accesses seen by the simulated cache are the misses of the traced one,
+/// and its hits for traces that log those.
+pub fn replay(events: &[Event], cache: &mut dyn Cache) -> Replay {
+    let mut replay = Replay::default();
+    let mut evicted = vec![];
+    for event in events.iter().rev().filter(|event| event.op.is_access()) {
+        replay.accesses += 1;
+        if cache.access(event.data, &mut evicted) {
+            replay.hits += 1;
+        }
+        evicted.clear();
+    }
+    replay
+}
+
+/// Replays the block accesses of `events` like [`replay`], but against the
+/// offline optimal cache of `capacity` blocks: Belady's MIN, which keeps the
+/// blocks that are accessed again soonest. No cache of that size can have
+/// more hits.
+pub fn replay_optimal(events: &[Event], capacity: usize) -> Replay {
+    let accesses: Vec<_> = (events.iter().rev())
+        .filter(|event| event.op.is_access())
+        .map(|event| event.data)
+        .collect();
+    // Index of the next access of the same block, `usize::MAX` for never.
+    let mut next_use = vec![usize::MAX; accesses.len()];
+    let mut seen = HashMap::new();
+    for (i, data) in accesses.iter().enumerate().rev() {
+        if let Some(next) = seen.insert(*data, i) {
+            next_use[i] = next;
+        }
+    }
+
+    let mut replay = Replay {
+        accesses: accesses.len() as u64,
+        hits: 0,
+    };
+    // Cached blocks by their next access, so the last one is the victim.
+    let mut cached: HashMap<Data, usize> = HashMap::new();
+    let mut order = BTreeSet::new();
+    for (i, data) in accesses.into_iter().enumerate() {
+        let next = next_use[i];
+        if let Some(pending) = cached.get_mut(&data) {
+            replay.hits += 1;
+            order.remove(&(*pending, data));
+            *pending = next;
+            order.insert((next, data));
+            continue;
+        }
+        if cached.len() == capacity {
+            // Not caching the block at all is best if it's needed last.
+            match order.last() {
+                Some(&(farthest, victim)) if farthest > next => {
+                    order.pop_last();
+                    cached.remove(&victim);
+                }
+                _ => continue,
+            }
+        }
+        cached.insert(data, next);
+        order.insert((next, data));
+    }
+    replay
+}
@@ -0,0 +1,195 @@
+//! Approximate counts of a trace in memory that doesn't grow with it, from
+//! one pass over the events as they're parsed: HyperLogLog for the distinct
+//! blocks and SSTs, and a Count-Min sketch for the most missed blocks and
+//! most evicted SSTs.
+
+use crate::model::{Data, Event, Op};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::SystemTime;
+
+/// Estimates the number of distinct values added, within about
+/// `1.04 / sqrt(2^precision)` of it.
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// With `2^precision` one byte registers, `precision` from 4 to 18.
+    pub fn new(precision: u32) -> Self {
+        let precision = precision.clamp(4, 18);
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn add(&mut self, value: impl Hash) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - self.precision)) as usize;
+        // The rest of the bits, with a stop bit so the rank stays in range.
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = (self.registers.iter())
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        // Linear counting is closer while many registers are still empty.
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// Columns of each row of a [`HeavyHitters`] sketch.
+const COUNT_MIN_WIDTH: usize = 1 << 16;
+
+/// Rows of a [`HeavyHitters`] sketch, each with its own hash of the keys.
+const COUNT_MIN_DEPTH: usize = 4;
+
+/// A key [`HeavyHitters`] counts, by at most `error` too many.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Heavy<K> {
+    pub key: K,
+    pub count: u64,
+    pub error: u64,
+}
+
+/// The most frequent keys: a Count-Min sketch estimating the count of every
+/// key, too high by at most `e / width` of all counted with probability
+/// `1 - e^-depth`, and the `candidates` keys estimated highest.
+#[derive(Debug, Clone)]
+pub struct HeavyHitters<K> {
+    candidates: usize,
+    rows: Vec<Vec<u32>>,
+    total: u64,
+    estimates: HashMap<K, u64>,
+    /// The candidates by estimate, to find the lowest one.
+    order: BTreeSet<(u64, K)>,
+}
+
+impl<K: Copy + Eq + Hash + Ord> HeavyHitters<K> {
+    pub fn new(candidates: usize) -> Self {
+        Self {
+            candidates: candidates.max(1),
+            rows: vec![vec![0; COUNT_MIN_WIDTH]; COUNT_MIN_DEPTH],
+            total: 0,
+            estimates: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: K) {
+        self.total += 1;
+        let mut estimate = u64::MAX;
+        for (seed, row) in self.rows.iter_mut().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            (seed, key).hash(&mut hasher);
+            let counter = &mut row[hasher.finish() as usize % COUNT_MIN_WIDTH];
+            *counter = counter.saturating_add(1);
+            estimate = estimate.min(u64::from(*counter));
+        }
+        if let Some(previous) = self.estimates.insert(key, estimate) {
+            self.order.remove(&(previous, key));
+        } else if self.estimates.len() > self.candidates {
+            // Only keys estimated higher than the lowest candidate get in.
+            let &(lowest, dropped) = self.order.first().unwrap();
+            if lowest >= estimate {
+                self.estimates.remove(&key);
+                return;
+            }
+            self.order.pop_first();
+            self.estimates.remove(&dropped);
+        }
+        self.order.insert((estimate, key));
+    }
+
+    /// The `n` keys estimated highest, highest first.
+    pub fn top(&self, n: usize) -> Vec<Heavy<K>> {
+        let error = (std::f64::consts::E * self.total as f64 / COUNT_MIN_WIDTH as f64).ceil();
+        (self.order.iter().rev().take(n))
+            .map(|&(count, key)| Heavy {
+                key,
+                count,
+                error: (error as u64).min(count - 1),
+            })
+            .collect()
+    }
+}
+
+/// Approximate [`crate::analysis::summary::Summary`] and top-K of the
+/// events seen, in any order.
+#[derive(Debug, Clone)]
+pub struct Sketch {
+    pub events: u64,
+    pub evicted: u64,
+    pub missed: u64,
+    pub inserted: u64,
+    pub hit: u64,
+    pub blocks: HyperLogLog,
+    pub ssts: HyperLogLog,
+    pub missed_blocks: HeavyHitters<Data>,
+    pub evicted_ssts: HeavyHitters<u64>,
+    /// Time of the oldest and newest event.
+    pub range: Option<(SystemTime, SystemTime)>,
+}
+
+impl Sketch {
+    /// With HyperLogLogs of `precision` and `candidates` per top-K.
+    pub fn new(precision: u32, candidates: usize) -> Self {
+        Self {
+            events: 0,
+            evicted: 0,
+            missed: 0,
+            inserted: 0,
+            hit: 0,
+            blocks: HyperLogLog::new(precision),
+            ssts: HyperLogLog::new(precision),
+            missed_blocks: HeavyHitters::new(candidates),
+            evicted_ssts: HeavyHitters::new(candidates),
+            range: None,
+        }
+    }
+
+    pub fn observe(&mut self, events: &[Event]) {
+        for event in events {
+            self.events += 1;
+            match event.op {
+                Op::Evicted => {
+                    self.evicted += 1;
+                    self.evicted_ssts.add(event.data.sst);
+                }
+                Op::Missed => {
+                    self.missed += 1;
+                    self.missed_blocks.add(event.data);
+                }
+                Op::Inserted => self.inserted += 1,
+                Op::Hit => self.hit += 1,
+            }
+            self.blocks.add(event.data);
+            self.ssts.add(event.data.sst);
+            self.range = Some(match self.range {
+                Some((first, last)) => (first.min(event.time), last.max(event.time)),
+                None => (event.time, event.time),
+            });
+        }
+    }
+}
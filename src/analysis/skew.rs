@@ -0,0 +1,213 @@
+//! Clock skew between the parts of a trace logged by different clocks, such
+//! as logs of one cache collected on several nodes.
+//!
+//! Unlike the rest of [`crate::analysis`], these take events in parse order,
+//! along with the clock each was logged by.
+
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Fewest blocks with misses or evictions by both clocks an offset is
+/// estimated from.
+const MIN_EVIDENCE: usize = 5;
+
+/// Offsets tried on either side of the best one so far in each round of
+/// [`detect_skew`]'s search.
+const STEPS: i64 = 32;
+
+/// The result of [`detect_skew`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Skews {
+    /// The clock with the most events, which the others are measured against.
+    pub reference: usize,
+    /// One per other clock with events, in clock order.
+    pub clocks: Vec<Skew>,
+}
+
+/// The estimated offset of one clock against the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skew {
+    pub clock: usize,
+    /// Blocks missed or evicted by both this clock and the reference.
+    pub blocks: usize,
+    /// Misses and evictions of those blocks out of turn as logged, see
+    /// [`detect_skew`].
+    pub out_of_turn: usize,
+    /// Of those, the ones left after correcting by the offset.
+    pub corrected_out_of_turn: usize,
+    /// How far the clock is ahead of the reference, in nanoseconds, if there
+    /// was enough evidence.
+    pub offset_nanos: Option<i64>,
+}
+
+/// Estimates clock offsets from the order of misses and evictions: between
+/// two misses of a block it has to have been evicted, and between two
+/// evictions missed, unless the clocks they were logged by disagree.
+/// `clocks[i]` is the clock of `events[i]`.
+///
+/// For each clock other than the reference, tries offsets at most `window`
+/// either way and keeps the one leaving the fewest misses and evictions out
+/// of turn in the two clocks' events, the smallest one on ties.
+pub fn detect_skew(events: &[Event], clocks: &[usize], window: Duration) -> Skews {
+    let mut per_clock: HashMap<usize, HashMap<Data, Vec<(i64, Op)>>> = HashMap::new();
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for (event, &clock) in events.iter().zip(clocks) {
+        *sizes.entry(clock).or_default() += 1;
+        if matches!(event.op, Op::Missed | Op::Evicted) {
+            (per_clock.entry(clock).or_default())
+                .entry(event.data)
+                .or_default()
+                .push((nanos(event.time), event.op));
+        }
+    }
+    let Some(reference) = (sizes.iter())
+        .max_by_key(|&(&clock, &size)| (size, std::cmp::Reverse(clock)))
+        .map(|(&clock, _)| clock)
+    else {
+        return Skews::default();
+    };
+    for blocks in per_clock.values_mut() {
+        for times in blocks.values_mut() {
+            times.sort_unstable();
+        }
+    }
+
+    let empty = HashMap::new();
+    let reference_blocks = per_clock.get(&reference).unwrap_or(&empty);
+    let mut others: Vec<_> = (sizes.into_keys())
+        .filter(|&clock| clock != reference)
+        .collect();
+    others.sort_unstable();
+    let clocks = (others.into_iter())
+        .map(|clock| {
+            let shared: Vec<_> = (per_clock.get(&clock).unwrap_or(&empty).iter())
+                .filter_map(|(data, times)| Some((reference_blocks.get(data)?, times)))
+                .collect();
+            let cost = |offset| {
+                (shared.iter())
+                    .map(|(reference, times)| out_of_turn(reference, times, offset))
+                    .sum::<usize>()
+            };
+            let logged = cost(0);
+            let offset = (shared.len() >= MIN_EVIDENCE)
+                .then(|| search(window.as_nanos() as i64, logged, cost));
+            Skew {
+                clock,
+                blocks: shared.len(),
+                out_of_turn: logged,
+                corrected_out_of_turn: offset.map_or(logged, cost),
+                offset_nanos: offset,
+            }
+        })
+        .collect();
+    Skews { reference, clocks }
+}
+
+/// The offset at most `range` either way minimizing `cost`, by narrowing a
+/// grid around the best offset so far. `at_zero` is the cost of no offset.
+fn search(range: i64, at_zero: usize, cost: impl Fn(i64) -> usize) -> i64 {
+    let (mut best, mut best_cost) = (0i64, at_zero);
+    let (mut center, mut half) = (0, range);
+    loop {
+        let step = (half / STEPS).max(1);
+        let mut offset = (center - half).max(-range);
+        while offset <= (center + half).min(range) {
+            let cost = cost(offset);
+            if (cost, offset.unsigned_abs()) < (best_cost, best.unsigned_abs()) {
+                (best, best_cost) = (offset, cost);
+            }
+            offset += step;
+        }
+        if step == 1 {
+            return best;
+        }
+        (center, half) = (best, step);
+    }
+}
+
+/// Misses following a miss and evictions following an eviction in the
+/// merged `reference` and `other` events of one block, once `other` is
+/// shifted back by `offset`.
+fn out_of_turn(reference: &[(i64, Op)], other: &[(i64, Op)], offset: i64) -> usize {
+    let (mut i, mut j) = (0, 0);
+    let mut last = None;
+    let mut count = 0;
+    while i < reference.len() || j < other.len() {
+        let op =
+            if j == other.len() || (i < reference.len() && reference[i].0 <= other[j].0 - offset) {
+                i += 1;
+                reference[i - 1].1
+            } else {
+                j += 1;
+                other[j - 1].1
+            };
+        if last == Some(op) {
+            count += 1;
+        }
+        last = Some(op);
+    }
+    count
+}
+
+/// Nanoseconds since the epoch, negative before it.
+fn nanos(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_nanos() as i64,
+        Err(before) => -(before.duration().as_nanos() as i64),
+    }
+}
+
+/// Shifts the events of each skewed clock back by its offset. Returns whether
+/// anything moved, after which the events need sorting again.
+pub fn correct_skew(events: &mut [Event], clocks: &[usize], skews: &Skews) -> bool {
+    let offsets: HashMap<usize, i64> = (skews.clocks.iter())
+        .filter_map(|skew| Some((skew.clock, skew.offset_nanos?)))
+        .filter(|&(_, offset)| offset != 0)
+        .collect();
+    for (event, clock) in events.iter_mut().zip(clocks) {
+        if let Some(&offset) = offsets.get(clock) {
+            let shift = Duration::from_nanos(offset.unsigned_abs());
+            event.time = if offset > 0 {
+                event.time.checked_sub(shift).unwrap_or(event.time)
+            } else {
+                event.time + shift
+            };
+        }
+    }
+    !offsets.is_empty()
+}
+
+/// Writes one line per [`Skew`] below a line naming the reference clock, by
+/// the names in `names`. Writes nothing without any clocks.
+pub fn write_skews(skews: &Skews, names: &[String], writer: &mut impl Write) -> io::Result<()> {
+    let Some(reference) = names.get(skews.reference) else {
+        return Ok(());
+    };
+    writeln!(writer, "Clock skew against {reference}:")?;
+    for skew in &skews.clocks {
+        let name = &names[skew.clock];
+        let Skew {
+            blocks,
+            out_of_turn,
+            corrected_out_of_turn,
+            ..
+        } = skew;
+        match skew.offset_nanos {
+            Some(offset) => {
+                let sign = if offset < 0 { "-" } else { "" };
+                let offset = Duration::from_nanos(offset.unsigned_abs());
+                writeln!(
+                    writer,
+                    "{name}: offset: {sign}{offset:?}, blocks: {blocks}, out of turn: {out_of_turn} -> {corrected_out_of_turn}"
+                )?
+            }
+            None => writeln!(
+                writer,
+                "{name}: offset: unknown, blocks: {blocks}, out of turn: {out_of_turn}"
+            )?,
+        }
+    }
+    Ok(())
+}
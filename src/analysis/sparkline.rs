@@ -0,0 +1,69 @@
+use crate::model::{Context, Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// Renders `counts` as a Unicode sparkline, with a blank for empty buckets.
+pub fn sparkline(counts: &[u64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => BARS[(count * (BARS.len() as u64 - 1) / max) as usize],
+        })
+        .collect()
+}
+
+/// Writes a miss timeline sparkline for each of the `top` most missed blocks,
+/// bucketed into `width` slices of the whole trace.
+pub fn write_sparklines(
+    events: &[Event],
+    context: &Context,
+    writer: &mut impl Write,
+    top: usize,
+    width: usize,
+) -> io::Result<()> {
+    // Events are sorted newest first.
+    let (Some(last), Some(first)) = (events.first(), events.last()) else {
+        return Ok(());
+    };
+    let width = width.max(1);
+    let (first, last) = (first.time, last.time);
+    let span = last.duration_since(first).unwrap();
+
+    let mut misses: HashMap<Data, Vec<SystemTime>> = HashMap::new();
+    for event in events {
+        if event.op == Op::Missed {
+            misses.entry(event.data).or_default().push(event.time);
+        }
+    }
+    let mut misses: Vec<_> = misses.into_iter().collect();
+    misses.sort_by_key(|(data, times)| (std::cmp::Reverse(times.len()), *data));
+
+    writeln!(
+        writer,
+        "{} - {}, {width} buckets of {:?}",
+        context.format_time(first),
+        context.format_time(last),
+        span / width as u32
+    )?;
+    for (data, times) in misses.into_iter().take(top) {
+        let mut counts = vec![0; width];
+        for time in &times {
+            let offset = time.duration_since(first).unwrap().as_secs_f64();
+            let bucket =
+                (offset / span.as_secs_f64().max(f64::MIN_POSITIVE) * width as f64) as usize;
+            counts[bucket.min(width - 1)] += 1;
+        }
+        writeln!(
+            writer,
+            "{:<28} |{}| {}",
+            context.data(data).to_string(),
+            sparkline(&counts),
+            times.len()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Nearest-rank percentile over an ascending, non-empty slice.
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Approximate quantiles of durations, using logarithmic buckets about 1%
+/// wide so memory stays small regardless of how many values are recorded.
+#[derive(Debug, Default)]
+pub struct LogHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+}
+
+impl LogHistogram {
+    const BASE: f64 = 1.01;
+
+    pub fn record(&mut self, duration: Duration) {
+        let nanos = duration.as_nanos().max(1) as f64;
+        let index = (nanos.ln() / Self::BASE.ln()) as usize;
+        if index >= self.buckets.len() {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The approximate `q` quantile, for `q` in `0.0..=1.0`.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let target = ((q * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                let nanos = Self::BASE.powf(index as f64 + 0.5);
+                return Some(Duration::from_nanos(nanos as u64));
+            }
+        }
+        None
+    }
+}
+
+/// Eviction-to-miss delta estimates updated while parsing, before the events
+/// are sorted. Misses are matched against the newest eviction seen so far, so
+/// the numbers are only a preview of the final duration pass.
+#[derive(Debug, Default)]
+pub struct StreamingStats {
+    evictions: HashMap<Data, SystemTime>,
+    pub deltas: LogHistogram,
+}
+
+impl StreamingStats {
+    pub fn observe(&mut self, events: &[Event]) {
+        for event in events {
+            match event.op {
+                Op::Evicted => {
+                    let evicted = self.evictions.entry(event.data).or_insert(event.time);
+                    *evicted = (*evicted).max(event.time);
+                }
+                Op::Missed => {
+                    if let Some(delta) = self
+                        .evictions
+                        .get(&event.data)
+                        .and_then(|evicted| event.time.duration_since(*evicted).ok())
+                    {
+                        self.deltas.record(delta);
+                    }
+                }
+                Op::Inserted | Op::Hit => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::analysis::analyzer::Analyzer;
+use crate::analysis::durations::{Counts, Matching, MissMatcher};
+use crate::analysis::warmup::Skipped;
+use crate::format::ts_nanos;
+use crate::model::{Context, Data, Event, Op};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// Overall counts of a trace.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub events: u64,
+    pub evicted: u64,
+    pub missed: u64,
+    pub inserted: u64,
+    pub hit: u64,
+    pub blocks: u64,
+    pub ssts: u64,
+    /// Time of the oldest and newest event.
+    pub range: Option<(SystemTime, SystemTime)>,
+    /// The warm-up skipped before counting, set by the caller.
+    pub warmup: Option<Skipped>,
+}
+
+/// Counts the events, distinct blocks and SSTs, and time span of `events`.
+pub fn summarize(events: &[Event]) -> Summary {
+    let mut summarizer = Summarizer::default();
+    for event in events {
+        summarizer.add(event);
+    }
+    summarizer.summary()
+}
+
+/// A [`Summary`] of the events added so far.
+#[derive(Debug, Default)]
+struct Summarizer {
+    summary: Summary,
+    blocks: HashSet<Data>,
+    ssts: HashSet<u64>,
+}
+
+impl Summarizer {
+    fn add(&mut self, event: &Event) {
+        let summary = &mut self.summary;
+        summary.events += 1;
+        match event.op {
+            Op::Evicted => summary.evicted += 1,
+            Op::Missed => summary.missed += 1,
+            Op::Inserted => summary.inserted += 1,
+            Op::Hit => summary.hit += 1,
+        }
+        self.blocks.insert(event.data);
+        self.ssts.insert(event.data.sst);
+        summary.range = Some(match summary.range {
+            Some((first, last)) => (first.min(event.time), last.max(event.time)),
+            None => (event.time, event.time),
+        });
+    }
+
+    fn summary(&self) -> Summary {
+        Summary {
+            blocks: self.blocks.len() as u64,
+            ssts: self.ssts.len() as u64,
+            ..self.summary.clone()
+        }
+    }
+}
+
+/// [`summarize`] and [`write_summary`] as an [`Analyzer`], with the misses
+/// matched for the long/short/none counts.
+#[derive(Debug)]
+pub struct SummaryAnalyzer {
+    summarizer: Summarizer,
+    matcher: MissMatcher,
+}
+
+impl SummaryAnalyzer {
+    pub fn new(matching: Matching) -> Self {
+        Self {
+            summarizer: Summarizer::default(),
+            matcher: MissMatcher::new(matching),
+        }
+    }
+}
+
+impl Analyzer for SummaryAnalyzer {
+    fn on_event(&mut self, event: &Event) {
+        self.summarizer.add(event);
+        self.matcher.on_event(event);
+    }
+
+    fn finish(&mut self, _: &Context, mut out: &mut dyn Write) -> io::Result<()> {
+        let (counts, _) = self.matcher.finish();
+        write_summary(&self.summarizer.summary(), counts, &mut out)
+    }
+}
+
+/// A [`Summary`] and its miss matching counts with the stable field names of
+/// the `--summary` JSON.
+#[derive(Debug, Serialize)]
+struct SummaryRecord {
+    events: u64,
+    ops: OpCounts,
+    unique_blocks: u64,
+    unique_ssts: u64,
+    first_ts_nanos: Option<u64>,
+    last_ts_nanos: Option<u64>,
+    long: u64,
+    short: u64,
+    none: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup_end_ts_nanos: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup_events: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpCounts {
+    evicted: u64,
+    missed: u64,
+    inserted: u64,
+    hit: u64,
+}
+
+/// Writes `summary` and `counts` as one pretty printed JSON object.
+pub fn write_summary(summary: &Summary, counts: Counts, writer: &mut impl Write) -> io::Result<()> {
+    let record = SummaryRecord {
+        events: summary.events,
+        ops: OpCounts {
+            evicted: summary.evicted,
+            missed: summary.missed,
+            inserted: summary.inserted,
+            hit: summary.hit,
+        },
+        unique_blocks: summary.blocks,
+        unique_ssts: summary.ssts,
+        first_ts_nanos: summary.range.map(|(first, _)| ts_nanos(first)),
+        last_ts_nanos: summary.range.map(|(_, last)| ts_nanos(last)),
+        long: counts.long,
+        short: counts.short,
+        none: counts.none,
+        warmup_end_ts_nanos: summary.warmup.map(|skipped| ts_nanos(skipped.end)),
+        warmup_events: summary.warmup.map(|skipped| skipped.events),
+    };
+    serde_json::to_writer_pretty(&mut *writer, &record)?;
+    writeln!(writer)
+}
@@ -0,0 +1,135 @@
+//! How long evicted blocks go until they're missed again, as a
+//! Kaplan–Meier survival curve. Unlike the long/short/none counts it keeps
+//! the evictions never followed by a miss, as censored observations: the
+//! block went at least that long without one.
+
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// The time from an eviction to the next miss of its block, or to when it
+/// stopped being observed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observation {
+    pub time: Duration,
+    /// Whether a miss ended it, rather than the block being evicted again
+    /// without one or the trace ending.
+    pub re_missed: bool,
+}
+
+/// One [`Observation`] per eviction in `events`, which are sorted newest
+/// first.
+pub fn observations(events: &[Event]) -> Vec<Observation> {
+    let mut observations = vec![];
+    let mut evicted: HashMap<Data, SystemTime> = HashMap::new();
+    let since = |from: SystemTime, to: SystemTime| to.duration_since(from).unwrap_or_default();
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Evicted => {
+                if let Some(previous) = evicted.insert(event.data, event.time) {
+                    observations.push(Observation {
+                        time: since(previous, event.time),
+                        re_missed: false,
+                    });
+                }
+            }
+            Op::Missed => {
+                if let Some(at) = evicted.remove(&event.data) {
+                    observations.push(Observation {
+                        time: since(at, event.time),
+                        re_missed: true,
+                    });
+                }
+            }
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+    if let Some(end) = events.first().map(|event| event.time) {
+        observations.extend(evicted.into_values().map(|at| Observation {
+            time: since(at, end),
+            re_missed: false,
+        }));
+    }
+    observations
+}
+
+/// The survival curve at one time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub time: Duration,
+    /// Observations lasting at least until `time`.
+    pub at_risk: u64,
+    /// Observations ended by a miss, and censored ones, up to `time`.
+    pub re_missed: u64,
+    pub censored: u64,
+    /// The probability of a block not being missed again within `time`
+    /// of its eviction.
+    pub survival: f64,
+}
+
+/// The Kaplan–Meier estimate of the survival curve of `observations` at
+/// each multiple of `step`, up to the longest observation.
+pub fn kaplan_meier(mut observations: Vec<Observation>, step: Duration) -> Vec<Point> {
+    observations.sort_by_key(|observation| observation.time);
+    let Some(max) = observations.last().map(|observation| observation.time) else {
+        return vec![];
+    };
+    let step = step.max(Duration::from_nanos(1));
+    let mut points = vec![];
+    let mut survival = 1.0;
+    let (mut re_missed, mut censored) = (0, 0);
+    let mut at = 0;
+    let mut bound = Duration::ZERO;
+    loop {
+        // Each distinct time with misses scales the survival by the share
+        // of those at risk without one.
+        while at < observations.len() && observations[at].time <= bound {
+            let time = observations[at].time;
+            let at_risk = (observations.len() - at) as f64;
+            let mut ended = 0;
+            while at < observations.len() && observations[at].time == time {
+                if observations[at].re_missed {
+                    ended += 1;
+                    re_missed += 1;
+                } else {
+                    censored += 1;
+                }
+                at += 1;
+            }
+            survival *= 1.0 - ended as f64 / at_risk;
+        }
+        points.push(Point {
+            time: bound,
+            at_risk: observations[at..].len() as u64,
+            re_missed,
+            censored,
+            survival,
+        });
+        if bound >= max {
+            return points;
+        }
+        bound += step;
+    }
+}
+
+/// Writes the [`kaplan_meier`] curve of `events` as CSV.
+pub fn write_survival(events: &[Event], step: Duration, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        writer,
+        "secs,at_risk,re_missed,censored,survival,re_miss_probability"
+    )?;
+    for point in kaplan_meier(observations(events), step) {
+        writeln!(
+            writer,
+            "{},{},{},{},{:.6},{:.6}",
+            point.time.as_secs_f64(),
+            point.at_risk,
+            point.re_missed,
+            point.censored,
+            point.survival,
+            1.0 - point.survival
+        )?;
+    }
+    Ok(())
+}
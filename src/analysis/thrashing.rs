@@ -0,0 +1,77 @@
+use crate::analysis::stats::percentile;
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// A block that was evicted and missed again over and over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thrashing {
+    pub data: Data,
+    /// Number of evicted-then-missed cycles in the whole trace.
+    pub cycles: usize,
+    /// Most cycles completed within one window.
+    pub peak: usize,
+    /// Median time from an eviction to the miss completing its cycle.
+    pub median: Duration,
+}
+
+/// Finds the blocks completing more than `threshold` evicted-then-missed
+/// cycles within some `window`, the most cycles in a window first.
+///
+/// A cycle is an eviction followed by a miss of the same block, and is
+/// placed in time at the miss.
+pub fn find_thrashing(events: &[Event], threshold: usize, window: Duration) -> Vec<Thrashing> {
+    // The (eviction, miss) times of every cycle, per block.
+    let mut cycles: HashMap<Data, Vec<(SystemTime, SystemTime)>> = HashMap::new();
+    let mut evicted: HashMap<Data, SystemTime> = HashMap::new();
+    for event in events.iter().rev() {
+        match event.op {
+            Op::Evicted => {
+                evicted.insert(event.data, event.time);
+            }
+            Op::Missed => {
+                if let Some(eviction) = evicted.remove(&event.data) {
+                    cycles
+                        .entry(event.data)
+                        .or_default()
+                        .push((eviction, event.time));
+                }
+            }
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+
+    let mut thrashing: Vec<_> = cycles
+        .into_iter()
+        .filter_map(|(data, cycles)| {
+            let mut start = 0;
+            let mut peak = 0;
+            for end in 0..cycles.len() {
+                while cycles[end]
+                    .1
+                    .duration_since(cycles[start].1)
+                    .unwrap_or_default()
+                    > window
+                {
+                    start += 1;
+                }
+                peak = peak.max(end - start + 1);
+            }
+            if peak <= threshold {
+                return None;
+            }
+            let mut durations: Vec<_> = (cycles.iter())
+                .map(|(eviction, miss)| miss.duration_since(*eviction).unwrap_or_default())
+                .collect();
+            durations.sort();
+            Some(Thrashing {
+                data,
+                cycles: cycles.len(),
+                peak,
+                median: percentile(&durations, 50.0),
+            })
+        })
+        .collect();
+    thrashing.sort_by_key(|thrashing| (std::cmp::Reverse(thrashing.peak), thrashing.data));
+    thrashing
+}
@@ -0,0 +1,43 @@
+use crate::model::{Context, Data, Event};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// The events of each block, oldest first, in block order.
+pub fn timelines(events: &[Event]) -> BTreeMap<Data, Vec<Event>> {
+    let mut timelines: BTreeMap<Data, Vec<Event>> = BTreeMap::new();
+    // Events are sorted newest first, walk them oldest first.
+    for event in events.iter().rev() {
+        timelines.entry(event.data).or_default().push(*event);
+    }
+    timelines
+}
+
+/// Writes the [`timelines`] of `events`, one line per event with the time
+/// since the block's previous event.
+pub fn write_timelines(
+    events: &[Event],
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    for (data, timeline) in timelines(events) {
+        writeln!(writer, "{}, events: {}", context.data(data), timeline.len())?;
+        let mut previous = None;
+        for event in timeline {
+            write!(
+                writer,
+                "    {}, {:?}",
+                context.format_time(event.time),
+                event.op
+            )?;
+            match previous {
+                Some(previous) => {
+                    let delta = event.time.duration_since(previous).unwrap_or_default();
+                    writeln!(writer, ", +{delta:?}")?;
+                }
+                None => writeln!(writer)?,
+            }
+            previous = Some(event.time);
+        }
+    }
+    Ok(())
+}
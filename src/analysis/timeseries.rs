@@ -0,0 +1,119 @@
+use crate::analysis::analyzer::Analyzer;
+use crate::model::{Context, Event, Op};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Event counts of one time bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    pub start: SystemTime,
+    pub evicted: u64,
+    pub missed: u64,
+    pub hit: u64,
+}
+
+impl Bucket {
+    /// The share of the accesses that hit, if there were any.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let accesses = self.hit + self.missed;
+        (accesses > 0).then(|| self.hit as f64 / accesses as f64)
+    }
+}
+
+/// Counts events per `width` wide bucket, aligned to multiples of `width`
+/// since the Unix epoch, oldest first. Buckets without events are left out,
+/// so that far apart events don't fill memory with empty ones.
+pub fn buckets(events: &[Event], width: Duration) -> Vec<Bucket> {
+    let mut counts = Counts::new(width);
+    for event in events {
+        counts.add(event);
+    }
+    counts.buckets.into_values().collect()
+}
+
+/// The non-empty buckets of the events added so far, in whatever order.
+#[derive(Debug, Clone)]
+struct Counts {
+    width: u128,
+    /// By their index since the Unix epoch.
+    buckets: BTreeMap<u128, Bucket>,
+}
+
+impl Counts {
+    fn new(width: Duration) -> Self {
+        Self {
+            width: width.as_nanos().max(1),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, event: &Event) {
+        let index = event.time.duration_since(UNIX_EPOCH).unwrap().as_nanos() / self.width;
+        let width = self.width;
+        let bucket = self.buckets.entry(index).or_insert_with(|| Bucket {
+            start: UNIX_EPOCH + Duration::from_nanos((index * width) as u64),
+            evicted: 0,
+            missed: 0,
+            hit: 0,
+        });
+        match event.op {
+            Op::Evicted => bucket.evicted += 1,
+            Op::Missed => bucket.missed += 1,
+            Op::Hit => bucket.hit += 1,
+            Op::Inserted => {}
+        }
+    }
+}
+
+/// Writes [`buckets`] as CSV, with the hit rate of each, empty for buckets
+/// without accesses.
+pub fn write_timeseries(
+    events: &[Event],
+    width: Duration,
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    write_buckets(&buckets(events, width), context, writer)
+}
+
+fn write_buckets(buckets: &[Bucket], context: &Context, writer: &mut dyn Write) -> io::Result<()> {
+    writeln!(writer, "start,evictions,misses,hits,hit_rate")?;
+    for bucket in buckets {
+        let hit_rate = (bucket.hit_rate()).map_or(String::new(), |rate| format!("{rate:.4}"));
+        writeln!(
+            writer,
+            "{},{},{},{},{hit_rate}",
+            context.format_time(bucket.start),
+            bucket.evicted,
+            bucket.missed,
+            bucket.hit
+        )?;
+    }
+    Ok(())
+}
+
+/// [`write_timeseries`] as an [`Analyzer`].
+#[derive(Debug, Clone)]
+pub struct TimeseriesAnalyzer {
+    counts: Counts,
+}
+
+impl TimeseriesAnalyzer {
+    pub fn new(width: Duration) -> Self {
+        Self {
+            counts: Counts::new(width),
+        }
+    }
+}
+
+impl Analyzer for TimeseriesAnalyzer {
+    fn on_event(&mut self, event: &Event) {
+        self.counts.add(event);
+    }
+
+    fn finish(&mut self, context: &Context, out: &mut dyn Write) -> io::Result<()> {
+        let buckets: Vec<_> = self.counts.buckets.values().copied().collect();
+        write_buckets(&buckets, context, out)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::analysis::analyzer::Analyzer;
+use crate::model::{Context, Data, Event, Op};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// How often a key showed up, and when it did first and last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hot<K> {
+    pub key: K,
+    pub count: u64,
+    pub first: SystemTime,
+    pub last: SystemTime,
+}
+
+/// Counts how often each key shows up, one event at a time.
+#[derive(Debug, Clone)]
+struct HotCounter<K> {
+    hot: HashMap<K, Hot<K>>,
+}
+
+impl<K: Copy + Eq + Hash + Ord> HotCounter<K> {
+    fn new() -> Self {
+        Self {
+            hot: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, key: K, time: SystemTime) {
+        let entry = self.hot.entry(key).or_insert(Hot {
+            key,
+            count: 0,
+            first: time,
+            last: time,
+        });
+        entry.count += 1;
+        entry.first = entry.first.min(time);
+        entry.last = entry.last.max(time);
+    }
+
+    /// The `n` keys counted most, most frequent first.
+    fn top(&self, n: usize) -> Vec<Hot<K>> {
+        let mut hot: Vec<_> = self.hot.values().copied().collect();
+        hot.sort_by_key(|hot| (Reverse(hot.count), hot.key));
+        hot.truncate(n);
+        hot
+    }
+}
+
+/// The `n` keys with the most `op` events, most frequent first.
+fn top<K: Copy + Eq + Hash + Ord>(
+    events: &[Event],
+    op: Op,
+    key: impl Fn(&Event) -> K,
+    n: usize,
+) -> Vec<Hot<K>> {
+    let mut counter = HotCounter::new();
+    for event in events.iter().filter(|event| event.op == op) {
+        counter.add(key(event), event.time);
+    }
+    counter.top(n)
+}
+
+/// The `n` most missed blocks.
+pub fn top_missed_blocks(events: &[Event], n: usize) -> Vec<Hot<Data>> {
+    top(events, Op::Missed, |event| event.data, n)
+}
+
+/// The `n` SSTs with the most evictions.
+pub fn top_evicted_ssts(events: &[Event], n: usize) -> Vec<Hot<u64>> {
+    top(events, Op::Evicted, |event| event.data.sst, n)
+}
+
+/// Writes the `n` most missed blocks and SSTs with the most evictions of
+/// [`top_missed_blocks`] and [`top_evicted_ssts`].
+pub fn write_topk(
+    blocks: &[Hot<Data>],
+    ssts: &[Hot<u64>],
+    n: usize,
+    context: &Context,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    writeln!(writer, "Top {n} blocks by misses:")?;
+    for hot in blocks {
+        writeln!(
+            writer,
+            "{}, misses: {}, first: {}, last: {}",
+            context.data(hot.key),
+            hot.count,
+            context.format_time(hot.first),
+            context.format_time(hot.last)
+        )?;
+    }
+    writeln!(writer, "Top {n} SSTs by evictions:")?;
+    for hot in ssts {
+        writeln!(
+            writer,
+            "sst: {}, evictions: {}, first: {}, last: {}",
+            hot.key,
+            hot.count,
+            context.format_time(hot.first),
+            context.format_time(hot.last)
+        )?;
+    }
+    Ok(())
+}
+
+/// [`write_topk`] as an [`Analyzer`].
+#[derive(Debug, Clone)]
+pub struct TopkAnalyzer {
+    n: usize,
+    blocks: HotCounter<Data>,
+    ssts: HotCounter<u64>,
+}
+
+impl TopkAnalyzer {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            blocks: HotCounter::new(),
+            ssts: HotCounter::new(),
+        }
+    }
+}
+
+impl Analyzer for TopkAnalyzer {
+    fn on_event(&mut self, event: &Event) {
+        match event.op {
+            Op::Missed => self.blocks.add(event.data, event.time),
+            Op::Evicted => self.ssts.add(event.data.sst, event.time),
+            Op::Inserted | Op::Hit => {}
+        }
+    }
+
+    fn finish(&mut self, context: &Context, out: &mut dyn Write) -> io::Result<()> {
+        let (blocks, ssts) = (self.blocks.top(self.n), self.ssts.top(self.n));
+        write_topk(&blocks, &ssts, self.n, context, out)
+    }
+}
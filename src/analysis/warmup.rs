@@ -0,0 +1,61 @@
+//! The start of a trace while the cache fills up, where every first access
+//! misses. Left in, it dominates the miss counts and makes runs of different
+//! lengths incomparable.
+
+use crate::model::{Event, Op};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// How long the warm-up lasts, given as a duration such as `5m` or
+/// `until-first-eviction`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warmup {
+    /// This long after the oldest event.
+    For(Duration),
+    /// Until the cache is full, when it evicts the first time.
+    UntilFirstEviction,
+}
+
+impl FromStr for Warmup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "until-first-eviction" => Ok(Warmup::UntilFirstEviction),
+            _ => humantime::parse_duration(s)
+                .map(Warmup::For)
+                .map_err(|e| format!("{s}: {e}, expected a duration or until-first-eviction")),
+        }
+    }
+}
+
+/// The events skipped as warm-up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skipped {
+    /// The first time kept.
+    pub end: SystemTime,
+    pub events: u64,
+}
+
+/// The time `warmup` of `events`, in any order, ends at, if they have one:
+/// none without events, or without evictions for
+/// [`Warmup::UntilFirstEviction`].
+pub fn warmup_end(events: &[Event], warmup: Warmup) -> Option<SystemTime> {
+    match warmup {
+        Warmup::For(duration) => Some(events.iter().map(|event| event.time).min()? + duration),
+        Warmup::UntilFirstEviction => (events.iter())
+            .filter(|event| event.op == Op::Evicted)
+            .map(|event| event.time)
+            .min(),
+    }
+}
+
+/// Drops the events before `end`, keeping the order of the rest.
+pub fn skip_warmup(events: &mut Vec<Event>, end: SystemTime) -> Skipped {
+    let before = events.len();
+    events.retain(|event| event.time >= end);
+    Skipped {
+        end,
+        events: (before - events.len()) as u64,
+    }
+}
@@ -0,0 +1,70 @@
+use crate::model::{Context, Data, Event};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Distinct blocks accessed within one window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start: SystemTime,
+    pub blocks: u64,
+}
+
+/// Counts the distinct blocks missed or hit in each `width` wide window.
+/// Windows start `step` apart, aligned to multiples of `step` since the Unix
+/// epoch, from the last one holding the first access to the one holding the
+/// last access.
+pub fn working_sets(events: &[Event], width: Duration, step: Duration) -> Vec<Window> {
+    let nanos = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let (width, step) = (width.as_nanos().max(1), step.as_nanos().max(1));
+    // Events are sorted newest first, walk them oldest first.
+    let accesses: Vec<_> = (events.iter().rev())
+        .filter(|event| event.op.is_access())
+        .map(|event| (nanos(event.time), event.data))
+        .collect();
+    let (Some(&(first, _)), Some(&(last, _))) = (accesses.first(), accesses.last()) else {
+        return vec![];
+    };
+
+    let mut windows = vec![];
+    let mut counts: HashMap<Data, u64> = HashMap::new();
+    let (mut head, mut tail) = (0, 0);
+    let mut start = (first.saturating_sub(width - 1)).div_ceil(step) * step;
+    while start <= last {
+        let end = start + width;
+        while head < accesses.len() && accesses[head].0 < end {
+            *counts.entry(accesses[head].1).or_default() += 1;
+            head += 1;
+        }
+        while tail < head && accesses[tail].0 < start {
+            let data = accesses[tail].1;
+            let count = counts.get_mut(&data).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&data);
+            }
+            tail += 1;
+        }
+        windows.push(Window {
+            start: UNIX_EPOCH + Duration::from_nanos(start as u64),
+            blocks: counts.len() as u64,
+        });
+        start += step;
+    }
+    windows
+}
+
+/// Writes [`working_sets`] as CSV.
+pub fn write_working_sets(
+    events: &[Event],
+    width: Duration,
+    step: Duration,
+    context: &Context,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "start,blocks")?;
+    for Window { start, blocks } in working_sets(events, width, step) {
+        writeln!(writer, "{},{blocks}", context.format_time(start))?;
+    }
+    Ok(())
+}
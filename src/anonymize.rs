@@ -0,0 +1,103 @@
+//! Pseudonymous traces, with the production identifiers and wall-clock
+//! times taken out so they can be shared.
+
+use crate::format::ts_nanos;
+use crate::model::{Context, Data, Event};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::UNIX_EPOCH;
+
+/// How [`anonymize`] rewrote a trace, to map results on it back to the
+/// original.
+#[derive(Debug, Default, Serialize)]
+pub struct Mapping {
+    /// Time of the earliest event, which became the Unix epoch, in
+    /// nanoseconds since the epoch.
+    pub origin_nanos: u64,
+    /// One per block, by anonymized SST id and block index.
+    pub blocks: Vec<BlockMapping>,
+    /// One per source, by anonymized name.
+    pub sources: Vec<SourceMapping>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockMapping {
+    pub sst_id: u64,
+    pub block_idx: u64,
+    pub anonymized_sst_id: u64,
+    pub anonymized_block_idx: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceMapping {
+    pub source: String,
+    pub anonymized_source: String,
+}
+
+/// Renumbers the SSTs of `events` densely from 0 in the order they first
+/// show up in time, and the blocks of each SST the same way, renames their
+/// sources `source-0`, `source-1` and so on, and shifts their times so
+/// that the earliest is the Unix epoch. A block keeps its new id across
+/// sources and key kinds, like it keeps its original one. Key kinds are
+/// named by the schema, so they keep their names.
+pub fn anonymize(events: &mut [Event], context: &Context) -> Mapping {
+    let Some(origin) = events.iter().map(|event| event.time).min() else {
+        return Mapping::default();
+    };
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_unstable_by_key(|&i| (events[i].time, events[i].data, events[i].op));
+
+    let mut ssts: HashMap<u64, (u64, u64)> = HashMap::new();
+    let mut blocks: HashMap<(u64, u64), Data> = HashMap::new();
+    let mut sources: HashMap<u32, u32> = HashMap::from([(0, 0)]);
+    for i in order {
+        let event = &mut events[i];
+        let Data {
+            sst,
+            blk,
+            source,
+            kind,
+        } = event.data;
+        let next_sst = ssts.len() as u64;
+        let data = *blocks.entry((sst, blk)).or_insert_with(|| {
+            let (sst, blocks) = ssts.entry(sst).or_insert((next_sst, 0));
+            *blocks += 1;
+            Data::new(*sst, *blocks - 1)
+        });
+        let next_source = sources.len() - 1;
+        let source = *(sources.entry(source))
+            .or_insert_with(|| context.source_id(&format!("source-{next_source}")));
+        event.data = Data {
+            source,
+            kind,
+            ..data
+        };
+        event.time = UNIX_EPOCH + event.time.duration_since(origin).unwrap();
+    }
+
+    let mut blocks: Vec<_> = (blocks.into_iter())
+        .map(|((sst_id, block_idx), data)| BlockMapping {
+            sst_id,
+            block_idx,
+            anonymized_sst_id: data.sst,
+            anonymized_block_idx: data.blk,
+        })
+        .collect();
+    blocks.sort_unstable_by_key(|block| (block.anonymized_sst_id, block.anonymized_block_idx));
+    let mut sources: Vec<_> = sources
+        .into_iter()
+        .filter(|&(source, _)| source != 0)
+        .collect();
+    sources.sort_unstable_by_key(|&(_, anonymized)| anonymized);
+    let sources = (sources.into_iter())
+        .map(|(source, anonymized)| SourceMapping {
+            source: context.source_name(source).unwrap().to_string(),
+            anonymized_source: context.source_name(anonymized).unwrap().to_string(),
+        })
+        .collect();
+    Mapping {
+        origin_nanos: ts_nanos(origin),
+        blocks,
+        sources,
+    }
+}
@@ -0,0 +1,108 @@
+//! Listing and reading input files on a tokio runtime, many at once, ahead of
+//! the threads parsing them. For directories of many small files, where
+//! opening and reading them takes longer than parsing.
+
+use std::fs::FileType;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+use tokio::sync::{Semaphore, mpsc};
+use tokio::task::JoinSet;
+
+/// Drives the listings and reads, started on first use.
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("can't start the I/O runtime")
+    })
+}
+
+/// The entries in `dir`, and in its subdirectories if `recursive`, with
+/// whether each is a regular file, in the order of a walk sorted by file
+/// name. Directories that can't be read are in there too, with the error.
+/// Up to `concurrency` directories are read at once.
+pub fn list(dir: PathBuf, recursive: bool, concurrency: usize) -> Vec<(PathBuf, io::Result<bool>)> {
+    runtime().block_on(async move {
+        let permits = Arc::new(Semaphore::new(concurrency));
+        let mut listings = JoinSet::new();
+        listings.spawn(read_dir(dir, permits.clone()));
+        let mut listed = vec![];
+        while let Some(listing) = listings.join_next().await {
+            let (dir, entries) = listing.expect("listing a directory panicked");
+            match entries {
+                Ok(entries) => {
+                    for (path, file_type) in entries {
+                        if recursive && file_type.is_dir() {
+                            listings.spawn(read_dir(path.clone(), permits.clone()));
+                        }
+                        listed.push((path, Ok(file_type.is_file())));
+                    }
+                }
+                Err(e) => listed.push((dir, Err(e))),
+            }
+        }
+        // Paths compare by component, so this puts every directory's entries
+        // right after it. The sort is stable, so a directory that can't be
+        // read stays before its error.
+        listed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        listed
+    })
+}
+
+/// The entries of `dir` and their types, not following symlinks.
+async fn read_dir(
+    dir: PathBuf,
+    permits: Arc<Semaphore>,
+) -> (PathBuf, io::Result<Vec<(PathBuf, FileType)>>) {
+    let _permit = permits.acquire().await.unwrap();
+    let entries = async {
+        let mut entries = vec![];
+        let mut listing = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = listing.next_entry().await? {
+            entries.push((entry.path(), entry.file_type().await?));
+        }
+        Ok(entries)
+    }
+    .await;
+    (dir, entries)
+}
+
+/// The contents of the files at `paths`, in order, read with up to
+/// `concurrency` reads ahead of the one asked for next.
+pub fn read_ahead(
+    paths: Vec<PathBuf>,
+    concurrency: usize,
+) -> impl Iterator<Item = io::Result<Vec<u8>>> {
+    let (reads, mut pending) = mpsc::channel(concurrency.max(1));
+    runtime().spawn(async move {
+        for path in paths {
+            // Stops once the contents aren't wanted anymore.
+            if reads.send(tokio::spawn(read(path))).await.is_err() {
+                return;
+            }
+        }
+    });
+    std::iter::from_fn(move || {
+        let read = pending.blocking_recv()?;
+        Some(
+            runtime()
+                .block_on(read)
+                .unwrap_or_else(|e| Err(io::Error::other(e))),
+        )
+    })
+}
+
+/// The whole file at `path`, which may also be an object storage URL.
+async fn read(path: PathBuf) -> io::Result<Vec<u8>> {
+    #[cfg(feature = "s3")]
+    if let Some(url) = path.to_str()
+        && url.starts_with(crate::parse::S3_SCHEME)
+    {
+        return crate::s3::read(url).await;
+    }
+    tokio::fs::read(path).await
+}
@@ -0,0 +1,164 @@
+//! Output written by a background thread, so that formatting records and
+//! writing them to disk overlap.
+
+use crossbeam_channel::{Sender, bounded};
+use flate2::write::GzEncoder;
+use std::io::{self, Write};
+use std::thread::{self, JoinHandle};
+
+/// Bytes buffered before they are handed to the writer thread.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks in flight before [`BackgroundWriter::write`] blocks on the writer
+/// thread.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Compression of an output file.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Compress {
+    Gzip,
+    Zstd,
+}
+
+impl Compress {
+    /// The file name suffix for it, such as `.gz`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compress::Gzip => ".gz",
+            Compress::Zstd => ".zst",
+        }
+    }
+}
+
+/// A writer compressing what it's given, if at all.
+enum Compressor<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Compressor<W> {
+    fn new(writer: W, compress: Option<Compress>) -> io::Result<Self> {
+        Ok(match compress {
+            None => Compressor::Plain(writer),
+            Some(Compress::Gzip) => {
+                Compressor::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Some(Compress::Zstd) => Compressor::Zstd(zstd::Encoder::new(writer, 0)?),
+        })
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Compressor::Plain(writer) => writer.write_all(bytes),
+            Compressor::Gzip(writer) => writer.write_all(bytes),
+            Compressor::Zstd(writer) => writer.write_all(bytes),
+        }
+    }
+
+    /// Ends the compressed stream and flushes the underlying writer.
+    fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            Compressor::Plain(writer) => writer,
+            Compressor::Gzip(writer) => writer.finish()?,
+            Compressor::Zstd(writer) => writer.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+/// Buffers writes into chunks and sends them over a bounded channel to a
+/// dedicated thread owning the underlying writer.
+///
+/// Errors of the writer thread surface on a later write or flush, and on
+/// [`BackgroundWriter::finish`], which should be called to know that
+/// everything was written. Dropping the writer waits for the thread and
+/// ignores its errors, like [`std::io::BufWriter`].
+pub struct BackgroundWriter {
+    buf: Vec<u8>,
+    sender: Option<Sender<Vec<u8>>>,
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl BackgroundWriter {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self::compressed(writer, None)
+    }
+
+    /// Like [`BackgroundWriter::new`], compressing on the writer thread.
+    pub fn compressed(writer: impl Write + Send + 'static, compress: Option<Compress>) -> Self {
+        let (sender, receiver) = bounded::<Vec<u8>>(CHANNEL_CAPACITY);
+        let thread = thread::spawn(move || {
+            let mut writer = Compressor::new(writer, compress)?;
+            for chunk in receiver {
+                writer.write_all(&chunk)?;
+            }
+            writer.finish()
+        });
+        Self {
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Writes the buffered bytes and waits for the writer thread to finish.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.send()?;
+        self.join()
+    }
+
+    /// Hands the buffered bytes to the writer thread.
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        let sent = self.sender.as_ref().map(|sender| sender.send(chunk));
+        match sent {
+            Some(Ok(())) => Ok(()),
+            // The thread only hangs up early when it failed.
+            _ => Err(self.join().err().unwrap_or_else(stopped)),
+        }
+    }
+
+    /// Closes the channel and returns the writer thread's result.
+    fn join(&mut self) -> io::Result<()> {
+        self.sender = None;
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| io::Error::other("writer thread panicked"))?,
+            None => Err(stopped()),
+        }
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::other("writer thread already stopped")
+}
+
+impl Write for BackgroundWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(bytes);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(bytes.len())
+    }
+
+    /// Only hands the buffered bytes over, without waiting for them to be
+    /// written.
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+impl Drop for BackgroundWriter {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            let _ = self.send();
+            let _ = self.join();
+        }
+    }
+}
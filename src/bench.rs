@@ -0,0 +1,80 @@
+//! The synthetic trace and the stages `blocks bench` times, shared with the
+//! criterion benches, so that their numbers compare across versions.
+
+use crate::analysis::durations::{Counts, Matching, intervals};
+use crate::analysis::summary::{Summary, summarize};
+use crate::analysis::topk::{top_evicted_ssts, top_missed_blocks};
+use crate::cache::Policy;
+use crate::generate::{Generator, Workload, write_csv};
+use crate::model::{Context, Event};
+use crate::parse::{Error, ParseOptions, Schema, TimeField, parse_dir};
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Cache size of the benchmarked trace, in blocks.
+const CAPACITY: usize = 1000;
+
+/// Blocks and SSTs listed by [`analyze`].
+const TOP: usize = 10;
+
+/// Writes the trace of `accesses` accesses of the default `blocks generate`
+/// workload to `files` CSV files in `dir`, the same records each time.
+/// Returns the number of records.
+pub fn write_trace(dir: &Path, accesses: u64, files: usize) -> io::Result<u64> {
+    let workload = Workload {
+        blocks: 10_000,
+        blocks_per_sst: 16,
+        zipf: 0.9,
+        rate: 1000.0,
+        accesses,
+        // 2024-01-01T00:00:00Z.
+        start: UNIX_EPOCH + Duration::from_secs(1_704_067_200),
+        all_ops: false,
+        seed: 0,
+    };
+    let events: Vec<Event> = Generator::new(workload, Policy::Lru.build(CAPACITY)).collect();
+    let per_file = events.len().div_ceil(files.max(1)).max(1);
+    for (number, part) in events.chunks(per_file).enumerate() {
+        let file = fs::File::create(dir.join(format!("part{number:04}.csv")))?;
+        write_csv(part.iter().copied(), 32, BufWriter::new(file))?;
+    }
+    Ok(events.len() as u64)
+}
+
+/// Parses the trace in `dir`, with `threads` parsing threads or all cores.
+pub fn parse(dir: &Path, threads: Option<usize>) -> Result<Vec<Event>, Error> {
+    let schema = Schema::builtin(TimeField::Systime);
+    let options = ParseOptions {
+        threads,
+        ..ParseOptions::default()
+    };
+    Ok(parse_dir(dir, &schema, &options, &Context::default(), &mut ())?.events)
+}
+
+/// The result of [`analyze`].
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub summary: Summary,
+    pub counts: Counts,
+    pub deltas: usize,
+    pub hot: usize,
+}
+
+/// What `blocks analyze` does with the parsed events by default: sorts them
+/// newest first, summarizes them, matches the misses to their evictions and
+/// finds the most missed blocks and most evicted SSTs.
+pub fn analyze(mut events: Vec<Event>) -> Analysis {
+    events.sort_by_key(|event| (Reverse(event.time), event.data, event.op));
+    let summary = summarize(&events);
+    let (counts, deltas) = intervals(&events, Matching::default());
+    let hot = top_missed_blocks(&events, TOP).len() + top_evicted_ssts(&events, TOP).len();
+    Analysis {
+        summary,
+        counts,
+        deltas: deltas.len(),
+        hot,
+    }
+}
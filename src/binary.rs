@@ -0,0 +1,293 @@
+//! A compact binary encoding of events, much faster to read back than the
+//! logs they were parsed from.
+//!
+//! A file starts with [`MAGIC`] and the [`VERSION`] byte, followed by one
+//! record per event: the op as a byte in [`Op::ALL`] order, the SST id, block
+//! index, source and key kind as varints, and the time as a zigzag varint of
+//! the nanoseconds since the previous event's time. Before the first event
+//! of a source or key kind other than 0, a record of [`SOURCE_NAME`] or
+//! [`KIND_NAME`] gives its id and name, as the length and UTF-8 bytes.
+//! Version 1 files have neither sources nor key kinds.
+
+use crate::format::ts_nanos;
+use crate::model::{Context, Data, Event, Op, Origin};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+pub const MAGIC: &[u8] = b"BLKS";
+
+/// Version of the encoding written, which follows [`MAGIC`].
+pub const VERSION: u8 = 2;
+
+/// Record byte naming a source id.
+pub const SOURCE_NAME: u8 = 0x10;
+
+/// Record byte naming a key kind id.
+pub const KIND_NAME: u8 = 0x11;
+
+/// Writes events in the binary encoding, with the names of their sources
+/// and key kinds in `context`.
+#[derive(Debug)]
+pub struct BinaryWriter<'a, W: Write> {
+    writer: W,
+    last: u64,
+    buf: Vec<u8>,
+    context: &'a Context,
+    /// Whether each source id has been named yet, by id.
+    named_sources: Vec<bool>,
+    named_kinds: Vec<bool>,
+}
+
+impl<'a, W: Write> BinaryWriter<'a, W> {
+    pub fn new(mut writer: W, context: &'a Context) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        Ok(Self {
+            writer,
+            last: 0,
+            buf: Vec::with_capacity(32),
+            context,
+            named_sources: vec![],
+            named_kinds: vec![],
+        })
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let time = ts_nanos(event.time);
+        let delta = time.wrapping_sub(self.last) as i64;
+        self.last = time;
+
+        self.buf.clear();
+        let Data {
+            sst,
+            blk,
+            source,
+            kind,
+        } = event.data;
+        name(
+            &mut self.buf,
+            &mut self.named_sources,
+            SOURCE_NAME,
+            source,
+            |id| self.context.source_name(id),
+        );
+        name(
+            &mut self.buf,
+            &mut self.named_kinds,
+            KIND_NAME,
+            kind,
+            |id| self.context.kind_name(id),
+        );
+        self.buf.push(match event.op {
+            Op::Evicted => 0,
+            Op::Missed => 1,
+            Op::Inserted => 2,
+            Op::Hit => 3,
+        });
+        put_varint(&mut self.buf, sst);
+        put_varint(&mut self.buf, blk);
+        put_varint(&mut self.buf, source.into());
+        put_varint(&mut self.buf, kind.into());
+        put_varint(&mut self.buf, ((delta << 1) ^ (delta >> 63)) as u64);
+        self.writer.write_all(&self.buf)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Puts a `record` naming `id` into `buf` the first time it comes up, as
+/// tracked in `named`.
+fn name(
+    buf: &mut Vec<u8>,
+    named: &mut Vec<bool>,
+    record: u8,
+    id: u32,
+    name_of: impl FnOnce(u32) -> Option<Arc<str>>,
+) {
+    let index = id as usize;
+    if named.get(index).copied().unwrap_or(false) {
+        return;
+    }
+    let Some(name) = name_of(id) else {
+        return;
+    };
+    if named.len() <= index {
+        named.resize(index + 1, false);
+    }
+    named[index] = true;
+    buf.push(record);
+    put_varint(buf, id.into());
+    put_varint(buf, name.len() as u64);
+    buf.extend_from_slice(name.as_bytes());
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Reads events written by a [`BinaryWriter`], registering the names of
+/// their sources and key kinds in `context`.
+#[derive(Debug)]
+pub struct BinaryReader<'a, R: Read> {
+    reader: R,
+    version: u8,
+    last: u64,
+    context: &'a Context,
+    /// The ids the file's sources and key kinds are registered under, by
+    /// their id in the file.
+    sources: Vec<Option<u32>>,
+    kinds: Vec<Option<u32>>,
+}
+
+impl<'a, R: Read> BinaryReader<'a, R> {
+    /// Fails unless `reader` starts with [`MAGIC`] and a version up to
+    /// [`VERSION`].
+    pub fn new(mut reader: R, context: &'a Context) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len() + 1];
+        reader.read_exact(&mut magic)?;
+        let version = magic[MAGIC.len()];
+        if &magic[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a binary blocks file",
+            ));
+        }
+        if !(1..=VERSION).contains(&version) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("binary blocks version {version} is newer than this build reads"),
+            ));
+        }
+        Ok(Self {
+            reader,
+            version,
+            last: 0,
+            context,
+            sources: vec![],
+            kinds: vec![],
+        })
+    }
+
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?.ok_or(io::ErrorKind::UnexpectedEof)?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "varint too long",
+        ))
+    }
+
+    /// The id and name of a [`SOURCE_NAME`] or [`KIND_NAME`] record.
+    fn name(&mut self) -> io::Result<(usize, String)> {
+        let id = self.varint()? as usize;
+        let mut name = vec![0; self.varint()? as usize];
+        self.reader.read_exact(&mut name)?;
+        let name =
+            String::from_utf8(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok((id, name))
+    }
+
+    /// The id registered for `id` in the file, which must have been named.
+    fn id(ids: &[Option<u32>], id: u64, what: &str) -> io::Result<u32> {
+        if id == 0 {
+            return Ok(0);
+        }
+        (ids.get(id as usize).copied().flatten()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{what} {id} has no name"),
+            )
+        })
+    }
+
+    fn next_event(&mut self) -> io::Result<Option<Event>> {
+        let op = loop {
+            match self.byte()? {
+                None => return Ok(None),
+                Some(0) => break Op::Evicted,
+                Some(1) => break Op::Missed,
+                Some(2) => break Op::Inserted,
+                Some(3) => break Op::Hit,
+                Some(SOURCE_NAME) if self.version >= 2 => {
+                    let (id, name) = self.name()?;
+                    register(&mut self.sources, id, self.context.source_id(&name));
+                }
+                Some(KIND_NAME) if self.version >= 2 => {
+                    let (id, name) = self.name()?;
+                    register(&mut self.kinds, id, self.context.kind_id(&name));
+                }
+                Some(op) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid op {op}"),
+                    ));
+                }
+            }
+        };
+        let sst = self.varint()?;
+        let blk = self.varint()?;
+        let (source, kind) = match self.version {
+            1 => (0, 0),
+            _ => {
+                let source = self.varint()?;
+                let kind = self.varint()?;
+                (
+                    Self::id(&self.sources, source, "source")?,
+                    Self::id(&self.kinds, kind, "key kind")?,
+                )
+            }
+        };
+        let zigzag = self.varint()?;
+        let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        self.last = self.last.wrapping_add(delta as u64);
+        Ok(Some(Event {
+            data: Data {
+                sst,
+                blk,
+                source,
+                kind,
+            },
+            time: UNIX_EPOCH + Duration::from_nanos(self.last),
+            op,
+            origin: Origin::default(),
+        }))
+    }
+}
+
+/// Notes that the file's `id` is registered as `registered`.
+fn register(ids: &mut Vec<Option<u32>>, id: usize, registered: u32) {
+    if ids.len() <= id {
+        ids.resize(id + 1, None);
+    }
+    ids[id] = Some(registered);
+}
+
+impl<R: Read> Iterator for BinaryReader<'_, R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
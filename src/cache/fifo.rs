@@ -0,0 +1,33 @@
+use super::{Cache, Queue};
+use crate::model::Data;
+
+/// Evicts the block cached first, regardless of hits.
+#[derive(Debug)]
+pub struct Fifo {
+    capacity: usize,
+    queue: Queue,
+}
+
+impl Fifo {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queue: Queue::default(),
+        }
+    }
+}
+
+impl Cache for Fifo {
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool {
+        if self.queue.contains(&key) {
+            return true;
+        }
+        if self.capacity > 0 {
+            if self.queue.len() == self.capacity {
+                evicted.extend(self.queue.pop_oldest());
+            }
+            self.queue.push(key);
+        }
+        false
+    }
+}
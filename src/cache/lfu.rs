@@ -0,0 +1,50 @@
+use super::Cache;
+use crate::model::Data;
+use std::collections::{BTreeSet, HashMap};
+
+/// Evicts the least frequently used block, the least recently used one among
+/// equally frequent blocks.
+#[derive(Debug)]
+pub struct Lfu {
+    capacity: usize,
+    /// Access count and last access tick of each cached block.
+    entries: HashMap<Data, (u64, u64)>,
+    /// The `entries` ordered by eviction priority.
+    order: BTreeSet<(u64, u64, Data)>,
+    tick: u64,
+}
+
+impl Lfu {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeSet::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl Cache for Lfu {
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool {
+        self.tick += 1;
+        if let Some((count, tick)) = self.entries.get_mut(&key) {
+            self.order.remove(&(*count, *tick, key));
+            *count += 1;
+            *tick = self.tick;
+            self.order.insert((*count, *tick, key));
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.entries.len() == self.capacity {
+            let (_, _, victim) = self.order.pop_first().unwrap();
+            self.entries.remove(&victim);
+            evicted.push(victim);
+        }
+        self.entries.insert(key, (1, self.tick));
+        self.order.insert((1, self.tick, key));
+        false
+    }
+}
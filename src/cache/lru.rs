@@ -0,0 +1,31 @@
+use super::{Cache, Queue};
+use crate::model::Data;
+
+/// Evicts the least recently used block.
+#[derive(Debug)]
+pub struct Lru {
+    capacity: usize,
+    queue: Queue,
+}
+
+impl Lru {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queue: Queue::default(),
+        }
+    }
+}
+
+impl Cache for Lru {
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool {
+        let hit = self.queue.contains(&key);
+        if hit || self.capacity > 0 {
+            self.queue.push(key);
+        }
+        if self.queue.len() > self.capacity {
+            evicted.extend(self.queue.pop_oldest());
+        }
+        hit
+    }
+}
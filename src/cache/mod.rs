@@ -0,0 +1,113 @@
+//! Cache models to replay traces against.
+//!
+//! Capacities count blocks, since the traces carry no block sizes.
+
+mod fifo;
+mod lfu;
+mod lru;
+mod s3fifo;
+mod tinylfu;
+
+pub use fifo::Fifo;
+pub use lfu::Lfu;
+pub use lru::Lru;
+pub use s3fifo::S3Fifo;
+pub use tinylfu::WTinyLfu;
+
+use crate::model::Data;
+use std::collections::{BTreeMap, HashMap};
+
+/// A cache holding up to a fixed number of blocks.
+pub trait Cache {
+    /// Looks up `key`, caching it on a miss, and adds the blocks that made
+    /// room for it to `evicted`. Returns whether it was a hit.
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool;
+}
+
+/// The eviction policies [`Cache`] is implemented for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Policy {
+    Lru,
+    Lfu,
+    Fifo,
+    S3Fifo,
+    WTinyLfu,
+}
+
+impl Policy {
+    pub const ALL: [Policy; 5] = [
+        Policy::Lru,
+        Policy::Lfu,
+        Policy::Fifo,
+        Policy::S3Fifo,
+        Policy::WTinyLfu,
+    ];
+
+    /// An empty cache of `capacity` blocks.
+    pub fn build(self, capacity: usize) -> Box<dyn Cache> {
+        match self {
+            Policy::Lru => Box::new(Lru::new(capacity)),
+            Policy::Lfu => Box::new(Lfu::new(capacity)),
+            Policy::Fifo => Box::new(Fifo::new(capacity)),
+            Policy::S3Fifo => Box::new(S3Fifo::new(capacity)),
+            Policy::WTinyLfu => Box::new(WTinyLfu::new(capacity)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Policy::Lru => "lru",
+            Policy::Lfu => "lfu",
+            Policy::Fifo => "fifo",
+            Policy::S3Fifo => "s3-fifo",
+            Policy::WTinyLfu => "w-tinylfu",
+        }
+    }
+}
+
+/// Keys ordered by when they were last pushed.
+#[derive(Debug, Default)]
+pub(crate) struct Queue {
+    ticks: HashMap<Data, u64>,
+    keys: BTreeMap<u64, Data>,
+    next: u64,
+}
+
+impl Queue {
+    pub(crate) fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub(crate) fn contains(&self, key: &Data) -> bool {
+        self.ticks.contains_key(key)
+    }
+
+    /// Makes `key` the newest, adding it if needed.
+    pub(crate) fn push(&mut self, key: Data) {
+        if let Some(tick) = self.ticks.insert(key, self.next) {
+            self.keys.remove(&tick);
+        }
+        self.keys.insert(self.next, key);
+        self.next += 1;
+    }
+
+    pub(crate) fn remove(&mut self, key: &Data) -> bool {
+        match self.ticks.remove(key) {
+            Some(tick) => {
+                self.keys.remove(&tick);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn oldest(&self) -> Option<Data> {
+        self.keys.first_key_value().map(|(_, key)| *key)
+    }
+
+    pub(crate) fn pop_oldest(&mut self) -> Option<Data> {
+        let (_, key) = self.keys.pop_first()?;
+        self.ticks.remove(&key);
+        Some(key)
+    }
+}
@@ -0,0 +1,96 @@
+use super::{Cache, Queue};
+use crate::model::Data;
+use std::collections::HashMap;
+
+/// S3-FIFO: new blocks enter a small FIFO queue and only move on to the main
+/// queue if they were hit while in it, or if they were recently evicted from
+/// it. The main queue gives blocks with hits another round instead of evicting
+/// them.
+#[derive(Debug)]
+pub struct S3Fifo {
+    capacity: usize,
+    small_capacity: usize,
+    ghost_capacity: usize,
+    small: Queue,
+    main: Queue,
+    /// Blocks recently evicted from `small`, as many as `main` can hold.
+    ghost: Queue,
+    /// Hits of each cached block, capped at 3.
+    freq: HashMap<Data, u8>,
+}
+
+impl S3Fifo {
+    pub fn new(capacity: usize) -> Self {
+        let small_capacity = (capacity / 10).max(1);
+        Self {
+            capacity,
+            small_capacity,
+            ghost_capacity: capacity.saturating_sub(small_capacity).max(1),
+            small: Queue::default(),
+            main: Queue::default(),
+            ghost: Queue::default(),
+            freq: HashMap::new(),
+        }
+    }
+
+    fn evict(&mut self, evicted: &mut Vec<Data>) {
+        if self.small.len() >= self.small_capacity || self.main.len() == 0 {
+            evicted.extend(self.evict_small());
+        } else {
+            evicted.extend(self.evict_main());
+        }
+    }
+
+    fn evict_small(&mut self) -> Option<Data> {
+        while let Some(key) = self.small.pop_oldest() {
+            // Moving a block doesn't free space, the caller evicts again.
+            if self.freq[&key] > 1 {
+                self.main.push(key);
+            } else {
+                self.freq.remove(&key);
+                self.ghost.push(key);
+                if self.ghost.len() > self.ghost_capacity {
+                    self.ghost.pop_oldest();
+                }
+                return Some(key);
+            }
+        }
+        None
+    }
+
+    fn evict_main(&mut self) -> Option<Data> {
+        while let Some(key) = self.main.pop_oldest() {
+            let freq = self.freq.get_mut(&key).unwrap();
+            if *freq > 0 {
+                *freq -= 1;
+                self.main.push(key);
+            } else {
+                self.freq.remove(&key);
+                return Some(key);
+            }
+        }
+        None
+    }
+}
+
+impl Cache for S3Fifo {
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool {
+        if let Some(freq) = self.freq.get_mut(&key) {
+            *freq = (*freq + 1).min(3);
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        while self.small.len() + self.main.len() >= self.capacity {
+            self.evict(evicted);
+        }
+        if self.ghost.remove(&key) {
+            self.main.push(key);
+        } else {
+            self.small.push(key);
+        }
+        self.freq.insert(key, 0);
+        false
+    }
+}
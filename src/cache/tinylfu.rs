@@ -0,0 +1,139 @@
+use super::{Cache, Queue};
+use crate::model::Data;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// W-TinyLFU: new blocks enter a small LRU window. Blocks leaving it are only
+/// admitted to the segmented LRU main area if they were accessed more often
+/// than the block they would evict, going by an approximate frequency sketch.
+#[derive(Debug)]
+pub struct WTinyLfu {
+    window_capacity: usize,
+    main_capacity: usize,
+    protected_capacity: usize,
+    window: Queue,
+    /// Main area blocks without a hit since they were admitted.
+    probation: Queue,
+    protected: Queue,
+    sketch: Sketch,
+}
+
+impl WTinyLfu {
+    pub fn new(capacity: usize) -> Self {
+        let window_capacity = (capacity / 100).max(1).min(capacity);
+        let main_capacity = capacity - window_capacity;
+        Self {
+            window_capacity,
+            main_capacity,
+            protected_capacity: main_capacity * 4 / 5,
+            window: Queue::default(),
+            probation: Queue::default(),
+            protected: Queue::default(),
+            sketch: Sketch::new(capacity),
+        }
+    }
+
+    /// Moves `candidate`, just evicted from the window, to the main area if
+    /// it's used more often than the main area's victim. Returns the block
+    /// that left the cache, if any.
+    fn admit(&mut self, candidate: Data) -> Option<Data> {
+        if self.probation.len() + self.protected.len() < self.main_capacity {
+            self.probation.push(candidate);
+            return None;
+        }
+        let victim = self.probation.oldest().or_else(|| self.protected.oldest());
+        let Some(victim) = victim else {
+            return Some(candidate);
+        };
+        if self.sketch.estimate(&candidate) > self.sketch.estimate(&victim) {
+            if !self.probation.remove(&victim) {
+                self.protected.remove(&victim);
+            }
+            self.probation.push(candidate);
+            Some(victim)
+        } else {
+            Some(candidate)
+        }
+    }
+}
+
+impl Cache for WTinyLfu {
+    fn access(&mut self, key: Data, evicted: &mut Vec<Data>) -> bool {
+        self.sketch.increment(&key);
+        if self.window.contains(&key) {
+            self.window.push(key);
+            return true;
+        }
+        if self.probation.remove(&key) {
+            self.protected.push(key);
+            if self.protected.len() > self.protected_capacity {
+                let demoted = self.protected.pop_oldest().unwrap();
+                self.probation.push(demoted);
+            }
+            return true;
+        }
+        if self.protected.contains(&key) {
+            self.protected.push(key);
+            return true;
+        }
+        if self.window_capacity == 0 {
+            return false;
+        }
+        self.window.push(key);
+        if self.window.len() > self.window_capacity {
+            let candidate = self.window.pop_oldest().unwrap();
+            evicted.extend(self.admit(candidate));
+        }
+        false
+    }
+}
+
+/// Count-min sketch of access frequencies with 4 bit counters, halved
+/// periodically so that old popularity fades.
+#[derive(Debug)]
+struct Sketch {
+    rows: [Vec<u8>; 4],
+    mask: usize,
+    additions: usize,
+    /// Halve all counters after this many additions.
+    sample_size: usize,
+}
+
+impl Sketch {
+    fn new(capacity: usize) -> Self {
+        let width = capacity.next_power_of_two().max(16);
+        Self {
+            rows: std::array::from_fn(|_| vec![0; width]),
+            mask: width - 1,
+            additions: 0,
+            sample_size: capacity.max(1) * 10,
+        }
+    }
+
+    fn index(&self, row: usize, key: &Data) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (row, key).hash(&mut hasher);
+        hasher.finish() as usize & self.mask
+    }
+
+    fn increment(&mut self, key: &Data) {
+        for row in 0..self.rows.len() {
+            let index = self.index(row, key);
+            let counter = &mut self.rows[row][index];
+            *counter = (*counter + 1).min(15);
+        }
+        self.additions += 1;
+        if self.additions == self.sample_size {
+            for counter in self.rows.iter_mut().flatten() {
+                *counter /= 2;
+            }
+            self.additions /= 2;
+        }
+    }
+
+    fn estimate(&self, key: &Data) -> u8 {
+        (0..self.rows.len())
+            .map(|row| self.rows[row][self.index(row, key)])
+            .min()
+            .unwrap()
+    }
+}
@@ -0,0 +1,1110 @@
+use super::{
+    InputArgs, Progress, create, create_background, drop_duplicates, newest_first, skip_warmup,
+    sort,
+};
+use anyhow::Context as _;
+use blocks::analysis::alerts::miss_rate_alerts;
+use blocks::analysis::analyzer::{Analyzer, run_analyzers};
+use blocks::analysis::bursts::find_bursts;
+use blocks::analysis::bytes::{Volumes, format_bytes, volumes};
+use blocks::analysis::diagnostics::write_duplicate_evictions;
+use blocks::analysis::diff::block_diff;
+use blocks::analysis::durations::{
+    Cap, Contention, Counts, DurationWriter, DurationsAnalyzer, Match, Matching, RunningSummary,
+    match_misses, residencies, write_durations,
+};
+use blocks::analysis::gaps::{find_gaps, phases, write_phases};
+use blocks::analysis::ghost::{ghosts, resident_peak, write_ghosts};
+use blocks::analysis::heatmap::{heatmap, write_heatmap_csv, write_heatmap_png};
+use blocks::analysis::histogram::{HistogramAnalyzer, write_cdf, write_histogram};
+use blocks::analysis::inter_arrival::write_inter_arrivals;
+use blocks::analysis::metrics::{Metrics, write_metrics};
+use blocks::analysis::negatives::{Negatives, negatives, write_negatives};
+use blocks::analysis::per_group::{
+    named, per_kind, per_level, per_source, per_table, write_groups,
+};
+use blocks::analysis::per_sst::write_per_sst;
+use blocks::analysis::plot::{SIZE, plot_histogram, plot_rates, plot_top_ssts};
+use blocks::analysis::popularity::{popularity, write_popularity};
+use blocks::analysis::recency::write_recency;
+use blocks::analysis::skew::{correct_skew, detect_skew, write_skews};
+use blocks::analysis::sparkline::write_sparklines;
+use blocks::analysis::stats::percentile;
+use blocks::analysis::summary::{Summary, SummaryAnalyzer, summarize, write_summary};
+use blocks::analysis::survival::write_survival;
+use blocks::analysis::thrashing::{Thrashing, find_thrashing};
+use blocks::analysis::timeseries::{TimeseriesAnalyzer, buckets};
+use blocks::analysis::topk::{TopkAnalyzer, top_evicted_ssts, top_missed_blocks, write_topk};
+use blocks::analysis::warmup::Warmup;
+use blocks::background::Compress;
+use blocks::binary::{BinaryReader, BinaryWriter};
+use blocks::export::Export;
+use blocks::format::Format;
+use blocks::model::{Context, Data, Event, Op};
+use blocks::parse::{Follower, ParsedDir, Schema};
+use clap::ValueEnum;
+use notify::{RecursiveMode, Watcher};
+use plotters::prelude::SVGBackend;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Match misses against evictions and write the requested reports.
+#[derive(Debug, clap::Args)]
+pub struct AnalyzeArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(short, long, default_value = "duration.txt")]
+    duration: String,
+    /// Compress the --duration output.
+    #[clap(long, value_enum)]
+    compress: Option<Compress>,
+    /// Only write the first this many misses to the duration output, of each
+    /// file with --per-file-matching. Counts and reports cover all of them.
+    #[clap(long)]
+    duration_limit: Option<usize>,
+    /// Only write the last this many misses to the duration output, of each
+    /// file with --per-file-matching.
+    #[clap(long, conflicts_with = "duration_limit")]
+    duration_tail: Option<usize>,
+    /// Report seconds whose miss rate exceeds this many misses per second and
+    /// exit non-zero if there are any.
+    #[clap(long)]
+    alert_miss_rate: Option<f64>,
+    /// Width in seconds of the moving average used by --alert-miss-rate.
+    #[clap(long, default_value_t = 1)]
+    alert_window: u64,
+    /// Exit non-zero if more than this many misses are short.
+    #[clap(long)]
+    max_short_misses: Option<u64>,
+    /// Exit non-zero if there are more than this many misses in total.
+    #[clap(long)]
+    max_misses: Option<u64>,
+    /// Write the recency-at-eviction report to this file.
+    #[clap(long)]
+    recency_out: Option<String>,
+    /// Write, per block and overall, the distribution of times between
+    /// consecutive misses of a block to this file.
+    #[clap(long)]
+    inter_arrival: Option<String>,
+    /// Ignore evictions more than this many seconds before a miss when
+    /// matching them.
+    #[clap(long)]
+    max_lookback_secs: Option<u64>,
+    /// Count misses whose block was evicted less than this long after them
+    /// as short with a zero delta instead of negative, e.g. `5ms`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "0s")]
+    negative_epsilon: Duration,
+    /// Write the negative deltas, where the block was only evicted after
+    /// the miss, per input file and as a --histogram-buckets histogram to
+    /// this file.
+    #[clap(long)]
+    negative_report: Option<String>,
+    /// Report periods longer than this many seconds without any events.
+    #[clap(long)]
+    report_gaps: Option<u64>,
+    /// Print event, distinct block, eviction and miss counts, the miss rate
+    /// and the long/short/none counts of each phase between periods longer
+    /// than this without any events, e.g. `5m`.
+    #[clap(long, value_parser = humantime::parse_duration)]
+    phases: Option<Duration>,
+    /// Write the --report-gaps results to this file instead of stdout.
+    #[clap(long)]
+    gaps_out: Option<String>,
+    /// Write blocks evicted repeatedly without an intervening miss to this
+    /// file.
+    #[clap(long)]
+    diagnostics_out: Option<String>,
+    /// Re-emit the running long/short/none summary after each parsed file.
+    #[clap(long)]
+    incremental_report: bool,
+    /// Write the --incremental-report summary to this file instead of stderr.
+    /// If it can't be written, the summary goes to stderr after a warning.
+    #[clap(long)]
+    status_file: Option<String>,
+    /// Print the N blocks most often missed shortly after being evicted.
+    #[clap(long)]
+    top_contention: Option<usize>,
+    /// Parse another directory and print the blocks, per op, seen in only one
+    /// of the two.
+    #[clap(long)]
+    diff_against: Option<PathBuf>,
+    /// Print estimated delta percentiles to stderr every N parsed records.
+    #[clap(long)]
+    stats_interval: Option<usize>,
+    /// Write miss timeline sparklines of the most missed blocks to this file.
+    #[clap(long)]
+    sparkline: Option<String>,
+    /// Number of blocks included in the --sparkline report.
+    #[clap(long, default_value_t = 20)]
+    sparkline_top: usize,
+    /// Number of time buckets per --sparkline timeline.
+    #[clap(long, default_value_t = 60)]
+    sparkline_width: usize,
+    /// Only match misses against evictions from the same input file, and
+    /// report counts per file. Premature misses across files are not detected
+    /// in this mode.
+    #[clap(long, conflicts_with = "coalesce_files")]
+    per_file_matching: bool,
+    /// Drop records that show up more than once before matching, and with a
+    /// window such as `5ms` also those of the same block and op at most that
+    /// far apart from a kept one, and report how many were dropped.
+    #[clap(
+        long,
+        value_parser = humantime::parse_duration,
+        num_args = 0..=1,
+        default_missing_value = "0s",
+        value_name = "WINDOW",
+        conflicts_with_all = ["per_file_matching", "follow"]
+    )]
+    dedup: Option<Duration>,
+    /// Drop the events of the warm-up at the start of the trace, while the
+    /// cache fills up, before matching: those within this long of the oldest
+    /// event, such as `5m`, or all before the first eviction with
+    /// `until-first-eviction`. Like with --since, misses after it of blocks
+    /// evicted during it count as none. Prints where it ended, and the
+    /// --summary has it too.
+    #[clap(long, value_name = "DURATION|until-first-eviction", conflicts_with_all = ["per_file_matching", "follow"])]
+    skip_warmup: Option<Warmup>,
+    /// Estimate the clock offset of each directory of input files against
+    /// the one with the most events, from misses logged before another
+    /// directory's eviction of their block, and print them. Files all in one
+    /// directory are taken to have a clock each.
+    #[clap(long, conflicts_with_all = ["per_file_matching", "follow", "coalesce_files"])]
+    detect_skew: bool,
+    /// Like --detect-skew, then shift the events of each clock by its offset
+    /// before matching.
+    #[clap(long, conflicts_with_all = ["per_file_matching", "follow", "coalesce_files"])]
+    skew_correct: bool,
+    /// Farthest apart a miss and another clock's eviction of its block are
+    /// for --detect-skew.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1m")]
+    skew_window: Duration,
+    /// Layout of the duration output. Structured formats hold one row per
+    /// miss and leave the counts to stdout: its `sst_id`, `block_idx`,
+    /// `delta_ns` since the eviction, `miss_ts` in nanoseconds and
+    /// `category`, one of `long`, `short`, `negative` and `none`. Then,
+    /// always there but empty unless filled in, the input `file` with
+    /// --per-file-matching, the `source`, the `kind` with more kinds of keys
+    /// than data blocks, and the `table_id`, `level`, `file_size` and
+    /// `block_count` of the SST from --sst-meta.
+    #[clap(long, visible_alias = "duration-format", value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Misses this soon after their eviction count as short, e.g. `500ms`,
+    /// `30s` or `5m`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    short_threshold: Duration,
+    /// Bytes of each block not sized by --sst-meta, to also report the
+    /// evicted and missed bytes. Those are reported whenever --sst-meta is
+    /// given too.
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+    block_size: Option<u64>,
+    /// Append a histogram and percentiles of the eviction-to-miss deltas to
+    /// the duration output, or write them to the given file.
+    #[clap(long, num_args = 0..=1)]
+    histogram: Option<Option<String>>,
+    /// Comma separated upper bounds of the --histogram buckets.
+    #[clap(
+        long,
+        value_parser = humantime::parse_duration,
+        value_delimiter = ',',
+        default_value = "100ms,1s,10s,1m,10m,1h"
+    )]
+    histogram_buckets: Vec<Duration>,
+    /// Write the cumulative distribution of the eviction-to-miss deltas as
+    /// CSV to this file.
+    #[clap(long)]
+    cdf: Option<String>,
+    /// Write the Kaplan–Meier probability of an evicted block being missed
+    /// again within each multiple of --survival-step as CSV to this file.
+    /// Evictions without a later miss of their block count as censored.
+    #[clap(long)]
+    survival: Option<String>,
+    /// Spacing of the times the --survival curve is evaluated at.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1s")]
+    survival_step: Duration,
+    /// Spacing of the deltas the --cdf is evaluated at.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1s")]
+    cdf_step: Duration,
+    /// Write a histogram and percentiles of how long blocks stayed cached, from
+    /// insert to eviction, to this file. Uses the --histogram-buckets.
+    #[clap(long)]
+    residency: Option<String>,
+    /// Count the first miss of a block since its last eviction as its insert,
+    /// for traces that don't log inserts.
+    #[clap(long)]
+    infer_inserts: bool,
+    /// Render SVG charts of the re-miss intervals, the miss rate over time and
+    /// the SSTs with the most evictions into this directory.
+    #[clap(long)]
+    plot: Option<String>,
+    /// Number of SSTs in the --plot bar chart.
+    #[clap(long, default_value_t = 10)]
+    plot_top: usize,
+    /// Width of the time buckets of the --plot miss rate.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    plot_bucket: Duration,
+    /// Write the misses per range of --heatmap-ssts SST ids and
+    /// --heatmap-bucket of time to this file: a PNG heatmap if it ends in
+    /// `.png`, a CSV with a row per range and a column per bucket otherwise.
+    #[clap(long)]
+    heatmap: Option<String>,
+    /// Number of SST ids per --heatmap row.
+    #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    heatmap_ssts: u64,
+    /// Width of the time buckets of the --heatmap columns.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    heatmap_bucket: Duration,
+    /// Pixels per side of each --heatmap cell in a PNG.
+    #[clap(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    heatmap_cell: u32,
+    /// Print the N most missed blocks and the N SSTs with the most
+    /// evictions.
+    #[clap(long)]
+    topk: Option<usize>,
+    /// Run these analyses, comma separated, together in a single pass over
+    /// the sorted records and print their results in the order given:
+    /// `durations` the long/short/none counts and delta percentiles, `topk`
+    /// the --topk lists, of 10 without it, `timeseries` the eviction, miss
+    /// and hit counts and hit rate per --timeseries-bucket as CSV,
+    /// `histogram` the re-miss intervals in --histogram-buckets and `summary`
+    /// the JSON of --summary.
+    #[clap(long, value_enum, value_delimiter = ',')]
+    analyzers: Vec<AnalyzerName>,
+    /// Write the --analyzers results to this file instead of stdout.
+    #[clap(long)]
+    analyzers_out: Option<String>,
+    /// Width of the time buckets of the `timeseries` analyzer.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    timeseries_bucket: Duration,
+    /// Instead of the other outputs, parse the input once into the
+    /// --all-cache and run every analyzer over it in a single pass, writing
+    /// their results to a file each in this directory: `durations.txt`,
+    /// `topk.txt`, `timeseries.csv`, `histogram.txt` with the re-miss
+    /// intervals in --histogram-buckets and `summary.json` like --summary.
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["follow", "per_file_matching", "dedup", "detect_skew", "skew_correct", "skip_warmup"])]
+    all: Option<String>,
+    /// Binary `.blocks` file the --all run caches the sorted records in,
+    /// instead of a temporary one it deletes. If it exists already, it's
+    /// read instead of parsing the input again, so later runs with other
+    /// analysis options skip parsing.
+    #[clap(long, requires = "all")]
+    all_cache: Option<PathBuf>,
+    /// Print how many blocks were accessed once, 2 to 10 times, 11 to 100
+    /// times and so on, their share of the misses, and the share of blocks
+    /// accessed only once. Accesses are misses and hits.
+    #[clap(long)]
+    popularity: bool,
+    /// Print how many misses a cache --ghost-extra percent larger would have
+    /// hit, keeping the blocks it evicted last in ghost lists.
+    #[clap(long)]
+    ghost: bool,
+    /// Extra capacities for --ghost, in percent of the cache's.
+    #[clap(long, value_delimiter = ',', default_values_t = [25, 50, 100])]
+    ghost_extra: Vec<u32>,
+    /// Capacity of the traced cache in blocks for --ghost. Defaults to the
+    /// most blocks cached at once in the trace.
+    #[clap(long)]
+    cache_blocks: Option<u64>,
+    /// Report the bursts of seconds with more than N evictions each lasting
+    /// longer than --burst-min, with the SSTs evicted most in them.
+    #[clap(long)]
+    bursts: Option<u64>,
+    /// Shortest duration of the --bursts reported.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    burst_min: Duration,
+    /// Number of SSTs listed per --bursts burst.
+    #[clap(long, default_value_t = 3)]
+    burst_ssts: usize,
+    /// Report blocks evicted and missed again more than N times within the
+    /// --thrashing-window.
+    #[clap(long)]
+    thrashing: Option<usize>,
+    /// Width of the window the --thrashing cycles are counted in.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1m")]
+    thrashing_window: Duration,
+    /// Write the --thrashing results to this file instead of stdout.
+    #[clap(long)]
+    thrashing_out: Option<String>,
+    /// Write distinct blocks, eviction, miss and re-miss counts and the mean
+    /// re-miss interval of each SST as CSV to this file.
+    #[clap(long)]
+    per_sst: Option<String>,
+    /// Print distinct block, eviction, miss and long/short/none counts per
+    /// source, told apart by --source-regex or --source-from-file, or per LSM
+    /// level or table of the SSTs, looked up in --sst-meta, or per kind of
+    /// key, named by the schema.
+    #[clap(long, value_enum)]
+    group_by: Option<GroupBy>,
+    /// Write the event, op, distinct block and SST counts, the time range and
+    /// the long/short/none counts as JSON to this file.
+    #[clap(long)]
+    summary: Option<String>,
+    /// Export the events and the miss matches into tables `events` and
+    /// `reuse` of a database, given as `sqlite:<path>`.
+    #[clap(long)]
+    export: Option<Export>,
+    /// Keep watching the input directory once parsed and parse files as they
+    /// grow or show up, re-emitting the running summary like
+    /// --incremental-report. Runs until interrupted.
+    #[clap(long, conflicts_with_all = ["per_file_matching", "coalesce_files"])]
+    follow: bool,
+    /// Write op and long/short/none totals in the Prometheus text format to
+    /// this file, rewritten after each poll with --follow.
+    #[clap(long)]
+    metrics_file: Option<String>,
+    /// Serve the --metrics-file totals over HTTP on this address, e.g.
+    /// `0.0.0.0:9100`, while following.
+    #[clap(long, requires = "follow")]
+    metrics_addr: Option<SocketAddr>,
+}
+
+/// The analyzers of --analyzers, all of which --all runs.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum AnalyzerName {
+    Durations,
+    Topk,
+    Timeseries,
+    Histogram,
+    Summary,
+}
+
+impl AnalyzerName {
+    /// The file --all writes the analyzer's results to.
+    fn file_name(self) -> &'static str {
+        match self {
+            AnalyzerName::Durations => "durations.txt",
+            AnalyzerName::Topk => "topk.txt",
+            AnalyzerName::Timeseries => "timeseries.csv",
+            AnalyzerName::Histogram => "histogram.txt",
+            AnalyzerName::Summary => "summary.json",
+        }
+    }
+
+    /// The analyzer, set up by the options in `args`.
+    fn analyzer(self, args: &AnalyzeArgs, matching: Matching) -> Box<dyn Analyzer> {
+        match self {
+            AnalyzerName::Durations => Box::new(DurationsAnalyzer::new(matching)),
+            AnalyzerName::Topk => Box::new(TopkAnalyzer::new(args.topk.unwrap_or(10))),
+            AnalyzerName::Timeseries => Box::new(TimeseriesAnalyzer::new(args.timeseries_bucket)),
+            AnalyzerName::Histogram => {
+                Box::new(HistogramAnalyzer::new(matching, &args.histogram_buckets))
+            }
+            AnalyzerName::Summary => Box::new(SummaryAnalyzer::new(matching)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum GroupBy {
+    Source,
+    Level,
+    Table,
+    Kind,
+}
+
+pub fn run(args: AnalyzeArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    if args.format == Format::Parquet {
+        anyhow::bail!("parquet is only supported for event output");
+    }
+    let schema = args.input.schema(context)?;
+    let matching = Matching {
+        lookback: args.max_lookback_secs.map(Duration::from_secs),
+        short_threshold: args.short_threshold,
+        negative_epsilon: args.negative_epsilon,
+    };
+
+    let mut progress = Progress {
+        stats_interval: args.stats_interval,
+        running: (args.incremental_report || args.follow)
+            .then(|| (RunningSummary::default(), matching)),
+        status_file: args.status_file.clone(),
+        ..args.input.progress()
+    };
+    if args.follow {
+        let exporter = Exporter::new(&args)?;
+        return follow(&args.dir, &args.input, &schema, progress, exporter, context);
+    }
+    if let Some(out) = &args.all {
+        return all(&args, Path::new(out), matching, progress, context);
+    }
+    let ParsedDir {
+        events: mut records,
+        files,
+        ..
+    } = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?;
+
+    // Per-file matching needs each file's records on their own, sorted the
+    // same way as the full set.
+    let per_file = if args.per_file_matching {
+        let mut per_file = records.clone();
+        for (_, range) in &files {
+            per_file[range.clone()].sort_by_key(newest_first);
+        }
+        per_file
+    } else {
+        vec![]
+    };
+    if args.detect_skew || args.skew_correct {
+        let (names, clock_of_file) = clocks(&files);
+        let mut clock_of = vec![0; records.len()];
+        for ((_, range), &clock) in files.iter().zip(&clock_of_file) {
+            clock_of[range.clone()].fill(clock);
+        }
+        let skews = detect_skew(&records, &clock_of, args.skew_window);
+        write_skews(&skews, &names, &mut std::io::stdout().lock())?;
+        if args.skew_correct && correct_skew(&mut records, &clock_of, &skews) {
+            println!("Shifted the clocks with a known offset by it");
+        }
+    }
+    // The input file of each miss, before sorting mixes them up.
+    let file_of_miss: HashMap<(Data, SystemTime), usize> =
+        if args.negative_report.is_some() && !args.per_file_matching {
+            (files.iter().enumerate())
+                .flat_map(|(i, (_, range))| records[range.clone()].iter().map(move |e| (i, e)))
+                .filter(|(_, event)| event.op == Op::Missed)
+                .map(|(i, event)| ((event.data, event.time), i))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+    sort(&mut records, args.input.quiet);
+    if let Some(window) = args.dedup {
+        let parsed = records.len();
+        drop_duplicates(&mut records, window);
+        println!("Dropped {} duplicate records", parsed - records.len());
+    }
+    let warmup = skip_warmup(&mut records, args.skip_warmup);
+    if let Some(skipped) = warmup {
+        println!(
+            "Skipped {} warm-up records before {}",
+            skipped.events,
+            context.format_time(skipped.end)
+        );
+    }
+
+    if let Some(path) = &args.recency_out {
+        write_recency(&records, context, &mut create(path)?)?;
+    }
+
+    if let Some(path) = &args.inter_arrival {
+        write_inter_arrivals(&records, context, &mut create(path)?)?;
+    }
+
+    if let Some(path) = &args.diagnostics_out {
+        write_duplicate_evictions(&records, context, &mut create(path)?)?;
+    }
+
+    if let Some(path) = &args.sparkline {
+        write_sparklines(
+            &records,
+            context,
+            &mut create(path)?,
+            args.sparkline_top,
+            args.sparkline_width,
+        )?;
+    }
+
+    // Calculate durations between evicted and missed events
+    let cap = (args.duration_limit.map(Cap::First)).or(args.duration_tail.map(Cap::Last));
+    let mut duration_writer = DurationWriter::new(
+        create_background(&args.duration, args.compress)?,
+        args.format,
+        context,
+    )
+    .with_cap(cap);
+    let mut contention = Contention::new();
+
+    let counts = if args.per_file_matching {
+        let mut totals = Counts::default();
+        for (file_name, range) in &files {
+            if duration_writer.is_text() {
+                writeln!(duration_writer.text()?, "# {file_name}")?;
+            }
+            duration_writer.set_file(Some(file_name.clone()))?;
+            let counts = write_durations(
+                &per_file[range.clone()],
+                matching,
+                &mut duration_writer,
+                &mut contention,
+            )?;
+            let Counts { long, short, none } = counts;
+            let line = format!("{file_name}: long: {long}, short: {short}, none: {none}");
+            if duration_writer.is_text() {
+                writeln!(duration_writer.text()?, "{line}")?;
+            } else {
+                println!("{line}");
+            }
+            totals += counts;
+        }
+        totals
+    } else {
+        write_durations(&records, matching, &mut duration_writer, &mut contention)?
+    };
+    if let Some(path) = &args.summary {
+        let summary = Summary {
+            warmup,
+            ..summarize(&records)
+        };
+        write_summary(&summary, counts, &mut create(path)?)?;
+    }
+    if let Some(path) = &args.metrics_file {
+        let mut metrics = Metrics {
+            counts,
+            ..Metrics::default()
+        };
+        metrics.observe(&records);
+        write_metrics(&metrics, &mut create(path)?)?;
+    }
+    let Counts { long, short, none } = counts;
+
+    // Structured formats only hold the per-miss rows.
+    let mut lines = vec![format!("long: {long}, short: {short}, none: {none}")];
+    let mut residencies = residencies(&records, args.infer_inserts);
+    if !residencies.is_empty() {
+        residencies.sort();
+        lines.push(format!(
+            "residency: count: {}, p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+            residencies.len(),
+            percentile(&residencies, 50.0),
+            percentile(&residencies, 90.0),
+            percentile(&residencies, 99.0),
+            residencies[residencies.len() - 1],
+        ));
+    }
+    if args.block_size.is_some() || context.has_sst_meta() {
+        let volumes = if args.per_file_matching {
+            let mut totals = Volumes::default();
+            for (_, range) in &files {
+                totals += volumes(&per_file[range.clone()], matching, args.block_size, context);
+            }
+            totals
+        } else {
+            volumes(&records, matching, args.block_size, context)
+        };
+        let mut line = format!(
+            "bytes: evicted: {}, missed: {}, long: {}, short: {}, none: {}",
+            format_bytes(volumes.evicted),
+            format_bytes(volumes.missed),
+            format_bytes(volumes.long),
+            format_bytes(volumes.short),
+            format_bytes(volumes.none),
+        );
+        if volumes.unknown > 0 {
+            line += &format!(", unknown: {}", volumes.unknown);
+        }
+        lines.push(line);
+    }
+    for line in lines {
+        if duration_writer.is_text() {
+            writeln!(duration_writer.text()?, "{line}")?;
+        } else {
+            println!("{line}");
+        }
+    }
+    let mut duration_writer = duration_writer.finish()?;
+
+    let matched: Vec<_> = if args.histogram.is_none()
+        && args.cdf.is_none()
+        && args.plot.is_none()
+        && args.export.is_none()
+    {
+        vec![]
+    } else if args.per_file_matching {
+        (files.iter())
+            .flat_map(|(_, range)| match_misses(&per_file[range.clone()], matching))
+            .collect()
+    } else {
+        match_misses(&records, matching).collect()
+    };
+
+    let deltas: Vec<_> = (matched.iter())
+        .filter_map(|(_, _, matched)| match matched {
+            Match::Evicted(delta, _) => Some(*delta),
+            _ => None,
+        })
+        .collect();
+    if let Some(path) = &args.histogram {
+        let mut histogram_writer: Box<dyn Write> = match path {
+            Some(path) => Box::new(create(path)?),
+            None if args.format == Format::Text => Box::new(&mut duration_writer),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        write_histogram(
+            "re-miss intervals",
+            deltas.clone(),
+            &args.histogram_buckets,
+            &mut histogram_writer,
+        )?;
+    }
+    duration_writer.finish()?;
+    if let Some(path) = &args.residency {
+        write_histogram(
+            "residencies",
+            residencies,
+            &args.histogram_buckets,
+            &mut create(path)?,
+        )?;
+    }
+    if let Some(dir) = &args.plot {
+        let dir = Path::new(dir);
+        fs::create_dir_all(dir).with_context(|| format!("can't create {}", dir.display()))?;
+        let [histogram, rates, top_ssts] =
+            ["remiss_histogram.svg", "miss_rate.svg", "top_ssts.svg"].map(|name| dir.join(name));
+        plot_histogram(
+            SVGBackend::new(&histogram, SIZE),
+            &deltas,
+            &args.histogram_buckets,
+        )?;
+        let buckets = buckets(&records, args.plot_bucket);
+        plot_rates(
+            SVGBackend::new(&rates, SIZE),
+            &buckets,
+            args.plot_bucket,
+            context,
+        )?;
+        let top = top_evicted_ssts(&records, args.plot_top);
+        plot_top_ssts(SVGBackend::new(&top_ssts, SIZE), &top)?;
+    }
+    if let Some(path) = &args.heatmap {
+        let heatmap = heatmap(&records, args.heatmap_ssts, args.heatmap_bucket);
+        let mut writer = create(path)?;
+        if path.ends_with(".png") {
+            write_heatmap_png(&heatmap, args.heatmap_cell, &mut writer)?;
+        } else {
+            write_heatmap_csv(&heatmap, context, &mut writer)?;
+        }
+        writer.flush()?;
+    }
+    if let Some(path) = &args.cdf {
+        write_cdf(deltas, args.cdf_step, &mut create(path)?)?;
+    }
+
+    if let Some(export) = &args.export {
+        export.write(&records, &matched)?;
+    }
+
+    if let Some(n) = args.top_contention {
+        let mut contention: Vec<_> = contention.into_iter().collect();
+        contention.sort_by_key(|&(data, (count, min))| (std::cmp::Reverse(count), min, data));
+        println!("Top {n} blocks by premature misses:");
+        for (data, (count, min)) in contention.into_iter().take(n) {
+            println!(
+                "{}, premature misses: {count}, min delta: {min:?}",
+                context.data(data)
+            );
+        }
+    }
+
+    if let Some(n) = args.topk {
+        let (blocks, ssts) = (
+            top_missed_blocks(&records, n),
+            top_evicted_ssts(&records, n),
+        );
+        write_topk(&blocks, &ssts, n, context, &mut std::io::stdout().lock())?;
+    }
+
+    if !args.analyzers.is_empty() {
+        let mut analyzers: Vec<_> = (args.analyzers.iter())
+            .map(|name| name.analyzer(&args, matching))
+            .collect();
+        let mut out: Box<dyn Write> = match &args.analyzers_out {
+            Some(path) => Box::new(create(path)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        run_analyzers(&records, &mut analyzers, context, &mut out)?;
+        out.flush()?;
+    }
+
+    if args.popularity {
+        println!("Popularity:");
+        write_popularity(&popularity(&records), &mut std::io::stdout().lock())?;
+    }
+
+    if args.ghost {
+        let (capacity, estimated) = match args.cache_blocks {
+            Some(blocks) => (blocks, ""),
+            None => (resident_peak(&records), ", estimated"),
+        };
+        println!("Ghost lists over {capacity} blocks{estimated}:");
+        write_ghosts(
+            &ghosts(&records, capacity, &args.ghost_extra),
+            &mut std::io::stdout().lock(),
+        )?;
+    }
+
+    if let Some(rate) = args.bursts {
+        println!(
+            "Eviction bursts over {rate}/s for longer than {}:",
+            humantime::format_duration(args.burst_min)
+        );
+        for burst in find_bursts(&records, rate, args.burst_min, args.burst_ssts) {
+            let ssts: Vec<_> = (burst.ssts.iter())
+                .map(|hot| format!("{} ({})", hot.key, hot.count))
+                .collect();
+            println!(
+                "start: {}, end: {}, evictions: {}, peak: {}/s, ssts: {}",
+                context.format_time(burst.start),
+                context.format_time(burst.end),
+                burst.evictions,
+                burst.peak,
+                ssts.join(", ")
+            );
+        }
+    }
+
+    if let Some(other_dir) = &args.diff_against {
+        let mut other_progress = args.input.progress();
+        let other = args
+            .input
+            .parse(other_dir, &schema, context, &mut other_progress)?
+            .events;
+        other_progress.report();
+        let diff = block_diff(&records, &other);
+        let mut lines: Vec<_> = (diff.only_ours.iter().map(|key| (key, &args.dir)))
+            .chain(diff.only_theirs.iter().map(|key| (key, other_dir)))
+            .collect();
+        lines.sort();
+        for ((op, data), side) in lines {
+            println!(
+                "only in {}: {}, {op:?}",
+                side.display(),
+                context.data(*data)
+            );
+        }
+        println!(
+            "only in {}: {}, only in {}: {}",
+            args.dir.display(),
+            diff.only_ours.len(),
+            other_dir.display(),
+            diff.only_theirs.len()
+        );
+    }
+
+    if let Some(secs) = args.report_gaps {
+        let mut gaps_writer: Box<dyn Write> = match &args.gaps_out {
+            Some(path) => Box::new(create(path)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        for (start, end) in find_gaps(&records, Duration::from_secs(secs)) {
+            writeln!(
+                gaps_writer,
+                "gap start: {}, end: {}, duration: {:?}",
+                context.format_time(start),
+                context.format_time(end),
+                end.duration_since(start).unwrap()
+            )?;
+        }
+    }
+
+    if let Some(min) = args.phases {
+        println!("Phases between gaps over {min:?}:");
+        write_phases(
+            &phases(&records, min, matching),
+            context,
+            &mut std::io::stdout().lock(),
+        )?;
+    }
+
+    if let Some(path) = &args.survival {
+        write_survival(&records, args.survival_step, &mut create(path)?)?;
+    }
+
+    if let Some(path) = &args.negative_report {
+        let names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+        let negatives = if args.per_file_matching {
+            let mut totals = Negatives::default();
+            for (i, (_, range)) in files.iter().enumerate() {
+                let file = negatives(&per_file[range.clone()], matching, &names, |_, _| Some(i));
+                totals.misses += file.misses;
+                totals.deltas.extend(file.deltas);
+                totals.per_file.push(file.per_file[i].clone());
+            }
+            totals.deltas.sort();
+            totals.epsilon = matching.negative_epsilon;
+            totals
+        } else {
+            negatives(&records, matching, &names, |data, time| {
+                file_of_miss.get(&(data, time)).copied()
+            })
+        };
+        write_negatives(&negatives, &args.histogram_buckets, &mut create(path)?)?;
+    }
+
+    if let Some(path) = &args.per_sst {
+        write_per_sst(&records, matching, context, &mut create(path)?)?;
+    }
+
+    if let Some(group_by) = args.group_by {
+        let out = &mut std::io::stdout().lock();
+        match group_by {
+            GroupBy::Source => {
+                println!("Per source:");
+                write_groups(&per_source(&records, matching, context), "source", out)?;
+            }
+            GroupBy::Level => {
+                println!("Per level:");
+                write_groups(&named(per_level(&records, matching, context)), "level", out)?;
+            }
+            GroupBy::Table => {
+                println!("Per table:");
+                write_groups(&named(per_table(&records, matching, context)), "table", out)?;
+            }
+            GroupBy::Kind => {
+                println!("Per kind:");
+                write_groups(&per_kind(&records, matching, context), "kind", out)?;
+            }
+        }
+    }
+
+    if let Some(threshold) = args.thrashing {
+        let mut thrashing_writer: Box<dyn Write> = match &args.thrashing_out {
+            Some(path) => Box::new(create(path)?),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        for Thrashing {
+            data,
+            cycles,
+            peak,
+            median,
+        } in find_thrashing(&records, threshold, args.thrashing_window)
+        {
+            writeln!(
+                thrashing_writer,
+                "{}, cycles: {cycles}, peak: {peak}, median cycle time: {median:?}",
+                context.data(data)
+            )?;
+        }
+    }
+
+    let mut breached = false;
+    let misses = records
+        .iter()
+        .filter(|event| event.op == Op::Missed)
+        .count() as u64;
+    for (name, count, max) in [
+        ("short misses", short, args.max_short_misses),
+        ("misses", misses, args.max_misses),
+    ] {
+        if let Some(max) = max.filter(|max| count > *max) {
+            breached = true;
+            println!("Budget exceeded: {name}: {count} > {max}");
+        }
+    }
+    if let Some(threshold) = args.alert_miss_rate {
+        for (sec, rate) in miss_rate_alerts(&records, threshold, args.alert_window) {
+            breached = true;
+            let time = UNIX_EPOCH + Duration::from_secs(sec);
+            println!(
+                "Miss rate alert: {}, {rate:.2} misses/s",
+                context.format_time(time)
+            );
+        }
+    }
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+
+    Ok(if breached {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Names the clocks of the input `files`, one per directory, or one per file
+/// if they are all in one directory, and returns them along with the clock
+/// of each file.
+fn clocks(files: &[(String, std::ops::Range<usize>)]) -> (Vec<String>, Vec<usize>) {
+    let dirs: Vec<&str> = (files.iter())
+        .map(|(name, _)| match Path::new(name).parent() {
+            Some(parent) => parent.to_str().unwrap_or(name),
+            None => "",
+        })
+        .collect();
+    let mut names: Vec<String> = vec![];
+    let mut clock_of_file = vec![];
+    if dirs.iter().all(|dir| *dir == dirs[0]) {
+        names = files.iter().map(|(name, _)| name.clone()).collect();
+        clock_of_file = (0..files.len()).collect();
+    } else {
+        for dir in dirs {
+            let name = if dir.is_empty() { "." } else { dir };
+            let clock = match names.iter().position(|known| known == name) {
+                Some(clock) => clock,
+                None => {
+                    names.push(name.to_string());
+                    names.len() - 1
+                }
+            };
+            clock_of_file.push(clock);
+        }
+    }
+    (names, clock_of_file)
+}
+
+/// The --all run: parses the input into the --all-cache, unless it exists
+/// already, then feeds the cached records to every analyzer at once and has
+/// each write its results to its own file in `out`.
+fn all(
+    args: &AnalyzeArgs,
+    out: &Path,
+    matching: Matching,
+    mut progress: Progress,
+    context: &Context,
+) -> anyhow::Result<ExitCode> {
+    fs::create_dir_all(out).with_context(|| format!("can't create {}", out.display()))?;
+    // Deleted when dropped, at the end of the run.
+    let temporary = match &args.all_cache {
+        Some(_) => None,
+        None => Some(tempfile::Builder::new().suffix(".blocks").tempfile()?),
+    };
+    let cache = (args.all_cache.as_deref())
+        .or(temporary.as_ref().map(|file| file.path()))
+        .unwrap();
+    if args.all_cache.is_some() && cache.exists() {
+        println!("Reading the records cached in {}", cache.display());
+    } else {
+        let schema = args.input.schema(context)?;
+        let mut records = args
+            .input
+            .parse(&args.dir, &schema, context, &mut progress)?
+            .events;
+        sort(&mut records, args.input.quiet);
+        let mut writer = BinaryWriter::new(create(cache)?, context)?;
+        for event in &records {
+            writer.write(event)?;
+        }
+        writer.finish()?.flush()?;
+        progress.report();
+    }
+
+    let mut analyzers: Vec<_> = (AnalyzerName::value_variants().iter())
+        .map(|name| (name.file_name(), name.analyzer(args, matching)))
+        .collect();
+    let file = fs::File::open(cache).with_context(|| format!("can't open {}", cache.display()))?;
+    let mut records = 0;
+    for event in BinaryReader::new(BufReader::new(file), context)? {
+        let event = event?;
+        for (_, analyzer) in &mut analyzers {
+            analyzer.on_event(&event);
+        }
+        records += 1;
+    }
+    for (name, analyzer) in &mut analyzers {
+        let mut writer = create(out.join(name))?;
+        analyzer.finish(context, &mut writer)?;
+        writer.flush()?;
+    }
+    println!("Done. Total records: {records}");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parses `dir` and then whatever is added to it, with `progress` re-emitting
+/// the running summary after each file that grew and `exporter` publishing
+/// the totals after each poll. Only returns on errors.
+fn follow(
+    dir: &Path,
+    input: &InputArgs,
+    schema: &Schema,
+    mut progress: Progress,
+    mut exporter: Exporter,
+    context: &Context,
+) -> anyhow::Result<ExitCode> {
+    anyhow::ensure!(dir.is_dir(), "--follow needs an input directory");
+    let options = input.options(false, context)?;
+    let mut follower = Follower::new(dir, schema, &options, context);
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mode = if input.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(dir, mode)?;
+    loop {
+        let events = follower.poll(&mut progress)?;
+        let (running, _) = progress.running.as_ref().unwrap();
+        exporter.update(&events, running.counts())?;
+        // One poll picks up every change made in the meantime.
+        changes.recv()??;
+        while changes.try_recv().is_ok() {}
+    }
+}
+
+/// Publishes the --follow totals to the --metrics-file and --metrics-addr.
+struct Exporter {
+    metrics: Metrics,
+    file: Option<String>,
+    served: Option<Arc<Mutex<Vec<u8>>>>,
+}
+
+impl Exporter {
+    fn new(args: &AnalyzeArgs) -> anyhow::Result<Self> {
+        let served = match args.metrics_addr {
+            Some(addr) => {
+                let listener =
+                    TcpListener::bind(addr).with_context(|| format!("can't listen on {addr}"))?;
+                let served = Arc::new(Mutex::new(vec![]));
+                let body = served.clone();
+                std::thread::spawn(move || {
+                    for stream in listener.incoming().flatten() {
+                        // A scrape that goes wrong or stalls only affects that scrape.
+                        let body = body.lock().unwrap().clone();
+                        std::thread::spawn(move || respond(stream, &body));
+                    }
+                });
+                Some(served)
+            }
+            None => None,
+        };
+        Ok(Self {
+            metrics: Metrics::default(),
+            file: args.metrics_file.clone(),
+            served,
+        })
+    }
+
+    fn update(&mut self, events: &[Event], counts: Counts) -> anyhow::Result<()> {
+        self.metrics.observe(events);
+        self.metrics.counts = counts;
+        let mut body = vec![];
+        write_metrics(&self.metrics, &mut body)?;
+        if let Some(path) = &self.file {
+            // Scrapers never see a half written file.
+            let partial = format!("{path}.tmp");
+            fs::write(&partial, &body).with_context(|| format!("can't write {partial}"))?;
+            fs::rename(&partial, path).with_context(|| format!("can't write {path}"))?;
+        }
+        if let Some(served) = &self.served {
+            *served.lock().unwrap() = body;
+        }
+        Ok(())
+    }
+}
+
+/// Answers any HTTP request on `stream` with `body`.
+fn respond(mut stream: TcpStream, body: &[u8]) -> std::io::Result<()> {
+    let mut request = BufReader::new(&stream);
+    let mut line = String::new();
+    while request.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)
+}
@@ -0,0 +1,54 @@
+use super::{InputArgs, Order, OutputArgs, sort};
+use anyhow::Context as _;
+use blocks::anonymize::anonymize;
+use blocks::model::Context;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Rewrite a trace with dense pseudonymous SST ids, block indexes and
+/// sources, and times relative to its first event, for sharing it.
+#[derive(Debug, clap::Args)]
+pub struct AnonymizeArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(flatten)]
+    output: OutputArgs,
+    /// Also write the original ids, sources and start time behind the
+    /// anonymized ones to this JSON file, which is best not shared along.
+    #[clap(long)]
+    mapping: Option<String>,
+}
+
+pub fn run(args: AnonymizeArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    if args.output.spills() || args.output.state.is_some() {
+        anyhow::bail!(
+            "anonymizing needs the whole trace in memory, without --streaming, --max-memory or --state"
+        );
+    }
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+
+    let mapping = anonymize(&mut records, context);
+    let order = args.output.order();
+    if order != Order::None {
+        sort(&mut records, args.input.quiet);
+        args.output.reorder(&mut records, order);
+    }
+    args.output
+        .write_parsed(&mut records, args.input.quiet, context)?;
+    if let Some(path) = &args.mapping {
+        let json = serde_json::to_string_pretty(&mapping)?;
+        fs::write(path, json).with_context(|| format!("can't write {path}"))?;
+    }
+
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,68 @@
+use blocks::bench::{analyze, parse, write_trace};
+use std::hint::black_box;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Time parsing and analyzing a synthetic trace, the same one for the same
+/// options, and print a score to compare versions and changes by: records
+/// per second from the input files to the analysis, in thousands.
+#[derive(Debug, clap::Args)]
+pub struct BenchArgs {
+    /// Accesses of the generated trace, whose misses and evictions are its
+    /// records.
+    #[clap(long, default_value_t = 500_000)]
+    accesses: u64,
+    /// CSV files the trace is split into, parsed in parallel.
+    #[clap(long, default_value_t = 16, value_parser = clap::value_parser!(u64).range(1..))]
+    files: u64,
+    /// Number of parsing threads, all cores if not given.
+    #[clap(long)]
+    threads: Option<usize>,
+    /// Times each stage is run, keeping the fastest.
+    #[clap(long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+    runs: u64,
+}
+
+pub fn run(args: BenchArgs) -> anyhow::Result<ExitCode> {
+    let dir = tempfile::tempdir()?;
+    let records = write_trace(dir.path(), args.accesses, args.files as usize)?;
+    let bytes: u64 = (std::fs::read_dir(dir.path())?)
+        .map(|entry| Ok(entry?.metadata()?.len()))
+        .sum::<std::io::Result<_>>()?;
+    println!(
+        "blocks {}: {records} records in {} files, {:.1} MiB, parsing threads: {}",
+        env!("CARGO_PKG_VERSION"),
+        args.files,
+        bytes as f64 / (1 << 20) as f64,
+        args.threads.unwrap_or_else(rayon::current_num_threads)
+    );
+
+    let (mut parsing, mut analyzing) = (Duration::MAX, Duration::MAX);
+    for _ in 0..args.runs {
+        let start = Instant::now();
+        let events = parse(dir.path(), args.threads)?;
+        parsing = parsing.min(start.elapsed());
+        anyhow::ensure!(
+            events.len() as u64 == records,
+            "parsed {} of {records} records",
+            events.len()
+        );
+        let start = Instant::now();
+        black_box(analyze(events));
+        analyzing = analyzing.min(start.elapsed());
+    }
+
+    let rate = |time: Duration| records as f64 / time.as_secs_f64();
+    println!(
+        "parse: {:.3}s, {:.0} records/s",
+        parsing.as_secs_f64(),
+        rate(parsing)
+    );
+    println!(
+        "analyze: {:.3}s, {:.0} records/s",
+        analyzing.as_secs_f64(),
+        rate(analyzing)
+    );
+    println!("score: {:.0}", rate(parsing + analyzing) / 1000.0);
+    Ok(ExitCode::SUCCESS)
+}
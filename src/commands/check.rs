@@ -0,0 +1,70 @@
+use super::{InputArgs, sort};
+use blocks::analysis::check::{
+    Check, ExpectedStats, backwards, counters, load_expected_stats, outliers, unexplained_misses,
+    write_check,
+};
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Scan a trace for anomalies: times going back within a file, misses
+/// without an earlier eviction or insertion, events far outside the main
+/// time range, CSV sections without entries and, with --expected-stats,
+/// counts that differ from the ones the cache exported.
+#[derive(Debug, clap::Args)]
+pub struct CheckArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// Steps back in time within a file up to this long are fine, such as
+    /// those between the entries of one logged batch.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1s")]
+    tolerance: Duration,
+    /// JSON object of the counters the cache exported itself for the traced
+    /// time, any of `evict`, `miss`, `insert` and `hit`, to compare the
+    /// counts of the trace with.
+    #[clap(long, value_parser = load_expected_stats)]
+    expected_stats: Option<ExpectedStats>,
+    /// How far in percent the counts may be off from the --expected-stats.
+    #[clap(long, default_value_t = 0.0)]
+    stats_tolerance: f64,
+    /// Exit with a failure status if there are any anomalies.
+    #[clap(long)]
+    fail: bool,
+}
+
+pub fn run(args: CheckArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let parsed = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?;
+    let mut records = parsed.events;
+
+    let check_backwards = (parsed.files.into_iter())
+        .map(|(file, range)| (file, backwards(&records[range], args.tolerance)))
+        .filter(|(_, backwards)| backwards.steps > 0)
+        .collect();
+    sort(&mut records, args.input.quiet);
+    let check = Check {
+        backwards: check_backwards,
+        tolerance: args.tolerance,
+        unexplained: unexplained_misses(&records),
+        outliers: outliers(&records),
+        empty_sections: std::mem::take(&mut progress.empty_sections),
+        counters: (args.expected_stats.as_ref())
+            .map_or(vec![], |expected| counters(&records, expected)),
+        counter_tolerance: args.stats_tolerance,
+    };
+    write_check(&check, context, &mut std::io::stdout().lock())?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(if args.fail && check.anomalies() > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
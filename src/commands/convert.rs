@@ -0,0 +1,39 @@
+use super::{InputArgs, create, sort};
+use blocks::binary::BinaryWriter;
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Re-encode a trace directory into a binary `.blocks` file, which every
+/// subcommand reads much faster than the original logs.
+#[derive(Debug, clap::Args)]
+pub struct ConvertArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// Output file. Keep the `.blocks` extension for it to be picked up as
+    /// input.
+    #[clap(short, long, default_value = "events.blocks")]
+    out: String,
+}
+
+pub fn run(args: ConvertArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    let mut writer = BinaryWriter::new(create(&args.out)?, context)?;
+    for event in &records {
+        writer.write(event)?;
+    }
+    writer.finish()?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,115 @@
+use super::{InputArgs, sort};
+use blocks::analysis::diff::{MissDiff, miss_diff};
+use blocks::analysis::durations::{Matching, intervals};
+use blocks::analysis::stats::percentile;
+use blocks::model::{Context, Event, Op};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Compare two traces of the same workload, e.g. before and after a cache
+/// configuration change.
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// The baseline: input directory or file, or `-` to read from stdin.
+    ours: PathBuf,
+    /// The trace compared against it.
+    theirs: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// Print this many SSTs and blocks whose miss count changed the most.
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+    /// Misses this soon after their eviction count as short, e.g. `500ms`,
+    /// `30s` or `5m`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    short_threshold: Duration,
+    /// Ignore evictions more than this many seconds before a miss when
+    /// matching them.
+    #[clap(long)]
+    max_lookback_secs: Option<u64>,
+}
+
+pub fn run(args: DiffArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let matching = Matching {
+        lookback: args.max_lookback_secs.map(Duration::from_secs),
+        short_threshold: args.short_threshold,
+        ..Matching::default()
+    };
+    let mut progress = args.input.progress();
+    let mut ours = args
+        .input
+        .parse(&args.ours, &schema, context, &mut progress)?
+        .events;
+    let mut theirs = args
+        .input
+        .parse(&args.theirs, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+    sort(&mut ours, args.input.quiet);
+    sort(&mut theirs, args.input.quiet);
+
+    let count = |events: &[Event], op| events.iter().filter(|event| event.op == op).count() as u64;
+    change(
+        "evictions",
+        count(&ours, Op::Evicted),
+        count(&theirs, Op::Evicted),
+    );
+    change(
+        "misses",
+        count(&ours, Op::Missed),
+        count(&theirs, Op::Missed),
+    );
+
+    let (our_counts, our_deltas) = intervals(&ours, matching);
+    let (their_counts, their_deltas) = intervals(&theirs, matching);
+    change("short misses", our_counts.short, their_counts.short);
+    change("long misses", our_counts.long, their_counts.long);
+    change("unmatched misses", our_counts.none, their_counts.none);
+    for p in [50.0, 90.0, 99.0] {
+        let at = |deltas: &[Duration]| (!deltas.is_empty()).then(|| percentile(deltas, p));
+        println!(
+            "re-miss interval p{p}: {} -> {}",
+            show(at(&our_deltas)),
+            show(at(&their_deltas))
+        );
+    }
+
+    println!("misses per sst, largest changes:");
+    for diff in miss_diff(&ours, &theirs, |event| event.data.sst)
+        .iter()
+        .take(args.top)
+    {
+        print_diff(format!("sst: {}", diff.key), diff);
+    }
+    println!("misses per block, largest changes:");
+    for diff in miss_diff(&ours, &theirs, |event| event.data)
+        .iter()
+        .take(args.top)
+    {
+        print_diff(context.data(diff.key), diff);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn change(label: &str, ours: u64, theirs: u64) {
+    println!(
+        "{label}: {ours} -> {theirs} ({:+})",
+        theirs as i64 - ours as i64
+    );
+}
+
+fn print_diff<K>(key: impl Display, diff: &MissDiff<K>) {
+    println!(
+        "{key}, misses: {} -> {} ({:+})",
+        diff.ours,
+        diff.theirs,
+        diff.change()
+    );
+}
+
+fn show(delta: Option<Duration>) -> String {
+    delta.map_or("-".to_string(), |delta| format!("{delta:?}"))
+}
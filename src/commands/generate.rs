@@ -0,0 +1,83 @@
+use super::create;
+use blocks::cache::Policy;
+use blocks::generate::{Generator, Workload, write_csv};
+use blocks::model::parse_time;
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+/// Write a synthetic trace of a cache serving Zipf-distributed block
+/// accesses, as a CSV export every subcommand reads.
+#[derive(Debug, clap::Args)]
+pub struct GenerateArgs {
+    #[clap(short, long, default_value = "trace.csv")]
+    out: String,
+    /// Block accesses to generate.
+    #[clap(long, default_value_t = 100_000)]
+    accesses: u64,
+    /// Distinct blocks accessed.
+    #[clap(long, default_value_t = 10_000)]
+    blocks: u64,
+    /// Blocks of each SST.
+    #[clap(long, default_value_t = 16, value_parser = clap::value_parser!(u64).range(1..))]
+    blocks_per_sst: u64,
+    /// Zipf exponent of block popularity, 0 for uniform.
+    #[clap(long, default_value_t = 0.9, value_parser = parse_non_negative)]
+    zipf: f64,
+    /// Mean accesses per second.
+    #[clap(long, default_value_t = 1000.0, value_parser = parse_positive)]
+    rate: f64,
+    /// Eviction policy of the cache.
+    #[clap(long, value_enum, default_value_t = Policy::Lru)]
+    policy: Policy,
+    /// Cache size, in blocks.
+    #[clap(long, default_value_t = 1000)]
+    capacity: usize,
+    /// RFC3339 or `YYYY-MM-DD HH:MM:SS` local time of the first access.
+    #[clap(long, value_parser = parse_time, default_value = "2024-01-01T00:00:00Z")]
+    start: SystemTime,
+    /// Also write hits and insertions, not only misses and evictions.
+    #[clap(long)]
+    all_ops: bool,
+    /// Seed of the random choices, the same one giving the same trace.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// Most records in one row of the CSV.
+    #[clap(long, default_value_t = 32, value_parser = clap::value_parser!(u64).range(1..))]
+    batch: u64,
+}
+
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|e| format!("{s}: {e}"))?;
+    if value >= 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(format!("{s} is not a non-negative number"))
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match parse_non_negative(s)? {
+        0.0 => Err(format!("{s} is not positive")),
+        value => Ok(value),
+    }
+}
+
+pub fn run(args: GenerateArgs) -> anyhow::Result<ExitCode> {
+    let workload = Workload {
+        blocks: args.blocks,
+        blocks_per_sst: args.blocks_per_sst,
+        zipf: args.zipf,
+        rate: args.rate,
+        accesses: args.accesses,
+        start: args.start,
+        all_ops: args.all_ops,
+        seed: args.seed,
+    };
+    let generator = Generator::new(workload, args.policy.build(args.capacity));
+    let mut records = 0;
+    let events = generator.inspect(|_| records += 1);
+    write_csv(events, args.batch as usize, create(&args.out)?)?;
+
+    println!("Done. Total records: {records}");
+    Ok(ExitCode::SUCCESS)
+}
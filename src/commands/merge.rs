@@ -0,0 +1,30 @@
+use super::{InputArgs, OutputArgs};
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Merge trace directories, or the output of earlier runs, into one sorted
+/// output without duplicate records.
+#[derive(Debug, clap::Args)]
+pub struct MergeArgs {
+    /// Input directories or files, or `-` to read from stdin.
+    #[clap(required = true)]
+    dirs: Vec<PathBuf>,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(flatten)]
+    output: OutputArgs,
+    /// Keep records that show up more than once, e.g. in overlapping traces.
+    #[clap(long)]
+    keep_duplicates: bool,
+    /// Also drop records of the same block and op at most this far apart
+    /// from a kept one, such as `5ms`.
+    #[clap(long, value_parser = humantime::parse_duration, conflicts_with = "keep_duplicates")]
+    dedup: Option<Duration>,
+}
+
+pub fn run(args: MergeArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let dedup = (!args.keep_duplicates).then(|| args.dedup.unwrap_or_default());
+    args.output.run(&args.input, &args.dirs, dedup, context)
+}
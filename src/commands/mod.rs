@@ -0,0 +1,1180 @@
+//! Subcommands and the arguments they share.
+
+pub mod analyze;
+pub mod anonymize;
+pub mod bench;
+pub mod check;
+pub mod convert;
+pub mod diff;
+pub mod generate;
+pub mod merge;
+pub mod mrc;
+pub mod parse;
+pub mod query;
+pub mod report;
+pub mod serve;
+pub mod simulate;
+pub mod stats;
+pub mod timeline;
+pub mod timeseries;
+pub mod tui;
+pub mod working_set;
+
+use anyhow::Context as _;
+use blocks::analysis::checksum::checksum;
+use blocks::analysis::durations::{Counts, Matching, RunningSummary};
+use blocks::analysis::stats::StreamingStats;
+use blocks::analysis::warmup::{self, Skipped, Warmup, warmup_end};
+use blocks::background::{BackgroundWriter, Compress};
+use blocks::dedup::Dedup;
+use blocks::format::{Encoder, EventRecord, Format, ParquetWriter};
+use blocks::model::{Context, Data, Event, Op, parse_time};
+use blocks::parse::{
+    Banner, Compression, EpochMap, Error as ParseError, Fields, Filter, Follower, InputFormat,
+    Observer, Offsets, ParseOptions, ParsedDir, Schema, TimeField, TimeUnit, parse_dir,
+};
+use blocks::spill::{ExternalSort, chunk_size_for};
+use blocks::template::{Column, Template};
+use clap::{Args, ValueEnum};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
+
+/// How input directories are read.
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// Concatenate small CSV files into larger buffers before parsing.
+    #[clap(long)]
+    pub coalesce_files: bool,
+    /// Map uncompressed input files into memory and parse them in place,
+    /// for multi-gigabyte files. They must not be truncated meanwhile.
+    #[clap(long, conflicts_with = "coalesce_files")]
+    pub mmap: bool,
+    /// List and read input files with up to this many at once on an async
+    /// runtime, ahead of the parsing threads. For directories of many small
+    /// files, where opening and reading them takes longer than parsing.
+    #[clap(long, conflicts_with_all = ["coalesce_files", "mmap"], value_parser = clap::value_parser!(u64).range(1..))]
+    pub async_reads: Option<u64>,
+    /// Load the log format definition from a TOML or JSON file.
+    #[clap(long)]
+    pub schema_config: Option<PathBuf>,
+    /// Seconds since the Unix epoch that logged times are relative to.
+    #[clap(long, default_value_t = 0)]
+    pub epoch: u64,
+    /// File of `<file name regex> <epoch secs>` lines overriding --epoch per
+    /// input file.
+    #[clap(long)]
+    pub epoch_map: Option<PathBuf>,
+    /// Timestamp layout of the built-in format. Ignored with --schema-config.
+    #[clap(long, value_enum, default_value_t = TimeField::Systime)]
+    pub time_field: TimeField,
+    /// Section marker of an op, as `<op>=<marker>`, replacing the format's
+    /// markers for that op. May be repeated.
+    #[clap(long, value_parser = parse_marker)]
+    pub marker: Vec<Banner>,
+    /// Regex matching one record, replacing the format's. Groups named `sst`,
+    /// `blk` and `time` hold the fields, and an optional `nanos` group the
+    /// sub-second part of the time in nanoseconds. Without a `blk` group
+    /// every record has block index 0.
+    #[clap(long, value_parser = parse_record_regex)]
+    pub record_regex: Option<Regex>,
+    /// Unit of the time field, replacing the format's.
+    #[clap(long, value_enum)]
+    pub time_unit: Option<TimeUnit>,
+    /// Fail on the first malformed record or unreadable file instead of
+    /// skipping it, and when no input files matched instead of warning.
+    #[clap(long)]
+    pub strict: bool,
+    /// Maximum number of files parsed in parallel. Defaults to the number of
+    /// cores.
+    #[clap(long)]
+    pub threads: Option<usize>,
+    /// Compression of the input files.
+    #[clap(long, value_enum, default_value_t = Compression::Auto)]
+    pub compression: Compression,
+    /// Format of the input files: CSV exports or plain text logs.
+    #[clap(long, value_enum, default_value_t = InputFormat::Csv)]
+    pub input_format: InputFormat,
+    /// Also look for input files in subdirectories.
+    #[clap(long)]
+    pub recursive: bool,
+    /// Only parse files whose path below the input directory matches this
+    /// glob. May be repeated.
+    #[clap(long, value_parser = Glob::new)]
+    pub include: Vec<Glob>,
+    /// Skip files whose path below the input directory matches this glob.
+    /// May be repeated.
+    #[clap(long, value_parser = Glob::new)]
+    pub exclude: Vec<Glob>,
+    /// Only keep events at or after this RFC3339 or `YYYY-MM-DD HH:MM:SS`
+    /// local time. Evictions before it are dropped too.
+    #[clap(long, value_parser = parse_time)]
+    pub since: Option<SystemTime>,
+    /// Only keep events before this RFC3339 or `YYYY-MM-DD HH:MM:SS` local
+    /// time.
+    #[clap(long, value_parser = parse_time)]
+    pub until: Option<SystemTime>,
+    /// Only keep events of this SST id or inclusive `first-last` id range.
+    /// May be repeated.
+    #[clap(long, value_parser = parse_id_range)]
+    pub sst: Vec<RangeInclusive<u64>>,
+    /// Only keep events of this block index or inclusive `first-last` index
+    /// range. May be repeated.
+    #[clap(long, value_parser = parse_id_range)]
+    pub block: Vec<RangeInclusive<u64>>,
+    /// Only keep events of keys of this kind, named by the schema, or
+    /// `data` for data blocks. May be repeated.
+    #[clap(long)]
+    pub kind: Vec<String>,
+    /// Only keep the events of this share of the blocks, such as `0.01`, for
+    /// a quick approximate run. Blocks are picked by hashing, so all events
+    /// of a picked block are kept and every run picks the same ones.
+    #[clap(long, value_parser = parse_ratio)]
+    pub sample: Option<f64>,
+    /// Tell the sources of events apart, such as cache shards or nodes, by
+    /// this regex on the input file paths: its first capture group, or the
+    /// whole match, names the source. Files it doesn't match are a source of
+    /// their own. Blocks of different sources are matched and counted
+    /// separately, and the output shows their source. Binary traces don't
+    /// keep it.
+    #[clap(long)]
+    pub source_regex: Option<Regex>,
+    /// Like --source-regex, with each input file name as its own source.
+    #[clap(long, conflicts_with = "source_regex")]
+    pub source_from_file: bool,
+    /// Don't draw progress bars or status messages, only the final summary.
+    #[clap(short, long)]
+    pub quiet: bool,
+}
+
+impl InputArgs {
+    /// Progress reporting as configured by `--quiet`.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            quiet: self.quiet,
+            strict: self.strict,
+            ..Progress::default()
+        }
+    }
+
+    pub fn schema(&self, context: &Context) -> anyhow::Result<Schema> {
+        let mut schema = match &self.schema_config {
+            Some(path) => Schema::load(path, context)?,
+            None => Schema::builtin(self.time_field),
+        };
+        if let Some(pattern) = &self.record_regex {
+            schema.fields = Fields::named(pattern).unwrap();
+            schema.pattern = pattern.clone();
+        }
+        if let Some(time_unit) = self.time_unit {
+            schema.time_unit = time_unit;
+        }
+        for marker in &self.marker {
+            schema.banners.retain(|banner| banner.op != marker.op);
+        }
+        schema.banners.extend(self.marker.iter().cloned());
+        Ok(schema)
+    }
+
+    /// Parses `dir`, warning or, with `--strict`, failing when it has no
+    /// inputs.
+    pub fn parse(
+        &self,
+        dir: &Path,
+        schema: &Schema,
+        context: &Context,
+        observer: &mut impl Observer,
+    ) -> anyhow::Result<ParsedDir> {
+        self.parse_with(dir, schema, false, context, observer)
+    }
+
+    /// Like [`InputArgs::parse`], but only hands the events to `observer`.
+    pub fn stream(
+        &self,
+        dir: &Path,
+        schema: &Schema,
+        context: &Context,
+        observer: &mut impl Observer,
+    ) -> anyhow::Result<()> {
+        self.parse_with(dir, schema, true, context, observer)?;
+        Ok(())
+    }
+
+    /// How the input files are read, with the kinds of `--kind` registered
+    /// in `context`.
+    pub fn options(&self, discard_events: bool, context: &Context) -> anyhow::Result<ParseOptions> {
+        Ok(ParseOptions {
+            epoch: Duration::from_secs(self.epoch),
+            epoch_map: self.epoch_map.as_deref().map(EpochMap::load).transpose()?,
+            coalesce_files: self.coalesce_files,
+            discard_events,
+            threads: self.threads,
+            compression: self.compression,
+            input_format: self.input_format,
+            recursive: self.recursive,
+            include: glob_set(&self.include),
+            exclude: glob_set(&self.exclude),
+            filter: Filter {
+                since: self.since,
+                until: self.until,
+                ssts: self.sst.clone(),
+                blocks: self.block.clone(),
+                sample: self.sample,
+                kinds: self.kind.iter().map(|name| context.kind_id(name)).collect(),
+            },
+            mmap: self.mmap,
+            source: (self.source_regex.clone()).or_else(|| {
+                self.source_from_file
+                    .then(|| Regex::new(r"[^/\\]+$").unwrap())
+            }),
+            async_reads: self.async_reads.map(|reads| reads as usize),
+        })
+    }
+
+    fn parse_with(
+        &self,
+        dir: &Path,
+        schema: &Schema,
+        discard_events: bool,
+        context: &Context,
+        observer: &mut impl Observer,
+    ) -> anyhow::Result<ParsedDir> {
+        let options = self.options(discard_events, context)?;
+        let parsed = parse_dir(dir, schema, &options, context, observer)?;
+
+        if parsed.matched == 0 {
+            let message = format!(
+                "no input files matched in {}: {} entries, {} filtered out by extension",
+                dir.display(),
+                parsed.entries,
+                parsed.entries - parsed.matched
+            );
+            if self.strict {
+                anyhow::bail!(message);
+            }
+            eprintln!("Warning: {message}");
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Parses `<op>=<marker>`, such as `missed=MISSED DATA BLOCKS`.
+fn parse_marker(s: &str) -> Result<Banner, String> {
+    let (name, marker) = s.split_once('=').ok_or("expected `<op>=<marker>`")?;
+    let op = Op::ALL
+        .into_iter()
+        .find(|op| op.name() == name)
+        .ok_or_else(|| format!("unknown op {name}"))?;
+    Ok(Banner {
+        marker: marker.to_string(),
+        op,
+    })
+}
+
+fn parse_record_regex(s: &str) -> Result<Regex, String> {
+    let pattern = Regex::new(s).map_err(|e| e.to_string())?;
+    Fields::named(&pattern)?;
+    Ok(pattern)
+}
+
+fn parse_ratio(s: &str) -> Result<f64, String> {
+    let ratio: f64 = s.parse().map_err(|e| format!("{s}: {e}"))?;
+    if ratio > 0.0 && ratio <= 1.0 {
+        Ok(ratio)
+    } else {
+        Err(format!("{s} is not in (0, 1]"))
+    }
+}
+
+/// Parses `id` or `first-last`.
+fn parse_id_range(s: &str) -> Result<RangeInclusive<u64>, String> {
+    let parse = |id: &str| id.trim().parse::<u64>().map_err(|e| format!("{id}: {e}"));
+    match s.split_once('-') {
+        Some((first, last)) => {
+            let (first, last) = (parse(first)?, parse(last)?);
+            if first > last {
+                return Err("start must not exceed end".to_string());
+            }
+            Ok(first..=last)
+        }
+        None => parse(s).map(|id| id..=id),
+    }
+}
+
+/// A number of bytes, with an optional binary unit such as `512MiB` or
+/// `4G`.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number.parse().map_err(|e| format!("size {s}: {e}"))?;
+    let shift = match unit.trim() {
+        "" | "B" => 0,
+        "K" | "KiB" => 10,
+        "M" | "MiB" => 20,
+        "G" | "GiB" => 30,
+        "T" | "TiB" => 40,
+        _ => {
+            return Err(format!(
+                "size {s}: unknown unit, expected KiB, MiB, GiB or TiB"
+            ));
+        }
+    };
+    (number.checked_mul(1 << shift)).ok_or_else(|| format!("size {s} is too large"))
+}
+
+fn glob_set(globs: &[Glob]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(glob.clone());
+    }
+    builder.build().unwrap()
+}
+
+/// How parsed records are written out.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    #[clap(short, long, default_value = "out.txt")]
+    pub out: String,
+    /// Group consecutive records of the same op under a section header.
+    #[clap(long)]
+    pub grouped: bool,
+    /// Order of records in the output file. Records with the same key are
+    /// ordered by block, then op.
+    #[clap(long, value_enum, default_value_t = SortBy::Time)]
+    pub sort_by: SortBy,
+    /// Direction of the --sort-by order, by default `desc` for time and `asc`
+    /// for sst. `none` keeps the order records were parsed in.
+    #[clap(long, value_enum)]
+    pub sort: Option<Order>,
+    /// Stop writing the output once it would exceed this many bytes.
+    #[clap(long)]
+    pub max_output_bytes: Option<u64>,
+    /// Split the output into numbered parts of about this size, such as
+    /// `1G`, each ending with the record that reaches it: `out.0001.txt`,
+    /// `out.0002.txt` and so on for `--out out.txt`, and `out.index.csv`
+    /// with the records and time range of each. Compressed parts also end
+    /// with `.gz` or `.zst`. Not for parquet.
+    #[clap(long, value_parser = parse_size, conflicts_with_all = ["max_output_bytes", "partition_by", "state"])]
+    pub max_out_size: Option<u64>,
+    /// Separator between the columns of the output file.
+    #[clap(long, default_value = ", ")]
+    pub field_sep: String,
+    /// Write only these comma separated columns of each record, separated
+    /// by --field-sep. Such output can't be read back.
+    #[clap(long, value_enum, value_delimiter = ',', conflicts_with = "grouped")]
+    pub columns: Vec<Column>,
+    /// Write each record as this text with the --columns names in braces
+    /// replaced by its fields, such as `{sst},{blk},{ts_epoch}`. `{{` and
+    /// `}}` stand for braces.
+    #[clap(long, value_parser = Template::parse, conflicts_with_all = ["grouped", "columns"])]
+    pub template: Option<Template>,
+    /// Print a SHA-256 checksum of the parsed records to stderr.
+    #[clap(long)]
+    pub checksum: bool,
+    /// Sort through temporary spill files instead of in memory, for traces
+    /// larger than RAM.
+    #[clap(long, conflicts_with = "checksum")]
+    pub streaming: bool,
+    /// Number of records sorted in memory per --streaming spill file.
+    #[clap(long, default_value_t = 1 << 20)]
+    pub spill_records: usize,
+    /// Keep about this much memory of records, such as `4GiB`, and once
+    /// there are more, sort them through spill files like --streaming. Runs
+    /// that fit need no spill files.
+    #[clap(long, value_parser = parse_size, conflicts_with_all = ["checksum", "spill_records"])]
+    pub max_memory: Option<u64>,
+    /// Directory for --streaming and --max-memory spill files instead of the system temp
+    /// directory.
+    #[clap(long)]
+    pub spill_dir: Option<String>,
+    /// Layout of the output file. --grouped needs the text layout, which
+    /// --field-sep, --columns and --template only apply to, and parquet
+    /// takes no --max-output-bytes.
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+    /// Only parse what was added to the input directories since the run that
+    /// saved this file, append the new records to the output, and save where
+    /// parsing stopped. Text logs are resumed within files, other input files
+    /// are parsed once. Records are only sorted within each run.
+    #[clap(long, conflicts_with_all = ["streaming", "max_memory"])]
+    pub state: Option<String>,
+    /// Write the records of each SST to their own file, such as
+    /// `sst_000123.txt`, in the --out directory.
+    #[clap(long, value_enum, conflicts_with_all = ["state", "max_output_bytes"])]
+    pub partition_by: Option<PartitionBy>,
+    /// Only write the first this many records of the output order.
+    #[clap(long, conflicts_with_all = ["state", "partition_by"])]
+    pub limit: Option<usize>,
+    /// Only write the last this many records of the output order.
+    #[clap(long, conflicts_with_all = ["state", "partition_by", "limit"])]
+    pub tail: Option<usize>,
+    /// Compress the output file, or each --partition-by file, which then
+    /// also gets the `.gz` or `.zst` suffix. --max-output-bytes counts the
+    /// bytes before compression.
+    #[clap(long, value_enum)]
+    pub compress: Option<Compress>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PartitionBy {
+    Sst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Order {
+    Asc,
+    Desc,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SortBy {
+    /// Newest record first.
+    Time,
+    /// By sst, then block, then time.
+    Sst,
+}
+
+impl OutputArgs {
+    /// Parses every input in `dirs` and writes the records out, without
+    /// duplicates at most `dedup` apart, see [`drop_duplicates`].
+    pub fn run(
+        &self,
+        input: &InputArgs,
+        dirs: &[PathBuf],
+        dedup: Option<Duration>,
+        context: &Context,
+    ) -> anyhow::Result<ExitCode> {
+        let schema = input.schema(context)?;
+        if self.state.is_some() && !matches!(self.format, Format::Text | Format::Jsonl) {
+            anyhow::bail!("--state appends to the output, which only works for text and jsonl");
+        }
+
+        let order = self.order();
+        if self.spills() && order == Order::None {
+            anyhow::bail!("--sort none keeps the parse order, there is nothing to spill");
+        }
+        if order == Order::None && dedup.is_some_and(|window| !window.is_zero()) {
+            anyhow::bail!("near-duplicates are only found in sorted records, not with --sort none");
+        }
+
+        // Every key ends in the whole event, so that duplicates end up next
+        // to each other and ties always come out the same way.
+        let (total, dropped) = if self.spills() {
+            match (self.sort_by, order) {
+                (SortBy::Time, Order::Desc) => {
+                    self.stream(input, dirs, &schema, dedup, newest_first, context)?
+                }
+                (SortBy::Time, _) => self.stream(
+                    input,
+                    dirs,
+                    &schema,
+                    dedup,
+                    |event| (event.time, event.data, event.op),
+                    context,
+                )?,
+                (SortBy::Sst, Order::Desc) => self.stream(
+                    input,
+                    dirs,
+                    &schema,
+                    dedup,
+                    |event| (Reverse(event.data), Reverse(event.time), event.op),
+                    context,
+                )?,
+                (SortBy::Sst, _) => self.stream(
+                    input,
+                    dirs,
+                    &schema,
+                    dedup,
+                    |event| (event.data, event.time, event.op),
+                    context,
+                )?,
+            }
+        } else {
+            let mut progress = input.progress();
+            let mut records = vec![];
+            let mut offsets = None;
+            if let Some(path) = &self.state {
+                let (events, resumed) = resume(input, dirs, &schema, path, context, &mut progress)?;
+                records = events;
+                offsets = Some(resumed);
+            } else {
+                for dir in dirs {
+                    records.extend(input.parse(dir, &schema, context, &mut progress)?.events);
+                }
+            }
+            progress.report();
+            let parsed = records.len();
+            if order == Order::None {
+                if dedup.is_some() {
+                    let mut seen = HashSet::new();
+                    records.retain(|event| seen.insert((event.data, event.time, event.op)));
+                }
+            } else {
+                sort(&mut records, input.quiet);
+                if let Some(window) = dedup {
+                    drop_duplicates(&mut records, window);
+                }
+                self.reorder(&mut records, order);
+            }
+            let dropped = parsed - records.len();
+            self.write_parsed(&mut records, input.quiet, context)?;
+            // Only once the records are safely written.
+            if let (Some(path), Some(offsets)) = (&self.state, offsets) {
+                let json = serde_json::to_string(&offsets)?;
+                fs::write(path, json).with_context(|| format!("can't write {path}"))?;
+            }
+            (records.len(), dropped)
+        };
+
+        if dedup.is_some() {
+            println!("Dropped {dropped} duplicate records");
+        }
+        println!("Done. Total records: {total}");
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Whether records are sorted through spill files, see [`ExternalSort`].
+    pub fn spills(&self) -> bool {
+        self.streaming || self.max_memory.is_some()
+    }
+
+    /// Records held in memory per spill file.
+    fn chunk_size(&self) -> usize {
+        (self.max_memory).map_or(self.spill_records, chunk_size_for)
+    }
+
+    /// Direction of the output order, by --sort or the --sort-by default.
+    pub fn order(&self) -> Order {
+        self.sort.unwrap_or(match self.sort_by {
+            SortBy::Time => Order::Desc,
+            SortBy::Sst => Order::Asc,
+        })
+    }
+
+    /// Puts records sorted by [`sort`] into the output order, which can't be
+    /// `none`.
+    pub fn reorder(&self, records: &mut [Event], order: Order) {
+        match (self.sort_by, order) {
+            (SortBy::Time, Order::Desc) => {}
+            (SortBy::Time, _) => records.sort_by_key(|event| (event.time, event.data, event.op)),
+            (SortBy::Sst, Order::Desc) => {
+                records.sort_by_key(|event| (Reverse(event.data), Reverse(event.time), event.op))
+            }
+            (SortBy::Sst, _) => records.sort_by_key(|event| (event.data, event.time, event.op)),
+        }
+    }
+
+    /// Writes records already in output order, cut to --limit or --tail,
+    /// and prints their --checksum.
+    pub fn write_parsed(
+        &self,
+        records: &mut Vec<Event>,
+        quiet: bool,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        if let Some(limit) = self.limit {
+            records.truncate(limit);
+        }
+        if let Some(tail) = self.tail {
+            records.drain(..records.len().saturating_sub(tail));
+        }
+        // Partitions are written one after the other, each in output order.
+        if self.partition_by.is_some() {
+            records.sort_by_key(|event| event.data.sst);
+        }
+        self.write(records.iter().copied(), records.len(), quiet, context)?;
+        if self.checksum {
+            eprintln!("Checksum: sha256:{}", checksum(records, context));
+        }
+        Ok(())
+    }
+
+    /// Parses `dirs` into spill files sorted by `key`, then writes their
+    /// merge. Returns the number of records written and dropped as
+    /// duplicates, which with --limit only counts those before the limit.
+    fn stream<K: Ord + 'static>(
+        &self,
+        input: &InputArgs,
+        dirs: &[PathBuf],
+        schema: &Schema,
+        dedup: Option<Duration>,
+        key: fn(&Event) -> K,
+        context: &Context,
+    ) -> anyhow::Result<(usize, usize)> {
+        let spill_dir = self
+            .spill_dir
+            .clone()
+            .map_or_else(std::env::temp_dir, PathBuf::from);
+        // Partitions are written one after the other.
+        let partition = self.partition_by.is_some();
+        let key = move |event: &Event| (partition.then_some(event.data.sst), key(event));
+        let mut spill = Spill {
+            sorter: ExternalSort::new(key, self.chunk_size(), spill_dir.clone()),
+            progress: input.progress(),
+            error: None,
+        };
+        for dir in dirs {
+            input.stream(dir, schema, context, &mut spill)?;
+        }
+        spill.progress.report();
+        if let Some(e) = spill.error {
+            return Err(e)
+                .with_context(|| format!("can't write spill files to {}", spill_dir.display()));
+        }
+
+        let total = spill.sorter.len();
+        let spinner = spinner(format!("Sorting {total} records"), input.quiet);
+        let merged = spill.sorter.finish()?;
+        spinner.finish_and_clear();
+        let mut previous = None;
+        let mut dropped = 0;
+        let mut written = 0;
+        let mut near = dedup.filter(|window| !window.is_zero()).map(Dedup::new);
+        // Stops at the first spill file that can't be read back.
+        let mut failed = None;
+        let records =
+            (merged.map_while(|event| event.map_err(|e| failed = Some(e)).ok())).filter(|event| {
+                let duplicate = match &mut near {
+                    Some(near) => !near.keep(event),
+                    None => dedup.is_some() && previous == Some(*event),
+                };
+                previous = Some(*event);
+                dropped += duplicate as usize;
+                !duplicate
+            });
+        match (self.limit, self.tail) {
+            (Some(limit), _) => {
+                let records = records.take(limit).inspect(|_| written += 1);
+                self.write(records, total.min(limit), input.quiet, context)?;
+            }
+            (_, Some(tail)) => {
+                let mut last = VecDeque::with_capacity(tail.min(total));
+                for event in records {
+                    if last.len() == tail {
+                        last.pop_front();
+                    }
+                    if tail > 0 {
+                        last.push_back(event);
+                    }
+                }
+                written = last.len();
+                self.write(last.into_iter(), written, input.quiet, context)?;
+            }
+            _ => {
+                let records = records.inspect(|_| written += 1);
+                self.write(records, total, input.quiet, context)?;
+            }
+        }
+        if let Some(e) = failed {
+            return Err(e).context("can't read back spill files");
+        }
+        Ok((written, dropped))
+    }
+
+    /// Writes `total` sorted records to the output file, or with
+    /// --partition-by, records grouped by SST to one file each.
+    fn write(
+        &self,
+        records: impl Iterator<Item = Event>,
+        total: usize,
+        quiet: bool,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        if self.format == Format::Parquet && self.compress.is_some() {
+            anyhow::bail!("parquet is compressed internally, without --compress");
+        }
+        if self.format == Format::Parquet && self.max_out_size.is_some() {
+            anyhow::bail!("parquet is written in row groups, without --max-out-size");
+        }
+        if self.format == Format::Parquet && self.max_output_bytes.is_some() {
+            anyhow::bail!("parquet is written in row groups, without --max-output-bytes");
+        }
+        if self.grouped && self.format != Format::Text {
+            anyhow::bail!("--grouped only works with the text format");
+        }
+        let bar = bar(
+            total,
+            "Writing [{bar:30}] {pos}/{len} records ({eta})",
+            quiet,
+        );
+        if let Some(max) = self.max_out_size {
+            self.write_parts(records, max, total, &bar, context)?;
+            bar.finish_and_clear();
+            return Ok(());
+        }
+        if self.partition_by.is_none() {
+            self.write_to(Path::new(&self.out), records, None, total, &bar, context)?;
+            bar.finish_and_clear();
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.out).with_context(|| format!("can't create {}", self.out))?;
+        let mut records = records.peekable();
+        while let Some(sst) = records.peek().map(|event| event.data.sst) {
+            let compressed = self.compress.map_or("", Compress::extension);
+            let path = Path::new(&self.out).join(format!(
+                "sst_{sst:06}.{}{compressed}",
+                self.format.extension()
+            ));
+            let partition = std::iter::from_fn(|| records.next_if(|event| event.data.sst == sst));
+            self.write_to(&path, partition, None, total, &bar, context)?;
+        }
+        bar.finish_and_clear();
+        Ok(())
+    }
+
+    /// Writes records to numbered parts of about `max` bytes each next to
+    /// --out, and their index.
+    fn write_parts(
+        &self,
+        records: impl Iterator<Item = Event>,
+        max: u64,
+        total: usize,
+        bar: &ProgressBar,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        let out = Path::new(&self.out);
+        let name = out
+            .file_name()
+            .map_or(self.out.clone(), |name| name.to_string_lossy().into_owned());
+        // The part number goes before all extensions, such as `.txt.gz`.
+        let (stem, mut extension) = match name.split_once('.') {
+            Some((stem, extension)) => (stem, format!(".{extension}")),
+            None => (name.as_str(), String::new()),
+        };
+        let compressed = self.compress.map_or("", Compress::extension);
+        if !extension.ends_with(compressed) {
+            extension.push_str(compressed);
+        }
+        let index_path = out.with_file_name(format!("{stem}.index.csv"));
+        let mut index = csv::Writer::from_writer(create(&index_path)?);
+        index.write_record(["part", "records", "start", "end"])?;
+        let mut records = records.peekable();
+        for number in 1.. {
+            let path = out.with_file_name(format!("{stem}.{number:04}{extension}"));
+            let part = self.write_to(&path, records.by_ref(), Some(max), total, bar, context)?;
+            let (start, end) = part
+                .times
+                .map_or((String::new(), String::new()), |(start, end)| {
+                    (
+                        context.format_time(start).to_string(),
+                        context.format_time(end).to_string(),
+                    )
+                });
+            let file = path.file_name().unwrap().to_string_lossy();
+            index.write_record([&file, part.records.to_string().as_str(), &start, &end])?;
+            if records.peek().is_none() {
+                break;
+            }
+        }
+        index.flush()?;
+        Ok(())
+    }
+
+    /// Writes sorted records to the file at `path`, one of `total`, and with
+    /// `part_bytes` only until it holds that many bytes.
+    fn write_to(
+        &self,
+        path: &Path,
+        records: impl Iterator<Item = Event>,
+        part_bytes: Option<u64>,
+        total: usize,
+        bar: &ProgressBar,
+        context: &Context,
+    ) -> anyhow::Result<Part> {
+        let output_file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(self.state.is_some())
+            .truncate(self.state.is_none())
+            .open(path)
+            .with_context(|| format!("can't create {}", path.display()))?;
+        let mut writer = BackgroundWriter::compressed(output_file, self.compress);
+        let mut section = None;
+        let mut written = 0;
+        let sep = &self.field_sep;
+        let template = (self.template.clone())
+            .or_else(|| (!self.columns.is_empty()).then(|| Template::columns(&self.columns, sep)));
+        let mut encoder = Encoder::new(self.format);
+        let mut line = vec![];
+        let mut part = Part::default();
+
+        // Parquet is written in row groups, without a byte budget.
+        if self.format == Format::Parquet {
+            let mut parquet = ParquetWriter::new(writer, context)?;
+            for event in records {
+                parquet.write(&event)?;
+                part.add(&event);
+                bar.inc(1);
+            }
+            parquet.finish()?.finish()?;
+            return Ok(part);
+        }
+
+        for (row, event) in records.enumerate() {
+            let Event {
+                data,
+                time,
+                op,
+                origin,
+            } = event;
+            line.clear();
+            if let Some(encoder) = &mut encoder {
+                encoder
+                    .encode(&EventRecord::new(&event, context), &mut line)
+                    .unwrap();
+            } else if let Some(template) = &template {
+                template.write(&event, context, &mut line);
+            } else {
+                if self.grouped {
+                    if section != Some(op) {
+                        writeln!(line, "{}", op.header()).unwrap();
+                        section = Some(op);
+                    }
+                    let (data, time) = (context.data(data), context.format_time(time));
+                    write!(line, "    {data}{sep}{time}").unwrap();
+                } else {
+                    let (data, time) = (context.data(data), context.format_time(time));
+                    write!(line, "{data}{sep}{time}{sep}{op:?}").unwrap();
+                }
+                if context.has_provenance() {
+                    write!(line, "{sep}{}", context.origin(origin)).unwrap();
+                }
+                line.push(b'\n');
+            }
+            if self
+                .max_output_bytes
+                .is_some_and(|max| written + line.len() as u64 > max)
+            {
+                bar.suspend(|| {
+                    println!(
+                        "Output truncated at {written} bytes, {row} of {total} records written"
+                    )
+                });
+                break;
+            }
+            writer.write_all(&line)?;
+            written += line.len() as u64;
+            part.add(&event);
+            bar.inc(1);
+            if part_bytes.is_some_and(|max| written >= max) {
+                break;
+            }
+        }
+        if let Some(encoder) = &encoder {
+            line.clear();
+            encoder.finish(&mut line);
+            writer.write_all(&line)?;
+        }
+        writer.finish()?;
+        Ok(part)
+    }
+}
+
+/// What [`OutputArgs::write_to`] wrote.
+#[derive(Debug, Default)]
+struct Part {
+    records: usize,
+    /// Of the earliest and the latest record.
+    times: Option<(SystemTime, SystemTime)>,
+}
+
+impl Part {
+    fn add(&mut self, event: &Event) {
+        self.records += 1;
+        let (start, end) = self.times.get_or_insert((event.time, event.time));
+        *start = (*start).min(event.time);
+        *end = (*end).max(event.time);
+    }
+}
+
+/// Parses what was added to `dirs` since the run that saved the offsets at
+/// `path`, returning the new events and the offsets to save next.
+fn resume(
+    input: &InputArgs,
+    dirs: &[PathBuf],
+    schema: &Schema,
+    path: &str,
+    context: &Context,
+    progress: &mut Progress,
+) -> anyhow::Result<(Vec<Event>, Offsets)> {
+    let mut offsets = match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).with_context(|| format!("can't load {path}"))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Offsets::default(),
+        Err(e) => return Err(e).with_context(|| format!("can't read {path}")),
+    };
+    let options = input.options(false, context)?;
+    let mut events = vec![];
+    for dir in dirs {
+        anyhow::ensure!(
+            fs::metadata(dir).is_ok_and(|meta| meta.is_dir()),
+            "--state needs input directories, not {}",
+            dir.display()
+        );
+        let mut follower = Follower::new(dir, schema, &options, context).with_offsets(offsets);
+        events.extend(follower.poll(progress)?);
+        offsets = follower.into_offsets();
+    }
+    Ok((events, offsets))
+}
+
+/// Feeds parsed events to an [`ExternalSort`].
+struct Spill<K> {
+    sorter: ExternalSort<K>,
+    progress: Progress,
+    /// The first spill file that couldn't be written, after which events are
+    /// only counted.
+    error: Option<io::Error>,
+}
+
+impl<K: Ord> Observer for Spill<K> {
+    fn on_events(&mut self, events: &[Event]) {
+        for event in events {
+            if self.error.is_some() {
+                break;
+            }
+            if let Err(e) = self.sorter.push(*event) {
+                self.error = Some(e);
+            }
+        }
+        self.progress.on_events(events);
+    }
+
+    fn on_skipped(&mut self, error: ParseError) -> Result<(), ParseError> {
+        self.progress.on_skipped(error)
+    }
+
+    fn on_inputs(&mut self, count: usize) {
+        self.progress.on_inputs(count);
+    }
+
+    fn on_parsed(&mut self, file: &str) {
+        self.progress.on_parsed(file);
+    }
+}
+
+/// Progress reporting while the input is parsed.
+#[derive(Debug, Default)]
+pub struct Progress {
+    processed: usize,
+    /// Print estimated delta percentiles every this many records.
+    pub stats_interval: Option<usize>,
+    stats: StreamingStats,
+    /// Re-emit the running summary after each file, matching like this.
+    pub running: Option<(RunningSummary, Matching)>,
+    /// Write the running summary here instead of stderr.
+    pub status_file: Option<String>,
+    quiet: bool,
+    /// Stop at the first skipped input.
+    strict: bool,
+    /// Number of skipped records per file, in parse order.
+    skipped: Vec<(String, usize)>,
+    /// Files or directories that couldn't be read, or only in part.
+    unreadable: Vec<String>,
+    /// File, line and op of each section banner without entries.
+    pub empty_sections: Vec<(String, u64, Op)>,
+    /// Files parsed so far of the current directory.
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    /// Prints `line` to stderr without tearing the progress bar.
+    fn note(&self, line: &str) {
+        match &self.bar {
+            Some(bar) => bar.suspend(|| eprintln!("{line}")),
+            None => eprintln!("{line}"),
+        }
+    }
+
+    /// Prints how many records were skipped in each file, and which files
+    /// couldn't be read.
+    pub fn report(&self) {
+        for (file, count) in &self.skipped {
+            let lines = if *count == 1 { "line" } else { "lines" };
+            eprintln!("{count} {lines} skipped in {file}");
+        }
+        for file in &self.unreadable {
+            eprintln!("{file} could not be read");
+        }
+    }
+}
+
+impl Observer for Progress {
+    fn on_events(&mut self, events: &[Event]) {
+        if let Some(interval) = self.stats_interval {
+            for (i, event) in events.iter().enumerate() {
+                self.stats.observe(std::slice::from_ref(event));
+                let processed = self.processed + i + 1;
+                if processed.is_multiple_of(interval.max(1)) {
+                    let deltas = &self.stats.deltas;
+                    self.note(&match (deltas.quantile(0.5), deltas.quantile(0.99)) {
+                        (Some(p50), Some(p99)) => format!(
+                            "Processed {processed} records, delta p50: ~{p50:?}, p99: ~{p99:?}"
+                        ),
+                        _ => format!("Processed {processed} records, no deltas yet"),
+                    });
+                }
+            }
+        }
+
+        self.processed += events.len();
+        if let Some(bar) = self.bar.as_ref().filter(|_| !events.is_empty()) {
+            bar.set_message(format!("{} records", self.processed));
+        }
+    }
+
+    fn on_skipped(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if self.strict {
+            return Err(error);
+        }
+        if !self.quiet {
+            self.note(&format!("Skipping {error}"));
+        }
+        let file = error.file().to_string();
+        match (&error, self.skipped.last_mut()) {
+            (ParseError::Read { .. }, _) => self.unreadable.push(file),
+            (ParseError::Malformed { .. }, Some((last, count))) if *last == file => *count += 1,
+            (ParseError::Malformed { .. }, _) => self.skipped.push((file, 1)),
+        }
+        Ok(())
+    }
+
+    fn on_file(&mut self, file: &str, events: &[Event]) {
+        if let Some((running, matching)) = &mut self.running {
+            running.update(events, *matching);
+            let Counts { long, short, none } = running.counts();
+            let line = format!("{file}: long: {long}, short: {short}, none: {none}");
+            match &self.status_file {
+                Some(path) => {
+                    if let Err(e) = fs::write(path, line + "\n") {
+                        // The parse goes on, with the summary on stderr.
+                        self.note(&format!("Warning: can't write {path}: {e}"));
+                        self.status_file = None;
+                    }
+                }
+                None => self.note(&line),
+            }
+        }
+    }
+
+    fn on_empty_section(&mut self, file: &str, line: u64, op: Op) {
+        self.empty_sections.push((file.to_string(), line, op));
+    }
+
+    fn on_inputs(&mut self, count: usize) {
+        let bar = bar(
+            count,
+            "Parsing [{bar:30}] {pos}/{len} files, {msg} ({eta})",
+            self.quiet,
+        );
+        bar.set_message(format!("{} records", self.processed));
+        self.bar = Some(bar);
+    }
+
+    fn on_parsed(&mut self, _file: &str) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+            if bar.position() == bar.length().unwrap_or(0) {
+                bar.finish_and_clear();
+            }
+        }
+    }
+}
+
+/// A bar counting up to `len`, drawn on stderr unless `quiet`.
+fn bar(len: usize, template: &str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len as u64).with_style(ProgressStyle::with_template(template).unwrap())
+}
+
+/// A spinner showing `message` until finished, unless `quiet`.
+fn spinner(message: String, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// Sorts `records` newest first, the order every report expects.
+pub fn sort(records: &mut [Event], quiet: bool) {
+    let spinner = spinner(format!("Sorting {} records", records.len()), quiet);
+    records.sort_by_key(newest_first);
+    spinner.finish_and_clear();
+}
+
+/// The key [`sort`] orders by, which breaks timestamp ties by block, then op.
+pub fn newest_first(event: &Event) -> (Reverse<SystemTime>, Data, Op) {
+    (Reverse(event.time), event.data, event.op)
+}
+
+/// Drops the `warmup` of `records`, if given, warning when it has no end.
+pub fn skip_warmup(records: &mut Vec<Event>, warmup: Option<Warmup>) -> Option<Skipped> {
+    let warmup = warmup?;
+    let Some(end) = warmup_end(records, warmup) else {
+        if warmup == Warmup::UntilFirstEviction {
+            eprintln!("Warning: no evictions to end the warm-up at, skipped none");
+        }
+        return None;
+    };
+    Some(warmup::skip_warmup(records, end))
+}
+
+/// Drops exact duplicates from `records` sorted by [`sort`], and with a
+/// non-zero `window` events at most that far apart from a kept event of the
+/// same block and op.
+pub fn drop_duplicates(records: &mut Vec<Event>, window: Duration) {
+    if window.is_zero() {
+        records.dedup();
+    } else {
+        let mut dedup = Dedup::new(window);
+        records.retain(|event| dedup.keep(event));
+    }
+}
+
+pub fn create(path: impl AsRef<Path>) -> anyhow::Result<BufWriter<fs::File>> {
+    let path = path.as_ref();
+    let file =
+        fs::File::create(path).with_context(|| format!("can't create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Like [`create`], for large outputs written while records are still being
+/// formatted, compressed with `compress` if given.
+pub fn create_background(
+    path: &str,
+    compress: Option<Compress>,
+) -> anyhow::Result<BackgroundWriter> {
+    let file = fs::File::create(path).with_context(|| format!("can't create {path}"))?;
+    Ok(BackgroundWriter::compressed(file, compress))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_ranges() {
+        assert_eq!(parse_id_range("7"), Ok(7..=7));
+        assert_eq!(parse_id_range("3-5"), Ok(3..=5));
+        assert_eq!(parse_id_range(" 3 - 3 "), Ok(3..=3));
+        assert_eq!(
+            parse_id_range("5-3"),
+            Err("start must not exceed end".to_string())
+        );
+        assert!(parse_id_range("a-3").is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use super::{InputArgs, create, sort};
+use blocks::analysis::mrc::{mrc, write_mrc};
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Write the LRU miss ratio curve of the trace as CSV.
+#[derive(Debug, clap::Args)]
+pub struct MrcArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(short, long, default_value = "mrc.csv")]
+    out: String,
+    /// Cache sizes to report, in blocks. Defaults to powers of two up to the
+    /// number of distinct blocks.
+    #[clap(long, value_delimiter = ',')]
+    sizes: Vec<u64>,
+    /// Only track this share of the blocks, e.g. `0.01`, to bound memory on
+    /// large traces at the cost of accuracy.
+    #[clap(long, default_value_t = 1.0)]
+    sample_rate: f64,
+}
+
+pub fn run(args: MrcArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    let mrc = mrc(&records, args.sample_rate, context);
+    write_mrc(&mrc, &args.sizes, &mut create(&args.out)?)?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
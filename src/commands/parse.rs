@@ -0,0 +1,32 @@
+use super::{InputArgs, OutputArgs};
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Parse a trace directory and write its records out.
+#[derive(Debug, clap::Args)]
+pub struct ParseArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(flatten)]
+    output: OutputArgs,
+    /// Drop records that show up more than once, and with a window such as
+    /// `5ms` also those of the same block and op at most that far apart from
+    /// a kept one, and report how many were dropped.
+    #[clap(
+        long,
+        value_parser = humantime::parse_duration,
+        num_args = 0..=1,
+        default_missing_value = "0s",
+        value_name = "WINDOW"
+    )]
+    dedup: Option<Duration>,
+}
+
+pub fn run(args: ParseArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    args.output
+        .run(&args.input, &[args.dir], args.dedup, context)
+}
@@ -0,0 +1,82 @@
+use super::{InputArgs, sort};
+use blocks::format::{Encoder, EventRecord, Format};
+use blocks::model::Context;
+use blocks::query::Query;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Print the events of a trace matching a filter expression, such as
+/// `op == miss && sst == 1234 && ts > '2024-05-01 12:00'`, newest first.
+#[derive(Debug, clap::Args)]
+pub struct QueryArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// The filter: comparisons of `op`, `sst`, `blk`, `ts`, `source`, `level`
+    /// or `table` with a value, combined with `&&`, `||`, `!` and
+    /// parentheses.
+    #[clap(long = "where", value_parser = Query::parse)]
+    query: Query,
+    /// Layout of the printed events.
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Only print the first this many matches.
+    #[clap(long)]
+    limit: Option<usize>,
+    /// Only print the number of matches.
+    #[clap(long)]
+    count: bool,
+}
+
+pub fn run(args: QueryArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    if args.format == Format::Parquet {
+        anyhow::bail!("parquet can't be printed, use `parse` with --format parquet");
+    }
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+    sort(&mut records, args.input.quiet);
+
+    let mut matched = 0;
+    let out = &mut std::io::stdout().lock();
+    let mut encoder = Encoder::new(args.format);
+    let mut line = vec![];
+    for event in records
+        .iter()
+        .filter(|event| args.query.matches(event, context))
+    {
+        matched += 1;
+        if args.count || args.limit.is_some_and(|limit| matched > limit) {
+            continue;
+        }
+        line.clear();
+        match &mut encoder {
+            Some(encoder) => encoder.encode(&EventRecord::new(event, context), &mut line)?,
+            None => writeln!(
+                line,
+                "{}, {}, {:?}",
+                context.data(event.data),
+                context.format_time(event.time),
+                event.op
+            )?,
+        }
+        out.write_all(&line)?;
+    }
+    if let Some(encoder) = &encoder
+        && !args.count
+    {
+        line.clear();
+        encoder.finish(&mut line);
+        out.write_all(&line)?;
+    }
+
+    println!("Matched: {matched}");
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,79 @@
+use super::{InputArgs, create, sort};
+use blocks::analysis::durations::Matching;
+use blocks::analysis::report::{ReportOptions, write_html};
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Write the summary, re-miss intervals, miss rate and top-K tables of a
+/// trace into one self-contained HTML file.
+#[derive(Debug, clap::Args)]
+pub struct ReportArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(long, default_value = "report.html")]
+    html: String,
+    /// Heading of the report, the input by default.
+    #[clap(long)]
+    title: Option<String>,
+    /// Misses this soon after their eviction count as short, e.g. `500ms`,
+    /// `30s` or `5m`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    short_threshold: Duration,
+    /// Ignore evictions more than this many seconds before a miss when
+    /// matching them.
+    #[clap(long)]
+    max_lookback_secs: Option<u64>,
+    /// Comma separated upper bounds of the re-miss interval buckets.
+    #[clap(
+        long,
+        value_parser = humantime::parse_duration,
+        value_delimiter = ',',
+        default_value = "100ms,1s,10s,1m,10m,1h"
+    )]
+    histogram_buckets: Vec<Duration>,
+    /// Width of the time buckets of the miss rate chart.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    bucket: Duration,
+    /// Number of rows of the top SST and block tables.
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+}
+
+pub fn run(args: ReportArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    let options = ReportOptions {
+        matching: Matching {
+            lookback: args.max_lookback_secs.map(Duration::from_secs),
+            short_threshold: args.short_threshold,
+            ..Matching::default()
+        },
+        bounds: args.histogram_buckets,
+        bucket: args.bucket,
+        top: args.top,
+    };
+    let title = args
+        .title
+        .unwrap_or(format!("blocks report: {}", args.dir.display()));
+    write_html(
+        &records,
+        &title,
+        &options,
+        context,
+        &mut create(&args.html)?,
+    )?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
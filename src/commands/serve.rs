@@ -0,0 +1,297 @@
+use super::{InputArgs, sort};
+use anyhow::Context as _;
+use blocks::analysis::durations::{Matching, RunningSummary};
+use blocks::analysis::summary::{summarize, write_summary};
+use blocks::analysis::topk::{Hot, top_evicted_ssts, top_missed_blocks};
+use blocks::format::{EventRecord, ts_nanos};
+use blocks::model::{Context, Data, Event};
+use blocks::parse::Follower;
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, RwLock, mpsc};
+use std::time::Duration;
+
+/// Answer HTTP requests for analyses of a trace with JSON, for those without
+/// the tool or the trace at hand: `/summary`, `/topk?n=10` with the most
+/// missed blocks and most evicted SSTs, and `/block/{sst}/{blk}/timeline`
+/// with the events of one block, oldest first, of any kind of key unless
+/// picked with `?kind=`.
+#[derive(Debug, clap::Args)]
+pub struct ServeArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// Keep watching the input directory and answer with the records added
+    /// to it too.
+    #[clap(long)]
+    follow: bool,
+    /// Number of blocks and SSTs `/topk` lists without `?n=`.
+    #[clap(long, default_value_t = 10)]
+    top: usize,
+    /// Misses this soon after their eviction count as short in `/summary`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    short_threshold: Duration,
+    /// Ignore evictions more than this many seconds before a miss when
+    /// matching them.
+    #[clap(long)]
+    max_lookback_secs: Option<u64>,
+}
+
+pub fn run(args: ServeArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let matching = Matching {
+        lookback: args.max_lookback_secs.map(Duration::from_secs),
+        short_threshold: args.short_threshold,
+        ..Matching::default()
+    };
+    let listener = TcpListener::bind(args.listen)
+        .with_context(|| format!("can't listen on {}", args.listen))?;
+    let trace = Arc::new(RwLock::new(Trace::default()));
+
+    if !args.follow {
+        let events = args
+            .input
+            .parse(&args.dir, &schema, context, &mut progress)?
+            .events;
+        progress.report();
+        trace
+            .write()
+            .unwrap()
+            .add(events, matching, args.input.quiet);
+        println!(
+            "Serving {} records on http://{}",
+            trace.read().unwrap().events.len(),
+            args.listen
+        );
+        serve(listener, trace, args.top, context);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    anyhow::ensure!(args.dir.is_dir(), "--follow needs an input directory");
+    let options = args.input.options(false, context)?;
+    let mut follower = Follower::new(&args.dir, &schema, &options, context);
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mode = if args.input.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(&args.dir, mode)?;
+    let events = follower.poll(&mut progress)?;
+    trace
+        .write()
+        .unwrap()
+        .add(events, matching, args.input.quiet);
+    println!(
+        "Serving {} records on http://{}",
+        trace.read().unwrap().events.len(),
+        args.listen
+    );
+    let served = trace.clone();
+    std::thread::scope(|scope| {
+        scope.spawn(move || serve(listener, served, args.top, context));
+        loop {
+            // One poll picks up every change made in the meantime.
+            changes.recv()??;
+            while changes.try_recv().is_ok() {}
+            let events = follower.poll(&mut progress)?;
+            if !events.is_empty() {
+                trace.write().unwrap().add(events, matching, true);
+            }
+        }
+    })
+}
+
+/// The records served, newest first, and their long/short/none counts.
+#[derive(Debug, Default)]
+struct Trace {
+    events: Vec<Event>,
+    running: RunningSummary,
+}
+
+impl Trace {
+    fn add(&mut self, mut events: Vec<Event>, matching: Matching, quiet: bool) {
+        self.running.update(&events, matching);
+        self.events.append(&mut events);
+        sort(&mut self.events, quiet);
+    }
+}
+
+/// Answers every connection to `listener` on a thread of its own.
+fn serve(listener: TcpListener, trace: Arc<RwLock<Trace>>, top: usize, context: &Context) {
+    std::thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            let trace = &trace;
+            scope.spawn(move || {
+                // A request that goes wrong only affects that request.
+                let _ = answer(stream, trace, top, context);
+            });
+        }
+    })
+}
+
+/// Reads one request from `stream` and writes the JSON response.
+fn answer(
+    mut stream: TcpStream,
+    trace: &RwLock<Trace>,
+    top: usize,
+    context: &Context,
+) -> std::io::Result<()> {
+    let mut request = BufReader::new(&stream);
+    let mut start = String::new();
+    request.read_line(&mut start)?;
+    let mut line = String::new();
+    while request.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let mut parts = start.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = if method != "GET" {
+        error(405, format!("only GET is supported, not {method}"))
+    } else {
+        match route(target, &trace.read().unwrap(), top, context) {
+            Ok(body) => (200, body),
+            Err((status, message)) => error(status, message),
+        }
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
+
+fn error(status: u16, message: String) -> (u16, Vec<u8>) {
+    let body = serde_json::json!({ "error": message });
+    (status, format!("{body}\n").into_bytes())
+}
+
+/// The body answering `target`, or the status and message of the error.
+fn route(
+    target: &str,
+    trace: &Trace,
+    top: usize,
+    context: &Context,
+) -> Result<Vec<u8>, (u16, String)> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+    let mut body = vec![];
+    match segments.as_slice() {
+        ["summary"] => {
+            let summary = summarize(&trace.events);
+            write_summary(&summary, trace.running.counts(), &mut body).unwrap();
+        }
+        ["topk"] => {
+            let n = match param(query, "n") {
+                Some(n) => number(n)? as usize,
+                None => top,
+            };
+            let record = TopRecord {
+                missed_blocks: (top_missed_blocks(&trace.events, n).into_iter())
+                    .map(|hot| HotBlock::new(hot, context))
+                    .collect(),
+                evicted_ssts: (top_evicted_ssts(&trace.events, n).into_iter())
+                    .map(HotSst::from)
+                    .collect(),
+            };
+            serde_json::to_writer_pretty(&mut body, &record).unwrap();
+            body.push(b'\n');
+        }
+        ["block", sst, blk, "timeline"] => {
+            let (sst, blk) = (number(sst)?, number(blk)?);
+            let kind = param(query, "kind");
+            // Newest first, so walk them backwards.
+            let events: Vec<EventRecord> = (trace.events.iter().rev())
+                .filter(|event| event.data.sst == sst && event.data.blk == blk)
+                .filter(|event| {
+                    kind.is_none_or(|kind| *context.kind_label(event.data.kind) == *kind)
+                })
+                .map(|event| EventRecord::new(event, context))
+                .collect();
+            serde_json::to_writer_pretty(&mut body, &events).unwrap();
+            body.push(b'\n');
+        }
+        _ => return Err((404, format!("no such endpoint {path}"))),
+    }
+    Ok(body)
+}
+
+/// The value of `name` in the query string `query`.
+fn param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    (query.split('&')).find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn number(s: &str) -> Result<u64, (u16, String)> {
+    s.parse().map_err(|_| (400, format!("{s} is not a number")))
+}
+
+/// The `/topk` response.
+#[derive(Debug, Serialize)]
+struct TopRecord {
+    missed_blocks: Vec<HotBlock>,
+    evicted_ssts: Vec<HotSst>,
+}
+
+#[derive(Debug, Serialize)]
+struct HotBlock {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Arc<str>>,
+    /// Only with kinds of keys besides data blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<Arc<str>>,
+    sst_id: u64,
+    block_idx: u64,
+    misses: u64,
+    first_ts_nanos: u64,
+    last_ts_nanos: u64,
+}
+
+impl HotBlock {
+    fn new(hot: Hot<Data>, context: &Context) -> Self {
+        Self {
+            source: context.source_name(hot.key.source),
+            kind: (context.has_kinds()).then(|| context.kind_label(hot.key.kind)),
+            sst_id: hot.key.sst,
+            block_idx: hot.key.blk,
+            misses: hot.count,
+            first_ts_nanos: ts_nanos(hot.first),
+            last_ts_nanos: ts_nanos(hot.last),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct HotSst {
+    sst_id: u64,
+    evictions: u64,
+    first_ts_nanos: u64,
+    last_ts_nanos: u64,
+}
+
+impl From<Hot<u64>> for HotSst {
+    fn from(hot: Hot<u64>) -> Self {
+        Self {
+            sst_id: hot.key,
+            evictions: hot.count,
+            first_ts_nanos: ts_nanos(hot.first),
+            last_ts_nanos: ts_nanos(hot.last),
+        }
+    }
+}
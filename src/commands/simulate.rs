@@ -0,0 +1,57 @@
+use super::{InputArgs, sort};
+use blocks::analysis::simulate::{Replay, replay, replay_optimal};
+use blocks::cache::Policy;
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Replay the trace against cache models and print their hit ratios, next to
+/// the best possible one.
+#[derive(Debug, clap::Args)]
+pub struct SimulateArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// Cache sizes to simulate, in blocks.
+    #[clap(long, value_delimiter = ',', required = true)]
+    capacity: Vec<usize>,
+    /// Eviction policies to simulate. Defaults to all of them.
+    #[clap(long, value_enum, value_delimiter = ',')]
+    policy: Vec<Policy>,
+}
+
+pub fn run(args: SimulateArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    let policies = if args.policy.is_empty() {
+        Policy::ALL.to_vec()
+    } else {
+        args.policy
+    };
+    let print = |name: &str, capacity: usize, result: Replay| {
+        println!(
+            "{name} capacity {capacity}: hits: {}, misses: {}, hit ratio: {:.2}%",
+            result.hits,
+            result.accesses - result.hits,
+            result.hit_ratio() * 100.0
+        )
+    };
+    for &capacity in &args.capacity {
+        for &policy in &policies {
+            let result = replay(&records, policy.build(capacity).as_mut());
+            print(policy.name(), capacity, result);
+        }
+        print("opt", capacity, replay_optimal(&records, capacity));
+    }
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
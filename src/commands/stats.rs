@@ -0,0 +1,161 @@
+use super::{InputArgs, Progress, skip_warmup};
+use blocks::analysis::sketch::Sketch;
+use blocks::analysis::summary::{Summary, summarize};
+use blocks::analysis::warmup::Warmup;
+use blocks::model::{Context, Event, Op};
+use blocks::parse::{Error as ParseError, Observer};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Print overall counts of a trace directory.
+#[derive(Debug, clap::Args)]
+pub struct StatsArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    /// Estimate the distinct blocks and SSTs in one pass over the events as
+    /// they're parsed, without keeping them, and print the approximate
+    /// --top most missed blocks and most evicted SSTs.
+    #[clap(long)]
+    sketch: bool,
+    /// Number of blocks and SSTs listed with --sketch.
+    #[clap(long, default_value_t = 10, requires = "sketch")]
+    top: usize,
+    /// The distinct counts of --sketch are within about
+    /// `1.04 / sqrt(2^precision)`, with `2^precision` bytes each.
+    #[clap(long, default_value_t = 14, value_parser = clap::value_parser!(u32).range(4..=18), requires = "sketch")]
+    sketch_precision: u32,
+    /// Blocks and SSTs kept as candidates for the --sketch top lists, those
+    /// estimated to have the most misses or evictions.
+    #[clap(long, default_value_t = 1000, requires = "sketch")]
+    sketch_candidates: usize,
+    /// Leave out the warm-up at the start of the trace, while the cache
+    /// fills up: the events within this long of the oldest one, such as
+    /// `5m`, or all before the first eviction with `until-first-eviction`.
+    #[clap(
+        long,
+        value_name = "DURATION|until-first-eviction",
+        conflicts_with = "sketch"
+    )]
+    skip_warmup: Option<Warmup>,
+}
+
+pub fn run(args: StatsArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    if args.sketch {
+        return sketch(args, context);
+    }
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+    let warmup = skip_warmup(&mut records, args.skip_warmup);
+
+    let summary = Summary {
+        warmup,
+        ..summarize(&records)
+    };
+    println!("events: {}", summary.events);
+    println!("evicted: {}", summary.evicted);
+    println!("missed: {}", summary.missed);
+    // Only some builds log these.
+    if summary.inserted + summary.hit > 0 {
+        println!("inserted: {}", summary.inserted);
+        println!("hit: {}", summary.hit);
+    }
+    println!("blocks: {}", summary.blocks);
+    println!("ssts: {}", summary.ssts);
+    if let Some((first, last)) = summary.range {
+        println!("first: {}", context.format_time(first));
+        println!("last: {}", context.format_time(last));
+        println!("span: {:?}", last.duration_since(first).unwrap());
+    }
+    if let Some(skipped) = summary.warmup {
+        println!("warm-up end: {}", context.format_time(skipped.end));
+        println!("warm-up events: {}", skipped.events);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The output of [`run`], with the distinct counts and top lists estimated.
+fn sketch(args: StatsArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut sketching = Sketching {
+        sketch: Sketch::new(args.sketch_precision, args.sketch_candidates),
+        progress: args.input.progress(),
+    };
+    args.input
+        .stream(&args.dir, &schema, context, &mut sketching)?;
+    sketching.progress.report();
+
+    let sketch = sketching.sketch;
+    println!("events: {}", sketch.events);
+    println!("evicted: {}", sketch.evicted);
+    println!("missed: {}", sketch.missed);
+    if sketch.inserted + sketch.hit > 0 {
+        println!("inserted: {}", sketch.inserted);
+        println!("hit: {}", sketch.hit);
+    }
+    println!("blocks: ~{}", sketch.blocks.estimate());
+    println!("ssts: ~{}", sketch.ssts.estimate());
+    if let Some((first, last)) = sketch.range {
+        println!("first: {}", context.format_time(first));
+        println!("last: {}", context.format_time(last));
+        println!("span: {:?}", last.duration_since(first).unwrap());
+    }
+    println!(
+        "Top {} blocks by misses, at most `error` too many:",
+        args.top
+    );
+    for heavy in sketch.missed_blocks.top(args.top) {
+        println!(
+            "{}, misses: {}, error: {}",
+            context.data(heavy.key),
+            heavy.count,
+            heavy.error
+        );
+    }
+    println!(
+        "Top {} SSTs by evictions, at most `error` too many:",
+        args.top
+    );
+    for heavy in sketch.evicted_ssts.top(args.top) {
+        println!(
+            "sst: {}, evictions: {}, error: {}",
+            heavy.key, heavy.count, heavy.error
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Feeds parsed events to a [`Sketch`].
+struct Sketching {
+    sketch: Sketch,
+    progress: Progress,
+}
+
+impl Observer for Sketching {
+    fn on_events(&mut self, events: &[Event]) {
+        self.sketch.observe(events);
+        self.progress.on_events(events);
+    }
+
+    fn on_skipped(&mut self, error: ParseError) -> Result<(), ParseError> {
+        self.progress.on_skipped(error)
+    }
+
+    fn on_inputs(&mut self, count: usize) {
+        self.progress.on_inputs(count);
+    }
+
+    fn on_parsed(&mut self, file: &str) {
+        self.progress.on_parsed(file);
+    }
+
+    fn on_empty_section(&mut self, file: &str, line: u64, op: Op) {
+        self.progress.on_empty_section(file, line, op);
+    }
+}
@@ -0,0 +1,34 @@
+use super::{InputArgs, sort};
+use blocks::analysis::timeline::write_timelines;
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Print the events of the blocks picked with --sst and --block oldest
+/// first, with the time between consecutive events of a block.
+#[derive(Debug, clap::Args)]
+pub struct TimelineArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+}
+
+pub fn run(args: TimelineArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    if args.input.sst.is_empty() && args.input.block.is_empty() {
+        anyhow::bail!("pick the blocks to show with --sst, --block or both");
+    }
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+    sort(&mut records, args.input.quiet);
+
+    write_timelines(&records, context, &mut std::io::stdout().lock())?;
+
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
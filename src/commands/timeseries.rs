@@ -0,0 +1,37 @@
+use super::{InputArgs, create, sort};
+use blocks::analysis::timeseries::write_timeseries;
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Write per-interval eviction, miss and hit counts and the hit rate as CSV.
+#[derive(Debug, clap::Args)]
+pub struct TimeseriesArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(short, long, default_value = "timeseries.csv")]
+    out: String,
+    /// Width of each time bucket, e.g. `500ms`, `10s` or `1m`. Buckets
+    /// without events are left out.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    bucket: Duration,
+}
+
+pub fn run(args: TimeseriesArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    write_timeseries(&records, args.bucket, context, &mut create(&args.out)?)?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
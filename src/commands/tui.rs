@@ -0,0 +1,281 @@
+use super::{InputArgs, sort};
+use blocks::model::{Context, Data, Event, Op, parse_time};
+use ratatui::crossterm::event::{self, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+/// Browse the events of a trace interactively, oldest first.
+///
+/// Keys: arrows, j/k, PageUp/PageDown and g/G scroll, `/` edits the filter,
+/// Enter jumps from an eviction to the next miss of its block or from a miss
+/// back to its eviction, n/p go to the next and previous event of the same
+/// block, and q quits.
+#[derive(Debug, clap::Args)]
+pub struct TuiArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+}
+
+pub fn run(args: TuiArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    progress.report();
+    sort(&mut records, args.input.quiet);
+    records.reverse();
+
+    let mut browser = Browser::new(records, context);
+    ratatui::run(|terminal| browser.run(terminal))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Comma separated `sst=N`, `blk=N`, `op=<op>`, `since=<time>` and
+/// `until=<time>` terms, all of which an event has to match.
+#[derive(Debug, Default)]
+struct Filter {
+    sst: Option<u64>,
+    blk: Option<u64>,
+    op: Option<Op>,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+}
+
+impl Filter {
+    fn parse(s: &str, context: &Context) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        let time =
+            |value: &str| (context.parse_formatted_time(value)).or_else(|_| parse_time(value));
+        for term in s.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+            let (key, value) = term
+                .split_once('=')
+                .ok_or(format!("expected key=value, got {term}"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let id = || value.parse().map_err(|e| format!("{term}: {e}"));
+            match key {
+                "sst" => filter.sst = Some(id()?),
+                "blk" => filter.blk = Some(id()?),
+                "op" => {
+                    let op = Op::ALL.into_iter().find(|op| op.name() == value);
+                    filter.op = Some(op.ok_or(format!("unknown op {value}"))?);
+                }
+                "since" => filter.since = Some(time(value)?),
+                "until" => filter.until = Some(time(value)?),
+                _ => return Err(format!("unknown filter {key}")),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        self.sst.is_none_or(|sst| event.data.sst == sst)
+            && self.blk.is_none_or(|blk| event.data.blk == blk)
+            && self.op.is_none_or(|op| event.op == op)
+            && self.since.is_none_or(|since| event.time >= since)
+            && self.until.is_none_or(|until| event.time < until)
+    }
+}
+
+struct Browser<'a> {
+    /// Oldest first.
+    events: Vec<Event>,
+    context: &'a Context,
+    filter: String,
+    /// Indices of the events matching the filter.
+    visible: Vec<usize>,
+    list: ListState,
+    /// The filter being edited.
+    prompt: Option<String>,
+    message: Option<String>,
+    page: usize,
+    quit: bool,
+}
+
+impl<'a> Browser<'a> {
+    fn new(events: Vec<Event>, context: &'a Context) -> Self {
+        let visible = (0..events.len()).collect();
+        Self {
+            events,
+            context,
+            filter: String::new(),
+            visible,
+            list: ListState::default().with_selected(Some(0)),
+            prompt: None,
+            message: None,
+            page: 1,
+            quit: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let event::Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.message = None;
+                if self.prompt.is_some() {
+                    self.edit(key.code);
+                } else {
+                    self.browse(key.code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [list, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        self.page = (list.height as usize).max(1);
+
+        let items = self.visible.iter().map(|&i| {
+            let Event { data, time, op, .. } = self.events[i];
+            let (time, data) = (self.context.format_time(time), self.context.data(data));
+            format!("{time}  {data}  {op:?}")
+        });
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(highlight),
+            list,
+            &mut self.list,
+        );
+
+        let line = if let Some(prompt) = &self.prompt {
+            match &self.message {
+                Some(message) => format!("filter: {prompt} | {message}"),
+                None => format!("filter: {prompt}"),
+            }
+        } else {
+            let position = self.list.selected().map_or(0, |i| i + 1);
+            let mut line = format!("{position}/{} of {}", self.visible.len(), self.events.len());
+            if !self.filter.is_empty() {
+                line += &format!(" | filter: {}", self.filter);
+            }
+            match &self.message {
+                Some(message) => line += &format!(" | {message}"),
+                None => line += " | / filter, Enter eviction<->miss, n/p same block, q quit",
+            }
+            line
+        };
+        frame.render_widget(Paragraph::new(line), status);
+    }
+
+    fn edit(&mut self, code: KeyCode) {
+        let prompt = self.prompt.as_mut().unwrap();
+        match code {
+            KeyCode::Char(c) => prompt.push(c),
+            KeyCode::Backspace => {
+                prompt.pop();
+            }
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => match Filter::parse(prompt, self.context) {
+                Ok(filter) => {
+                    self.filter = self.prompt.take().unwrap();
+                    self.apply(&filter);
+                }
+                Err(e) => self.message = Some(e),
+            },
+            _ => {}
+        }
+    }
+
+    fn browse(&mut self, code: KeyCode) {
+        let last = self.visible.len().saturating_sub(1);
+        let selected = self.list.selected().unwrap_or(0);
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::PageDown => self.select(selected + self.page),
+            KeyCode::PageUp => self.select(selected.saturating_sub(self.page)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(last),
+            KeyCode::Char('/') => self.prompt = Some(self.filter.clone()),
+            KeyCode::Enter => self.jump_to_pair(),
+            KeyCode::Char('n') => self.jump_to_block(true),
+            KeyCode::Char('p') => self.jump_to_block(false),
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, row: usize) {
+        let row = row.min(self.visible.len().saturating_sub(1));
+        self.list.select(Some(row));
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.visible.get(self.list.selected()?).copied()
+    }
+
+    /// Keeps the selection on the first matching event at or after the
+    /// selected one.
+    fn apply(&mut self, filter: &Filter) {
+        let current = self.current().unwrap_or(0);
+        self.visible = (0..self.events.len())
+            .filter(|&i| filter.matches(&self.events[i]))
+            .collect();
+        self.select(self.visible.partition_point(|&i| i < current));
+    }
+
+    /// Selects the event at `index`, unless the filter hides it.
+    fn show(&mut self, index: Option<usize>, what: &str) {
+        let Some(index) = index else {
+            self.message = Some(format!("no {what}"));
+            return;
+        };
+        match self.visible.binary_search(&index) {
+            Ok(row) => self.select(row),
+            Err(_) => self.message = Some(format!("the {what} is hidden by the filter")),
+        }
+    }
+
+    fn find(&self, from: usize, forward: bool, matches: impl Fn(&Event) -> bool) -> Option<usize> {
+        if forward {
+            (from + 1..self.events.len()).find(|&i| matches(&self.events[i]))
+        } else {
+            (0..from).rev().find(|&i| matches(&self.events[i]))
+        }
+    }
+
+    fn jump_to_pair(&mut self) {
+        let Some(current) = self.current() else {
+            return;
+        };
+        let Event { data, op, .. } = self.events[current];
+        let is = |op: Op| move |event: &Event| event.data == data && event.op == op;
+        match op {
+            Op::Evicted => self.show(self.find(current, true, is(Op::Missed)), "later miss"),
+            Op::Missed => self.show(
+                self.find(current, false, is(Op::Evicted)),
+                "earlier eviction",
+            ),
+            Op::Inserted | Op::Hit => {
+                self.message = Some("Enter jumps from evictions and misses".to_string())
+            }
+        }
+    }
+
+    fn jump_to_block(&mut self, forward: bool) {
+        let Some(current) = self.current() else {
+            return;
+        };
+        let data: Data = self.events[current].data;
+        let what = if forward {
+            "next event"
+        } else {
+            "previous event"
+        };
+        let found = self.find(current, forward, |event| event.data == data);
+        self.show(found, &format!("{what} of {}", self.context.data(data)));
+    }
+}
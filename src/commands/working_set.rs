@@ -0,0 +1,45 @@
+use super::{InputArgs, create, sort};
+use blocks::analysis::working_set::write_working_sets;
+use blocks::model::Context;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Write the number of distinct blocks accessed per sliding window as CSV.
+#[derive(Debug, clap::Args)]
+pub struct WorkingSetArgs {
+    /// Input directory or file, or `-` to read from stdin.
+    dir: PathBuf,
+    #[clap(flatten)]
+    input: InputArgs,
+    #[clap(short, long, default_value = "working_set.csv")]
+    out: String,
+    /// Width of each window, e.g. `10s`, `1m` or `1h`.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "1m")]
+    width: Duration,
+    /// Distance between the starts of consecutive windows.
+    #[clap(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    step: Duration,
+}
+
+pub fn run(args: WorkingSetArgs, context: &Context) -> anyhow::Result<ExitCode> {
+    let schema = args.input.schema(context)?;
+    let mut progress = args.input.progress();
+    let mut records = args
+        .input
+        .parse(&args.dir, &schema, context, &mut progress)?
+        .events;
+    sort(&mut records, args.input.quiet);
+
+    write_working_sets(
+        &records,
+        args.width,
+        args.step,
+        context,
+        &mut create(&args.out)?,
+    )?;
+
+    progress.report();
+    println!("Done. Total records: {}", records.len());
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,71 @@
+//! `--config` files holding options for repeated runs.
+
+use anyhow::Context;
+use std::ffi::OsString;
+use std::fs;
+use toml::{Table, Value};
+
+/// Inserts the options of the `--config` file named in `args` right after the
+/// subcommand, where options given on the command line override them.
+///
+/// Top-level keys of the file apply to every subcommand, and keys in a table
+/// named after a subcommand only to that one. Keys are long option names,
+/// `true` stands for a flag, and arrays for a repeated option.
+pub fn expand(mut args: Vec<OsString>) -> anyhow::Result<Vec<OsString>> {
+    let Some(path) = config_path(&args) else {
+        return Ok(args);
+    };
+    let content = fs::read_to_string(&path).with_context(|| format!("can't read {path}"))?;
+    let config: Table = if path.ends_with(".json") {
+        serde_json::from_str(&content).with_context(|| format!("can't load {path}"))?
+    } else {
+        toml::from_str(&content).with_context(|| format!("can't load {path}"))?
+    };
+
+    let Some(command) = args.get(1).and_then(|arg| arg.to_str()).map(str::to_string) else {
+        return Ok(args);
+    };
+    let mut options = vec![];
+    for (key, value) in &config {
+        match value {
+            Value::Table(table) if *key == command => {
+                for (key, value) in table {
+                    push_option(key, value, &mut options)?;
+                }
+            }
+            Value::Table(_) => {}
+            value => push_option(key, value, &mut options)?,
+        }
+    }
+    args.splice(2..2, options);
+    Ok(args)
+}
+
+/// The value of `--config <path>` or `--config=<path>`.
+fn config_path(args: &[OsString]) -> Option<String> {
+    let args: Vec<_> = args.iter().filter_map(|arg| arg.to_str()).collect();
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if *arg == "--config" {
+            args.get(i + 1).map(|path| path.to_string())
+        } else {
+            arg.strip_prefix("--config=").map(str::to_string)
+        }
+    })
+}
+
+fn push_option(key: &str, value: &Value, options: &mut Vec<OsString>) -> anyhow::Result<()> {
+    let flag = format!("--{}", key.replace('_', "-"));
+    match value {
+        Value::Boolean(true) => options.push(flag.into()),
+        Value::Boolean(false) => {}
+        Value::Array(values) => {
+            for value in values {
+                push_option(key, value, options)?;
+            }
+        }
+        Value::String(value) => options.push(format!("{flag}={value}").into()),
+        Value::Table(_) => anyhow::bail!("config key {key}: tables only group subcommand options"),
+        value => options.push(format!("{flag}={value}").into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Removal of near-duplicate events, such as log lines shipped twice with
+//! slightly different timestamps.
+
+use crate::model::{Data, Event, Op};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Drops events of the same block and op as a kept event at most `window`
+/// apart from it. The events of each block and op have to come in time
+/// order, either way, as they do in any sorted output.
+#[derive(Debug, Default)]
+pub struct Dedup {
+    window: Duration,
+    kept: HashMap<(Data, Op), SystemTime>,
+    dropped: usize,
+}
+
+impl Dedup {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            ..Self::default()
+        }
+    }
+
+    /// Whether `event` is kept, as opposed to dropped as a duplicate.
+    pub fn keep(&mut self, event: &Event) -> bool {
+        let key = (event.data, event.op);
+        if let Some(&kept) = self.kept.get(&key) {
+            let gap = (kept.duration_since(event.time)).unwrap_or_else(|e| e.duration());
+            if gap <= self.window {
+                self.dropped += 1;
+                return false;
+            }
+        }
+        self.kept.insert(key, event.time);
+        true
+    }
+
+    /// Number of events dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
@@ -0,0 +1,77 @@
+//! Exports of parsed events and their matches into databases.
+
+use crate::analysis::durations::Match;
+use crate::format::ts_nanos;
+use crate::model::{Data, Event};
+use rusqlite::{Connection, params};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Where to export to, given as `<kind>:<path>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Export {
+    /// A SQLite database, created if needed.
+    Sqlite(PathBuf),
+}
+
+impl FromStr for Export {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("sqlite", path)) if !path.is_empty() => Ok(Export::Sqlite(path.into())),
+            _ => Err(format!("{s}: expected sqlite:<path>")),
+        }
+    }
+}
+
+impl Export {
+    /// Replaces the `events` and `reuse` tables with `events` and the
+    /// `matched` misses.
+    ///
+    /// `reuse.delta_ns` is negative for misses only followed by an eviction,
+    /// and null for misses without any.
+    pub fn write(
+        &self,
+        events: &[Event],
+        matched: &[(Data, SystemTime, Match)],
+    ) -> rusqlite::Result<()> {
+        let Export::Sqlite(path) = self;
+        let mut connection = Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(
+            "DROP TABLE IF EXISTS events;
+             DROP TABLE IF EXISTS reuse;
+             CREATE TABLE events (sst_id INTEGER, block_idx INTEGER, ts INTEGER, op TEXT);
+             CREATE TABLE reuse (sst_id INTEGER, block_idx INTEGER, delta_ns INTEGER, miss_ts INTEGER);",
+        )?;
+        {
+            let mut insert = transaction.prepare("INSERT INTO events VALUES (?1, ?2, ?3, ?4)")?;
+            for event in events.iter().rev() {
+                let op = event.op.name();
+                insert.execute(params![
+                    event.data.sst as i64,
+                    event.data.blk as i64,
+                    ts_nanos(event.time) as i64,
+                    op
+                ])?;
+            }
+            let mut insert = transaction.prepare("INSERT INTO reuse VALUES (?1, ?2, ?3, ?4)")?;
+            for (data, miss, matched) in matched {
+                let delta = match matched {
+                    Match::Evicted(delta, _) => Some(delta.as_nanos() as i64),
+                    Match::Negative(delta) => Some(-(delta.as_nanos() as i64)),
+                    Match::None => None,
+                };
+                insert.execute(params![
+                    data.sst as i64,
+                    data.blk as i64,
+                    delta,
+                    ts_nanos(*miss) as i64
+                ])?;
+            }
+        }
+        transaction.commit()
+    }
+}
@@ -0,0 +1,377 @@
+//! Machine readable output formats.
+
+use crate::model::{Context, Event, Op};
+use crate::sst_meta::SstMeta;
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Layout of the event and duration outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum Format {
+    /// The human readable layout.
+    #[default]
+    Text,
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// CSV with a header row.
+    Csv,
+    /// Like CSV, with tabs between the fields.
+    Tsv,
+    /// An Apache Parquet file, for event output only.
+    Parquet,
+}
+
+impl Format {
+    /// File name extension of output in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Json => "json",
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// An event with the stable field names of the structured formats. The
+/// fields that are `None` are left out, which the [`Context`] decides for
+/// every record alike.
+#[derive(Debug, Serialize)]
+pub struct EventRecord {
+    /// Only with sources told apart, see [`crate::model::Data::source`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<Option<Arc<str>>>,
+    /// Only with kinds of keys besides data blocks, see
+    /// [`crate::model::Data::kind`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Arc<str>>,
+    pub sst_id: u64,
+    pub block_idx: u64,
+    pub ts_nanos: u64,
+    pub op: Op,
+    /// The rest only with SST metadata loaded, see [`crate::sst_meta`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table_id: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<Option<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<Option<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_count: Option<Option<u64>>,
+    /// The input file and line, only with provenance, see
+    /// [`Context::with_provenance`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<Option<Arc<str>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<Option<u64>>,
+}
+
+impl EventRecord {
+    /// The record of `event`, with the fields `context` has.
+    pub fn new(event: &Event, context: &Context) -> Self {
+        let with_meta = context.has_sst_meta();
+        let meta = context.sst_meta(event.data.sst);
+        let field = |value: fn(&SstMeta) -> Option<u64>| with_meta.then(|| meta.and_then(value));
+        let file = context.file_name(event.origin);
+        let line = file.as_ref().map(|_| u64::from(event.origin.line));
+        let provenance = context.has_provenance();
+        Self {
+            source: (context.has_sources()).then(|| context.source_name(event.data.source)),
+            kind: (context.has_kinds()).then(|| context.kind_label(event.data.kind)),
+            sst_id: event.data.sst,
+            block_idx: event.data.blk,
+            ts_nanos: ts_nanos(event.time),
+            op: event.op,
+            table_id: field(|meta| meta.table_id),
+            level: with_meta.then(|| meta.and_then(|meta| meta.level)),
+            file_size: field(|meta| meta.file_size),
+            block_count: field(|meta| meta.block_count),
+            file: provenance.then_some(file),
+            line: provenance.then_some(line),
+        }
+    }
+}
+
+/// Nanoseconds since the Unix epoch, 0 for times before it.
+pub fn ts_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64)
+}
+
+/// Renders records one at a time in a structured format.
+#[derive(Debug)]
+pub struct Encoder {
+    format: Format,
+    records: usize,
+}
+
+impl Encoder {
+    /// `None` for [`Format::Text`], whose layout depends on the output, and
+    /// [`Format::Parquet`], which is written by a [`ParquetWriter`].
+    pub fn new(format: Format) -> Option<Self> {
+        (format != Format::Text && format != Format::Parquet).then_some(Self { format, records: 0 })
+    }
+
+    /// Appends `record` to `buf`, preceded by whatever separates it from the
+    /// previous one.
+    pub fn encode<T: Serialize>(&mut self, record: &T, buf: &mut Vec<u8>) -> io::Result<()> {
+        let first = self.records == 0;
+        self.records += 1;
+        match self.format {
+            Format::Text | Format::Parquet => unreachable!("{:?} has no encoder", self.format),
+            Format::Json => {
+                buf.extend_from_slice(if first { b"[\n" } else { b",\n" });
+                serde_json::to_writer(&mut *buf, record)?;
+            }
+            Format::Jsonl => {
+                serde_json::to_writer(&mut *buf, record)?;
+                buf.push(b'\n');
+            }
+            Format::Csv | Format::Tsv => {
+                let delimiter = if self.format == Format::Tsv {
+                    b'\t'
+                } else {
+                    b','
+                };
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(first)
+                    .delimiter(delimiter)
+                    .from_writer(&mut *buf);
+                writer.serialize(record)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends whatever has to follow the last record.
+    pub fn finish(&self, buf: &mut Vec<u8>) {
+        if self.format == Format::Json {
+            buf.extend_from_slice(if self.records == 0 { b"[]\n" } else { b"\n]\n" });
+        }
+    }
+}
+
+/// Events buffered into each Parquet row group.
+const ROW_GROUP_SIZE: usize = 1 << 20;
+
+const PARQUET_COLUMNS: &str = "
+        REQUIRED INT64 sst_id (INTEGER(64, false));
+        REQUIRED INT64 block_idx (INTEGER(64, false));
+        REQUIRED INT64 ts_nanos (TIMESTAMP(NANOS, true));
+        REQUIRED BINARY op (STRING);
+";
+
+/// Columns before [`PARQUET_COLUMNS`] with sources told apart.
+const PARQUET_SOURCE_COLUMN: &str = "
+        REQUIRED BINARY source (STRING);
+";
+
+/// Columns before [`PARQUET_COLUMNS`] with kinds of keys besides data
+/// blocks.
+const PARQUET_KIND_COLUMN: &str = "
+        REQUIRED BINARY kind (STRING);
+";
+
+/// Columns after [`PARQUET_COLUMNS`] with SST metadata loaded.
+const PARQUET_META_COLUMNS: &str = "
+        OPTIONAL INT64 table_id (INTEGER(64, false));
+        OPTIONAL INT32 level (INTEGER(32, false));
+        OPTIONAL INT64 file_size (INTEGER(64, false));
+        OPTIONAL INT64 block_count (INTEGER(64, false));
+";
+
+/// Columns after those with provenance.
+const PARQUET_PROVENANCE_COLUMNS: &str = "
+        OPTIONAL BINARY file (STRING);
+        OPTIONAL INT64 line (INTEGER(64, false));
+";
+
+/// Writes events as [`EventRecord`] rows of a Snappy compressed Parquet file.
+pub struct ParquetWriter<'a, W: Write + Send> {
+    output: Option<W>,
+    /// Created for the first row group.
+    writer: Option<SerializedFileWriter<W>>,
+    rows: Vec<EventRecord>,
+    /// Decides the columns, which every row group has alike.
+    context: &'a Context,
+}
+
+impl<'a, W: Write + Send> ParquetWriter<'a, W> {
+    pub fn new(writer: W, context: &'a Context) -> parquet::errors::Result<Self> {
+        Ok(Self {
+            output: Some(writer),
+            writer: None,
+            rows: Vec::with_capacity(ROW_GROUP_SIZE),
+            context,
+        })
+    }
+
+    fn file_writer(&mut self) -> parquet::errors::Result<&mut SerializedFileWriter<W>> {
+        if let Some(output) = self.output.take() {
+            let context = self.context;
+            let source = if context.has_sources() {
+                PARQUET_SOURCE_COLUMN
+            } else {
+                ""
+            };
+            let kind = if context.has_kinds() {
+                PARQUET_KIND_COLUMN
+            } else {
+                ""
+            };
+            let meta = if context.has_sst_meta() {
+                PARQUET_META_COLUMNS
+            } else {
+                ""
+            };
+            let provenance = if context.has_provenance() {
+                PARQUET_PROVENANCE_COLUMNS
+            } else {
+                ""
+            };
+            let schema =
+                format!("message event {{{source}{kind}{PARQUET_COLUMNS}{meta}{provenance}}}");
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let schema = Arc::new(parse_message_type(&schema)?);
+            self.writer = Some(SerializedFileWriter::new(
+                output,
+                schema,
+                Arc::new(properties),
+            )?);
+        }
+        Ok(self.writer.as_mut().unwrap())
+    }
+
+    pub fn write(&mut self, event: &Event) -> parquet::errors::Result<()> {
+        self.rows.push(EventRecord::new(event, self.context));
+        if self.rows.len() == ROW_GROUP_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows as a row group.
+    fn flush(&mut self) -> parquet::errors::Result<()> {
+        let int = |value: fn(&EventRecord) -> u64| -> Vec<i64> {
+            self.rows.iter().map(|row| value(row) as i64).collect()
+        };
+        let ssts = int(|row| row.sst_id);
+        let blocks = int(|row| row.block_idx);
+        let times = int(|row| row.ts_nanos);
+        let ops: Vec<ByteArray> = (self.rows.iter()).map(|row| row.op.name().into()).collect();
+        let sources: Vec<ByteArray> = (self.rows.iter())
+            .map(|row| {
+                (row.source.as_ref().and_then(Option::as_deref))
+                    .unwrap_or_default()
+                    .into()
+            })
+            .collect();
+
+        self.file_writer()?;
+        let context = self.context;
+        let writer = self.writer.as_mut().unwrap();
+        let mut group = writer.next_row_group()?;
+        if context.has_sources() {
+            let mut column = group.next_column()?.unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&sources, None, None)?;
+            column.close()?;
+        }
+        if context.has_kinds() {
+            let kinds: Vec<ByteArray> = (self.rows.iter())
+                .map(|row| row.kind.as_deref().unwrap_or_default().into())
+                .collect();
+            let mut column = group.next_column()?.unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&kinds, None, None)?;
+            column.close()?;
+        }
+        for values in [&ssts, &blocks, &times] {
+            let mut column = group.next_column()?.unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(values, None, None)?;
+            column.close()?;
+        }
+        let mut column = group.next_column()?.unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&ops, None, None)?;
+        column.close()?;
+        if context.has_sst_meta() {
+            // Values of an optional column, and whether each row has one.
+            let rows = &self.rows;
+            let optional = |value: fn(&EventRecord) -> Option<u64>| -> (Vec<i64>, Vec<i16>) {
+                let values = rows.iter().filter_map(|row| Some(value(row)? as i64));
+                (
+                    values.collect(),
+                    rows.iter().map(|row| value(row).is_some() as i16).collect(),
+                )
+            };
+            let (table_ids, defined) = optional(|row| row.table_id.flatten());
+            let mut column = group.next_column()?.unwrap();
+            (column.typed::<Int64Type>()).write_batch(&table_ids, Some(&defined), None)?;
+            column.close()?;
+            let (levels, defined) = optional(|row| row.level.flatten().map(u64::from));
+            let levels: Vec<i32> = levels.into_iter().map(|level| level as i32).collect();
+            let mut column = group.next_column()?.unwrap();
+            (column.typed::<Int32Type>()).write_batch(&levels, Some(&defined), None)?;
+            column.close()?;
+            for value in [
+                (|row| row.file_size.flatten()) as fn(&EventRecord) -> Option<u64>,
+                |row| row.block_count.flatten(),
+            ] {
+                let (values, defined) = optional(value);
+                let mut column = group.next_column()?.unwrap();
+                (column.typed::<Int64Type>()).write_batch(&values, Some(&defined), None)?;
+                column.close()?;
+            }
+        }
+        if context.has_provenance() {
+            let rows = &self.rows;
+            let files: Vec<ByteArray> = (rows.iter())
+                .filter_map(|row| Some(row.file.as_ref()?.as_deref()?.into()))
+                .collect();
+            let lines: Vec<i64> = (rows.iter().filter_map(|row| row.line.flatten()))
+                .map(|line| line as i64)
+                .collect();
+            let defined: Vec<i16> = (rows.iter())
+                .map(|row| row.file.as_ref().is_some_and(Option::is_some) as i16)
+                .collect();
+            let mut column = group.next_column()?.unwrap();
+            (column.typed::<ByteArrayType>()).write_batch(&files, Some(&defined), None)?;
+            column.close()?;
+            let mut column = group.next_column()?.unwrap();
+            (column.typed::<Int64Type>()).write_batch(&lines, Some(&defined), None)?;
+            column.close()?;
+        }
+        group.close()?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the file footer.
+    pub fn finish(mut self) -> parquet::errors::Result<W> {
+        if !self.rows.is_empty() {
+            self.flush()?;
+        }
+        self.file_writer()?;
+        self.writer.take().unwrap().into_inner()
+    }
+}
@@ -0,0 +1,205 @@
+//! Synthetic traces of a cache serving a skewed workload, to check the
+//! analyses against known inputs and to benchmark on.
+
+use crate::cache::Cache;
+use crate::model::{Data, Event, Op, Origin};
+use crate::parse::{Schema, TimeField};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What the generated accesses look like.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    /// Distinct blocks accessed.
+    pub blocks: u64,
+    /// Blocks of each SST, numbered from 0.
+    pub blocks_per_sst: u64,
+    /// Zipf exponent of block popularity, 0 for uniform.
+    pub zipf: f64,
+    /// Mean accesses per second, arriving as a Poisson process.
+    pub rate: f64,
+    /// Accesses generated in total.
+    pub accesses: u64,
+    /// Time of the first access.
+    pub start: SystemTime,
+    /// Also emit hits and insertions, not only misses and evictions.
+    pub all_ops: bool,
+    /// Seed of the random choices. The same seed and workload always give
+    /// the same trace.
+    pub seed: u64,
+}
+
+/// The events of a [`Workload`] served by a [`Cache`], oldest first. A miss
+/// comes before the evictions making room for its block, and those before
+/// its insertion, all at the time of the access.
+pub struct Generator {
+    workload: Workload,
+    cache: Box<dyn Cache>,
+    rng: SplitMix64,
+    /// Cumulative popularity of the ranks, the last one being the total.
+    cdf: Vec<f64>,
+    /// The block of each popularity rank, shuffled so that hot blocks are
+    /// spread over the SSTs.
+    ranked: Vec<u64>,
+    time: SystemTime,
+    generated: u64,
+    evicted: Vec<Data>,
+    pending: VecDeque<Event>,
+}
+
+impl Generator {
+    pub fn new(workload: Workload, cache: Box<dyn Cache>) -> Self {
+        let mut rng = SplitMix64(workload.seed);
+        let mut total = 0.0;
+        let cdf = (1..=workload.blocks)
+            .map(|rank| {
+                total += (rank as f64).powf(-workload.zipf);
+                total
+            })
+            .collect();
+        let mut ranked: Vec<u64> = (0..workload.blocks).collect();
+        for i in (1..ranked.len()).rev() {
+            ranked.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        Self {
+            time: workload.start,
+            workload,
+            cache,
+            rng,
+            cdf,
+            ranked,
+            generated: 0,
+            evicted: vec![],
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Serves one more access, queueing its events.
+    fn access(&mut self) {
+        let gap = -(1.0 - self.rng.unit()).ln() / self.workload.rate;
+        self.time += Duration::from_secs_f64(gap);
+        let target = self.rng.unit() * self.cdf.last().copied().unwrap_or_default();
+        let rank = (self.cdf.partition_point(|&sum| sum <= target)).min(self.cdf.len() - 1);
+        let block = self.ranked[rank];
+        let data = Data::new(
+            block / self.workload.blocks_per_sst,
+            block % self.workload.blocks_per_sst,
+        );
+
+        let time = self.time;
+        let event = |op| Event {
+            data,
+            time,
+            op,
+            origin: Origin::default(),
+        };
+        if self.cache.access(data, &mut self.evicted) {
+            if self.workload.all_ops {
+                self.pending.push_back(event(Op::Hit));
+            }
+            return;
+        }
+        self.pending.push_back(event(Op::Missed));
+        for evicted in self.evicted.drain(..) {
+            self.pending.push_back(Event {
+                data: evicted,
+                time,
+                op: Op::Evicted,
+                origin: Origin::default(),
+            });
+        }
+        if self.workload.all_ops {
+            self.pending.push_back(event(Op::Inserted));
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        while self.pending.is_empty() && self.generated < self.workload.accesses {
+            if self.workload.blocks == 0 {
+                return None;
+            }
+            self.generated += 1;
+            self.access();
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// A small, fast generator whose output never changes across releases,
+/// unlike those of most crates.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, n)`, near enough for shuffling.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Writes `events` as a CSV export in the built-in layout, with consecutive
+/// events of one op in rows of at most `batch` records under its banner.
+/// Returns the number of rows written.
+pub fn write_csv(
+    events: impl Iterator<Item = Event>,
+    batch: usize,
+    writer: impl Write,
+) -> io::Result<usize> {
+    let banners = Schema::builtin(TimeField::Systime).banners;
+    let banner = |op| {
+        &banners
+            .iter()
+            .find(|banner| banner.op == op)
+            .unwrap()
+            .marker
+    };
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["timestamp", "message"])?;
+    let mut rows = 0;
+    let mut row: Vec<Event> = Vec::with_capacity(batch);
+    let mut events = events.peekable();
+    while let Some(event) = events.next() {
+        row.push(event);
+        let full = row.len() >= batch.max(1);
+        if full || events.peek().is_none_or(|next| next.op != event.op) {
+            let entries: Vec<String> = (row.iter())
+                .map(|event| {
+                    let since = event.time.duration_since(UNIX_EPOCH).unwrap();
+                    format!(
+                        "(SstableBlockIndex {{ sst_id: {}, block_idx: {} }}, SystemTime {{ tv_sec: {}, tv_nsec: {} }})",
+                        event.data.sst,
+                        event.data.blk,
+                        since.as_secs(),
+                        since.subsec_nanos()
+                    )
+                })
+                .collect();
+            let timestamp = chrono::DateTime::<chrono::Utc>::from(row[0].time)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string();
+            let message = format!("{}\n[{}]", banner(row[0].op), entries.join(", "));
+            writer.write_record([timestamp, message])?;
+            rows += 1;
+            row.clear();
+        }
+    }
+    writer.flush()?;
+    Ok(rows)
+}
@@ -0,0 +1,24 @@
+//! Parsing and analysis of block cache eviction/miss traces.
+//!
+//! [`parse`] turns trace CSVs into [`model::Event`]s and [`analysis`] holds the
+//! reports the `blocks` CLI builds from them.
+
+pub mod analysis;
+pub mod anonymize;
+pub mod async_io;
+pub mod background;
+pub mod bench;
+pub mod binary;
+pub mod cache;
+pub mod dedup;
+pub mod export;
+pub mod format;
+pub mod generate;
+pub mod model;
+pub mod parse;
+pub mod query;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod spill;
+pub mod sst_meta;
+pub mod template;
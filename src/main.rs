@@ -1,188 +1,118 @@
-use chrono::{DateTime, Local, TimeZone};
-use clap::Parser;
-use csv::ReaderBuilder;
-use regex::Regex;
-use std::collections::HashMap;
-use std::fs;
-use std::io::{BufReader, BufWriter, Write};
-use std::time::SystemTime;
-use std::time::{Duration, UNIX_EPOCH};
+mod commands;
+mod config;
+
+use blocks::model::{Context, TimeStyle, Zone};
+use blocks::sst_meta::{SstMetas, load_sst_meta};
+use chrono::format::{Item, StrftimeItems};
+use clap::{Parser, Subcommand};
+use commands::{
+    analyze, anonymize, bench, check, convert, diff, generate, merge, mrc, parse, query, report,
+    serve, simulate, stats, timeline, timeseries, tui, working_set,
+};
+use std::process::ExitCode;
 
 #[derive(Debug, Parser)]
-struct Args {
-    dir: String,
-    #[clap(short, long, default_value = "out.txt")]
-    out: String,
-    #[clap(short, long, default_value = "duration.txt")]
-    duration: String,
+#[command(args_override_self = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// TOML or JSON file of options, overridden by the ones given here. Its
+    /// top-level keys apply to every subcommand, and those in a table named
+    /// after a subcommand only to that one.
+    #[clap(long, global = true)]
+    config: Option<String>,
+    /// Time zone of the printed timestamps: `local`, `utc` or a name such as
+    /// `Europe/Berlin`. Earlier output is read back in this zone too.
+    #[clap(long, global = true, default_value = "local")]
+    timezone: Zone,
+    /// strftime layout of the printed timestamps instead of
+    /// `%Y-%m-%d %H:%M:%S%.f`. Text output written with it can't be read back.
+    #[clap(long, global = true, value_parser = parse_time_format)]
+    time_format: Option<String>,
+    /// Print timestamps as nanoseconds since the Unix epoch.
+    #[clap(long, global = true, conflicts_with_all = ["timezone", "time_format"])]
+    epoch_nanos: bool,
+    /// Print timestamps as offsets from the oldest event parsed, such as
+    /// `+00:03:12.450`, to compare runs of the same workload. Text output
+    /// written with it can't be read back.
+    #[clap(long, global = true, conflicts_with_all = ["timezone", "time_format", "epoch_nanos"])]
+    relative_time: bool,
+    /// CSV or JSON file of the table, LSM level, file size and block count
+    /// of each SST by `sst_id`, added to the structured outputs and
+    /// available to `analyze --group-by`.
+    #[clap(long, global = true, value_parser = load_sst_meta)]
+    sst_meta: Option<SstMetas>,
+    /// Record the input file and line every event was parsed from, and add
+    /// them to the event outputs: as `file:line` after each text line, and
+    /// as `file` and `line` fields and template columns.
+    #[clap(long, global = true)]
+    with_provenance: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Data {
-    sst: u64,
-    blk: u64,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Op {
-    Evicted,
-    Missed,
-}
-
-fn parse(s: &str) -> Vec<(Data, SystemTime, Op)> {
-    let op = if s.contains("========== EVICTED DATA BLOCKS ==========") {
-        Op::Evicted
-    } else if s.contains("========== MISSED DATA BLOCKS ==========") {
-        Op::Missed
-    } else {
-        return vec![];
-    };
-
-    // 定义正则表达式，匹配 sst_id 和 block_idx 以及时间戳
-    let re = Regex::new(r"SstableBlockIndex \{ sst_id: (\d+), block_idx: (\d+) \}, SystemTime \{ tv_sec: (\d+), tv_nsec: (\d+) \}").unwrap();
-
-    let mut res = vec![];
-
-    for cap in re.captures_iter(s) {
-        let sst = cap[1].parse::<u64>().unwrap();
-        let blk = cap[2].parse::<u64>().unwrap();
-        let tv_sec = cap[3].parse::<u64>().unwrap();
-        let tv_nsec = cap[4].parse::<u32>().unwrap();
-
-        let data = Data { sst, blk };
-        let system_time = UNIX_EPOCH + Duration::new(tv_sec, tv_nsec);
-
-        res.push((data, system_time, op));
+fn parse_time_format(s: &str) -> Result<String, String> {
+    if StrftimeItems::new(s).any(|item| item == Item::Error) {
+        return Err(format!("invalid strftime layout {s}"));
     }
-
-    res
+    Ok(s.to_string())
 }
 
-fn main() {
-    let args = Args::parse();
-
-    let mut records = vec![];
-    let mut evicted_times: HashMap<(u64, u64), SystemTime> = HashMap::new();
-
-    for entry in fs::read_dir(&args.dir).unwrap() {
-        let entry = entry.unwrap();
-        let file_path = entry.path();
-
-        if file_path.extension().and_then(|ext| ext.to_str()) == Some("csv") {
-            let file = fs::File::open(&file_path).unwrap();
-            let buffered_reader = BufReader::new(file);
-            let mut reader = ReaderBuilder::new()
-                .has_headers(true)
-                .from_reader(buffered_reader);
+#[derive(Debug, Subcommand)]
+enum Command {
+    Parse(parse::ParseArgs),
+    Analyze(Box<analyze::AnalyzeArgs>),
+    Anonymize(anonymize::AnonymizeArgs),
+    Check(check::CheckArgs),
+    Query(query::QueryArgs),
+    Stats(stats::StatsArgs),
+    Merge(merge::MergeArgs),
+    Timeseries(timeseries::TimeseriesArgs),
+    Timeline(timeline::TimelineArgs),
+    Simulate(simulate::SimulateArgs),
+    Mrc(mrc::MrcArgs),
+    Convert(convert::ConvertArgs),
+    Diff(diff::DiffArgs),
+    Generate(generate::GenerateArgs),
+    WorkingSet(working_set::WorkingSetArgs),
+    Report(report::ReportArgs),
+    Tui(tui::TuiArgs),
+    Serve(serve::ServeArgs),
+    Bench(bench::BenchArgs),
+}
 
-            for result in reader.records() {
-                let record = result.unwrap();
-                let rs = parse(record.as_slice());
-                records.extend(rs);
-                let row = records.len();
-                if records.len() % 10000 == 0 {
-                    println!("Processed {row} records");
-                }
-            }
-        }
+fn main() -> anyhow::Result<ExitCode> {
+    let args = config::expand(std::env::args_os().collect())?;
+    let cli = Cli::parse_from(args);
+    let mut context = Context::new(TimeStyle {
+        zone: cli.timezone,
+        format: cli.time_format,
+        epoch_nanos: cli.epoch_nanos,
+        relative: cli.relative_time,
+    });
+    if let Some(metas) = cli.sst_meta {
+        context = context.with_sst_meta(metas);
     }
-
-    println!("Sorting...");
-    records.sort_by_key(|(_, time, _)| std::cmp::Reverse(*time));
-
-    let output_file = fs::File::create(&args.out).unwrap();
-    let mut writer = BufWriter::with_capacity(64 * 1024, output_file); // Use a larger buffer size for better performance
-
-    for (row, record) in records.iter().enumerate() {
-        let (data, system_time, op) = record;
-        let datetime: DateTime<Local> = Local
-            .timestamp_opt(
-                system_time.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
-                system_time
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .subsec_nanos(),
-            )
-            .unwrap();
-        writeln!(
-            writer,
-            "{:?}, {}, {:?}",
-            data,
-            datetime.format("%Y-%m-%d %H:%M:%S%.f"),
-            op
-        )
-        .unwrap();
-        if row % 10000 == 0 {
-            println!("Written {row} records");
-        }
-
-        // Store evicted times
-        if *op == Op::Evicted {
-            evicted_times.insert((data.sst, data.blk), *system_time);
-        }
+    if cli.with_provenance {
+        context = context.with_provenance();
     }
-
-    // Calculate durations between evicted and missed events
-    let duration_file = fs::File::create(&args.duration).unwrap();
-    let mut duration_writer = BufWriter::new(duration_file);
-
-    let mut long = 0;
-    let mut short = 0;
-    let mut none = 0;
-
-    for (data, system_time, op) in &records {
-        if *op == Op::Missed {
-            let datetime: DateTime<Local> = Local
-                .timestamp_opt(
-                    system_time.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64,
-                    system_time
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .subsec_nanos(),
-                )
-                .unwrap();
-            let miss = datetime.format("%Y-%m-%d %H:%M:%S%.f");
-            if let Some(&evicted_time) = evicted_times.get(&(data.sst, data.blk)) {
-                if evicted_time > *system_time {
-                    let duration = evicted_time.duration_since(*system_time).unwrap();
-                    writeln!(
-                        duration_writer,
-                        "{data:?}, delta: -{duration:?}, miss time: {miss}"
-                    )
-                    .unwrap();
-                } else {
-                    let duration = system_time.duration_since(evicted_time).unwrap();
-
-                    let suffix = if duration.as_secs_f64() < 10.0 {
-                        short += 1;
-                        "!!!!!!!!!!"
-                    } else {
-                        long += 1;
-                        ""
-                    };
-                    writeln!(
-                        duration_writer,
-                        "{data:?}, delta: {duration:?}, miss time: {miss} {suffix}"
-                    )
-                    .unwrap();
-                }
-            } else {
-                none += 1;
-                writeln!(
-                    duration_writer,
-                    "{:?}, miss time: {miss}, No evicted time found",
-                    data
-                )
-                .unwrap();
-            }
-        }
+    match cli.command {
+        Command::Parse(args) => parse::run(args, &context),
+        Command::Analyze(args) => analyze::run(*args, &context),
+        Command::Anonymize(args) => anonymize::run(args, &context),
+        Command::Check(args) => check::run(args, &context),
+        Command::Query(args) => query::run(args, &context),
+        Command::Stats(args) => stats::run(args, &context),
+        Command::Merge(args) => merge::run(args, &context),
+        Command::Timeseries(args) => timeseries::run(args, &context),
+        Command::Timeline(args) => timeline::run(args, &context),
+        Command::Simulate(args) => simulate::run(args, &context),
+        Command::Mrc(args) => mrc::run(args, &context),
+        Command::Convert(args) => convert::run(args, &context),
+        Command::Diff(args) => diff::run(args, &context),
+        Command::Generate(args) => generate::run(args),
+        Command::WorkingSet(args) => working_set::run(args, &context),
+        Command::Report(args) => report::run(args, &context),
+        Command::Tui(args) => tui::run(args, &context),
+        Command::Serve(args) => serve::run(args, &context),
+        Command::Bench(args) => bench::run(args),
     }
-
-    writeln!(
-        duration_writer,
-        "long: {long}, short: {short}, none: {none}"
-    )
-    .unwrap();
-
-    println!("Done. Total records: {}", records.len());
 }
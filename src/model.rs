@@ -0,0 +1,491 @@
+//! Types shared by the parser and the analyses.
+
+use crate::format::ts_nanos;
+use crate::sst_meta::{SstMeta, SstMetas};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Identifies a cached block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Data {
+    pub sst: u64,
+    pub blk: u64,
+    /// The shard or node the block is cached by, see
+    /// [`Context::source_id`], or 0 if sources aren't told apart.
+    pub source: u32,
+    /// The kind of key logged for it, such as the meta blocks keyed by
+    /// `SstableMetaIndex`, see [`Context::kind_id`], or 0 for data blocks.
+    /// Keys without a block index have `blk` 0.
+    pub kind: u32,
+}
+
+impl Data {
+    /// A block of no particular source.
+    pub fn new(sst: u64, blk: u64) -> Self {
+        Self {
+            sst,
+            blk,
+            source: 0,
+            kind: 0,
+        }
+    }
+
+    /// Whether the block is among a `rate` share of all blocks, picked by
+    /// hashing so that every run and every event of it agrees.
+    pub fn sampled(&self, rate: f64, context: &Context) -> bool {
+        const MODULUS: u64 = 1 << 24;
+        let threshold = (rate.clamp(0.0, 1.0) * MODULUS as f64) as u64;
+        let mut hasher = DefaultHasher::new();
+        (self.sst, self.blk).hash(&mut hasher);
+        if self.source != 0 {
+            context.source_name(self.source).hash(&mut hasher);
+        }
+        if self.kind != 0 {
+            context.kind_name(self.kind).hash(&mut hasher);
+        }
+        hasher.finish() % MODULUS < threshold
+    }
+}
+
+/// What the data blocks of kind 0 are called, such as in `--kind`.
+pub const DATA_KIND: &str = "data";
+
+/// Names registered for ids, the first one with id 1.
+#[derive(Debug, Default)]
+struct Names(RwLock<Vec<Arc<str>>>);
+
+impl Names {
+    fn id(&self, name: &str) -> u32 {
+        let position = |names: &[Arc<str>]| names.iter().position(|known| **known == *name);
+        if let Some(i) = position(&self.0.read().unwrap()) {
+            return i as u32 + 1;
+        }
+        let mut names = self.0.write().unwrap();
+        if let Some(i) = position(&names) {
+            return i as u32 + 1;
+        }
+        names.push(name.into());
+        names.len() as u32
+    }
+
+    fn name(&self, id: u32) -> Option<Arc<str>> {
+        let index = id.checked_sub(1)?;
+        self.0.read().unwrap().get(index as usize).cloned()
+    }
+}
+
+/// Names of the input files events came from, the first one with id 1, and
+/// their ids, as there may be very many.
+#[derive(Debug, Default)]
+struct Files {
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, u32>,
+}
+
+/// What the events of a trace are read and shown with: the names their
+/// source, kind and file ids stand for, how timestamps look, whether the
+/// origin of each event is kept, and the SST metadata. Parsing registers
+/// names in it and the outputs look them up, so events are only shown with
+/// the one they were parsed with, and its names go away with it.
+#[derive(Debug)]
+pub struct Context {
+    sources: Names,
+    kinds: Names,
+    /// [`DATA_KIND`], shared by the events of kind 0.
+    data_kind: Arc<str>,
+    files: RwLock<Files>,
+    time_style: TimeStyle,
+    /// Nanoseconds since the Unix epoch of the oldest event parsed, which
+    /// relative timestamps count from.
+    anchor: AtomicU64,
+    provenance: bool,
+    sst_meta: Option<SstMetas>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new(TimeStyle::default())
+    }
+}
+
+impl Context {
+    /// Shows timestamps in `time_style`, without provenance or SST metadata.
+    pub fn new(time_style: TimeStyle) -> Self {
+        Self {
+            sources: Names::default(),
+            kinds: Names::default(),
+            data_kind: DATA_KIND.into(),
+            files: RwLock::default(),
+            time_style,
+            anchor: AtomicU64::new(u64::MAX),
+            provenance: false,
+            sst_meta: None,
+        }
+    }
+
+    /// Records the [`Origin`] of every event parsed, and adds it to the
+    /// outputs.
+    pub fn with_provenance(self) -> Self {
+        Self {
+            provenance: true,
+            ..self
+        }
+    }
+
+    /// Joins `metas` to the events by SST id, see [`Context::sst_meta`].
+    pub fn with_sst_meta(self, metas: SstMetas) -> Self {
+        Self {
+            sst_meta: Some(metas),
+            ..self
+        }
+    }
+
+    /// The [`Data::source`] id of the source called `name`, registering it
+    /// if it's new. Ids are handed out in registration order, so inputs
+    /// register their sources before they're parsed in parallel.
+    pub fn source_id(&self, name: &str) -> u32 {
+        self.sources.id(name)
+    }
+
+    /// The name of the source with id `source`, if it's not 0.
+    pub fn source_name(&self, source: u32) -> Option<Arc<str>> {
+        self.sources.name(source)
+    }
+
+    /// Whether any source is known, by --source-regex or the inputs.
+    /// Without, the structured outputs leave out the source.
+    pub fn has_sources(&self) -> bool {
+        self.sources.name(1).is_some()
+    }
+
+    /// The [`Data::kind`] id of the key kind called `name`, registering it
+    /// if it's new, like [`Context::source_id`]. [`DATA_KIND`] is 0.
+    pub fn kind_id(&self, name: &str) -> u32 {
+        if name == DATA_KIND {
+            return 0;
+        }
+        self.kinds.id(name)
+    }
+
+    /// The name of the key kind with id `kind`, if it's not 0.
+    pub fn kind_name(&self, kind: u32) -> Option<Arc<str>> {
+        self.kinds.name(kind)
+    }
+
+    /// The name of the key kind with id `kind`, [`DATA_KIND`] for 0.
+    pub fn kind_label(&self, kind: u32) -> Arc<str> {
+        self.kind_name(kind)
+            .unwrap_or_else(|| self.data_kind.clone())
+    }
+
+    /// Whether any kind of key besides data blocks is known, by the schema
+    /// or `--kind`. Without, the structured outputs leave out the kind.
+    pub fn has_kinds(&self) -> bool {
+        self.kinds.name(1).is_some()
+    }
+
+    /// `data` as the text layout shows it: like the derived `Debug` layout,
+    /// with the source and kind only if there are any, so that text output
+    /// without them looks the same as before there were any.
+    pub fn data(&self, data: Data) -> impl Display + '_ {
+        Named {
+            data,
+            context: self,
+        }
+    }
+
+    /// The [`Origin::file`] id of the input file called `name`, registering
+    /// it if it's new.
+    pub fn origin_file_id(&self, name: &str) -> u32 {
+        if let Some(&id) = self.files.read().unwrap().ids.get(name) {
+            return id;
+        }
+        let mut files = self.files.write().unwrap();
+        if let Some(&id) = files.ids.get(name) {
+            return id;
+        }
+        let name: Arc<str> = name.into();
+        files.names.push(name.clone());
+        let id = files.names.len() as u32;
+        files.ids.insert(name, id);
+        id
+    }
+
+    /// The name of the input file `origin` is in, if it's known.
+    pub fn file_name(&self, origin: Origin) -> Option<Arc<str>> {
+        let index = origin.file.checked_sub(1)?;
+        (self.files.read().unwrap().names)
+            .get(index as usize)
+            .cloned()
+    }
+
+    /// `origin` as `file:line`, or `-` if the file is unknown.
+    pub fn origin(&self, origin: Origin) -> impl Display + '_ {
+        Located {
+            origin,
+            context: self,
+        }
+    }
+
+    /// Whether the [`Origin`] of every event is recorded, see
+    /// [`Context::with_provenance`].
+    pub fn has_provenance(&self) -> bool {
+        self.provenance
+    }
+
+    /// The metadata of SST `sst`, if any was loaded for it.
+    pub fn sst_meta(&self, sst: u64) -> Option<&SstMeta> {
+        self.sst_meta.as_ref()?.get(&sst)
+    }
+
+    /// Whether any SST metadata was loaded. Without it the structured
+    /// outputs leave out its columns.
+    pub fn has_sst_meta(&self) -> bool {
+        self.sst_meta.is_some()
+    }
+}
+
+/// See [`Context::data`].
+struct Named<'a> {
+    data: Data,
+    context: &'a Context,
+}
+
+impl Display for Named<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Data");
+        debug
+            .field("sst", &self.data.sst)
+            .field("blk", &self.data.blk);
+        if let Some(name) = self.context.source_name(self.data.source) {
+            debug.field("source", &format_args!("{name}"));
+        }
+        if let Some(name) = self.context.kind_name(self.data.kind) {
+            debug.field("kind", &format_args!("{name}"));
+        }
+        debug.finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Evicted,
+    Missed,
+    /// The block was put into the cache.
+    Inserted,
+    /// The block was found in the cache.
+    Hit,
+}
+
+impl Op {
+    pub const ALL: [Op; 4] = [Op::Evicted, Op::Missed, Op::Inserted, Op::Hit];
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Op::Evicted => "=== EVICTED ===",
+            Op::Missed => "=== MISSED ===",
+            Op::Inserted => "=== INSERTED ===",
+            Op::Hit => "=== HIT ===",
+        }
+    }
+
+    /// The lowercase name used by the structured formats.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Evicted => "evicted",
+            Op::Missed => "missed",
+            Op::Inserted => "inserted",
+            Op::Hit => "hit",
+        }
+    }
+
+    /// Whether the op is an access of the block by a reader: a miss, or a
+    /// hit for traces that log those.
+    pub fn is_access(&self) -> bool {
+        matches!(self, Op::Missed | Op::Hit)
+    }
+}
+
+/// A parsed block event.
+#[derive(Debug, Clone, Copy)]
+pub struct Event {
+    pub data: Data,
+    pub time: SystemTime,
+    pub op: Op,
+    /// Only recorded with [`Context::with_provenance`].
+    pub origin: Origin,
+}
+
+/// Events are the same wherever they were read from.
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        (self.data, self.time, self.op) == (other.data, other.time, other.op)
+    }
+}
+
+/// Where an event was read from: its input file, see
+/// [`Context::origin_file_id`], and the line of its record there. File 0 is
+/// unknown, as is line 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Origin {
+    pub file: u32,
+    pub line: u32,
+}
+
+/// See [`Context::origin`].
+struct Located<'a> {
+    origin: Origin,
+    context: &'a Context,
+}
+
+impl Display for Located<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.context.file_name(self.origin) {
+            Some(name) => write!(f, "{name}:{}", self.origin.line),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// Layout used for every human readable timestamp.
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Time zone timestamps are shown in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// `utc`, `local` or an IANA time zone name such as `Europe/Berlin`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Zone::Local),
+            "utc" | "UTC" => Ok(Zone::Utc),
+            name => name
+                .parse()
+                .map(Zone::Named)
+                .map_err(|_| format!("unknown time zone {name}")),
+        }
+    }
+}
+
+/// How [`Context::format_time`] shows timestamps.
+#[derive(Debug, Clone, Default)]
+pub struct TimeStyle {
+    pub zone: Zone,
+    /// strftime layout instead of [`TIME_FORMAT`].
+    pub format: Option<String>,
+    /// Nanoseconds since the Unix epoch instead of a date and time.
+    pub epoch_nanos: bool,
+    /// Offsets from the oldest event parsed, see [`Context::anchor_time`],
+    /// as `+HH:MM:SS.mmm` instead of a date and time.
+    pub relative: bool,
+}
+
+impl Context {
+    /// Moves the start relative timestamps count from, see
+    /// [`TimeStyle::relative`], back to the oldest of `events` if it's older.
+    pub fn anchor_time(&self, events: &[Event]) {
+        if !self.time_style.relative {
+            return;
+        }
+        if let Some(oldest) = events.iter().map(|event| ts_nanos(event.time)).min() {
+            self.anchor.fetch_min(oldest, Ordering::Relaxed);
+        }
+    }
+
+    /// Formats `system_time` in the [`TimeStyle`] of the context, in local
+    /// time with [`TIME_FORMAT`] by default.
+    pub fn format_time(&self, system_time: SystemTime) -> impl Display + '_ {
+        FormattedTime {
+            time: system_time,
+            context: self,
+        }
+    }
+
+    /// Parses a timestamp written by [`Context::format_time`], or
+    /// nanoseconds since the Unix epoch.
+    pub fn parse_formatted_time(&self, s: &str) -> Result<SystemTime, String> {
+        if let Ok(nanos) = s.parse() {
+            return Ok(UNIX_EPOCH + Duration::from_nanos(nanos));
+        }
+        let style = &self.time_style;
+        let layout = style.format.as_deref().unwrap_or(TIME_FORMAT);
+        let naive = NaiveDateTime::parse_from_str(s, layout).map_err(|e| e.to_string())?;
+        match style.zone {
+            Zone::Local => from_naive(&Local, &naive),
+            Zone::Utc => from_naive(&Utc, &naive),
+            Zone::Named(tz) => from_naive(&tz, &naive),
+        }
+    }
+}
+
+/// See [`Context::format_time`].
+struct FormattedTime<'a> {
+    time: SystemTime,
+    context: &'a Context,
+}
+
+impl Display for FormattedTime<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = &self.context.time_style;
+        if style.epoch_nanos {
+            return write!(f, "{}", ts_nanos(self.time));
+        }
+        if style.relative {
+            let nanos = ts_nanos(self.time);
+            // Times before the anchor, such as any before an event was
+            // parsed, show as the start.
+            let anchor = (self.context.anchor.load(Ordering::Relaxed)).min(nanos);
+            let millis = (nanos - anchor) / 1_000_000;
+            return write!(
+                f,
+                "+{:02}:{:02}:{:02}.{:03}",
+                millis / 3_600_000,
+                millis / 60_000 % 60,
+                millis / 1000 % 60,
+                millis % 1000
+            );
+        }
+        let layout = style.format.as_deref().unwrap_or(TIME_FORMAT);
+        let time = DateTime::<Utc>::from(self.time);
+        match style.zone {
+            Zone::Local => write!(f, "{}", time.with_timezone(&Local).format(layout)),
+            Zone::Utc => write!(f, "{}", time.format(layout)),
+            Zone::Named(tz) => write!(f, "{}", time.with_timezone(&tz).format(layout)),
+        }
+    }
+}
+
+/// Parses an RFC3339 timestamp, or a local time in [`TIME_FORMAT`] with
+/// optional fractional seconds.
+pub fn parse_time(s: &str) -> Result<SystemTime, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.into());
+    }
+    let naive = NaiveDateTime::parse_from_str(s, TIME_FORMAT)
+        .map_err(|e| format!("expected RFC3339 or `YYYY-MM-DD HH:MM:SS`: {e}"))?;
+    from_naive(&Local, &naive)
+}
+
+fn from_naive<Z: TimeZone>(zone: &Z, naive: &NaiveDateTime) -> Result<SystemTime, String> {
+    zone.from_local_datetime(naive)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("{naive} doesn't exist in the time zone"))
+}
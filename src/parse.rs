@@ -0,0 +1,2187 @@
+//! Turning trace CSVs into [`Event`]s.
+
+use crate::async_io;
+use crate::binary::{BinaryReader, MAGIC};
+use crate::model::{Context, Data, Event, Op, Origin};
+use anyhow::Context as _;
+use chrono::DateTime;
+use crossbeam_channel::{Sender, bounded};
+use csv::{ByteRecord, ReaderBuilder};
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use flate2::read::MultiGzDecoder;
+use globset::GlobSet;
+use memchr::memmem::Finder;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// The entries of [`Schema::builtin`] with [`TimeField::Systime`].
+const SYSTIME_PATTERN: &str = r"SstableBlockIndex \{ sst_id: (\d+), block_idx: (\d+) \}, SystemTime \{ tv_sec: (\d+), tv_nsec: (\d+) \}";
+
+/// Flush coalesced input to the CSV reader once it grows past this size.
+const COALESCE_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Describes a log format: how to find records and which section they belong
+/// to. Loaded from `--schema-config`, or [`Schema::builtin`] for the
+/// `SstableBlockIndex` format.
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    pub fields: Fields,
+    #[serde(default)]
+    pub time_unit: TimeUnit,
+    pub banners: Vec<Banner>,
+    /// The [`Data::kind`] of the records of `pattern`, data blocks without.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Other kinds of keys logged in the same sections.
+    #[serde(default)]
+    pub keys: Vec<Key>,
+}
+
+/// Records of another kind of key than [`Schema::pattern`]'s, such as the
+/// `SstableMetaIndex { sst_id }` of meta blocks.
+#[derive(Debug, Deserialize)]
+pub struct Key {
+    /// The [`Data::kind`] of its records.
+    pub kind: String,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    pub fields: Fields,
+    /// The schema's if not given.
+    pub time_unit: Option<TimeUnit>,
+}
+
+/// Capture group indices of each field in [`Schema::pattern`].
+#[derive(Debug, Deserialize)]
+pub struct Fields {
+    pub sst: usize,
+    /// Keys without one have block index 0.
+    pub blk: Option<usize>,
+    pub time: usize,
+    /// Optional sub-second part, always in nanoseconds.
+    pub nanos: Option<usize>,
+}
+
+impl Fields {
+    /// The groups of `pattern` named `sst`, `time` and optionally `blk` and
+    /// `nanos`.
+    pub fn named(pattern: &Regex) -> Result<Self, String> {
+        let group = |name| {
+            pattern
+                .capture_names()
+                .position(|group| group == Some(name))
+        };
+        let required = |name| group(name).ok_or(format!("no group named `{name}`"));
+        Ok(Self {
+            sst: required("sst")?,
+            blk: group("blk"),
+            time: required("time")?,
+            nanos: group("nanos"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Banner {
+    pub marker: String,
+    pub op: Op,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeUnit {
+    #[default]
+    S,
+    Ms,
+    Us,
+    Ns,
+    /// An RFC3339 string such as `2024-01-15T10:00:00.123456789Z`.
+    Rfc3339,
+}
+
+impl TimeUnit {
+    fn parse(self, value: &str) -> Result<SystemTime, String> {
+        let int = || {
+            value
+                .parse::<u64>()
+                .map_err(|e| format!("timestamp {value}: {e}"))
+        };
+        let since_epoch = match self {
+            TimeUnit::S => Duration::from_secs(int()?),
+            TimeUnit::Ms => Duration::from_millis(int()?),
+            TimeUnit::Us => Duration::from_micros(int()?),
+            TimeUnit::Ns => Duration::from_nanos(int()?),
+            TimeUnit::Rfc3339 => {
+                let time: SystemTime = DateTime::parse_from_rfc3339(value)
+                    .map_err(|e| format!("timestamp {value}: {e}"))?
+                    .into();
+                // Times are kept as nanoseconds since the epoch in the outputs.
+                if time < UNIX_EPOCH {
+                    return Err(format!("timestamp {value} is before 1970"));
+                }
+                return Ok(time);
+            }
+        };
+        (UNIX_EPOCH.checked_add(since_epoch))
+            .ok_or_else(|| format!("timestamp {value} is out of range"))
+    }
+}
+
+/// Built-in timestamp layouts selectable with `--time-field`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum TimeField {
+    /// `SystemTime { tv_sec: .., tv_nsec: .. }`.
+    Systime,
+    /// `time: "<RFC3339>"`.
+    Rfc3339,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl Schema {
+    /// The `SstableBlockIndex` format with the given timestamp layout.
+    pub fn builtin(time_field: TimeField) -> Self {
+        // 定义正则表达式，匹配 sst_id 和 block_idx 以及时间戳
+        let (pattern, nanos, time_unit) = match time_field {
+            TimeField::Systime => (SYSTIME_PATTERN, Some(4), TimeUnit::S),
+            TimeField::Rfc3339 => (
+                r#"SstableBlockIndex \{ sst_id: (\d+), block_idx: (\d+) \}, time: "([^"]+)""#,
+                None,
+                TimeUnit::Rfc3339,
+            ),
+        };
+        Self {
+            pattern: Regex::new(pattern).unwrap(),
+            fields: Fields {
+                sst: 1,
+                blk: Some(2),
+                time: 3,
+                nanos,
+            },
+            time_unit,
+            kind: None,
+            keys: vec![],
+            banners: vec![
+                Banner {
+                    marker: "========== EVICTED DATA BLOCKS ==========".to_string(),
+                    op: Op::Evicted,
+                },
+                Banner {
+                    marker: "========== MISSED DATA BLOCKS ==========".to_string(),
+                    op: Op::Missed,
+                },
+                Banner {
+                    marker: "========== INSERTED DATA BLOCKS ==========".to_string(),
+                    op: Op::Inserted,
+                },
+                // Admission is what puts a block into the cache.
+                Banner {
+                    marker: "========== ADMITTED DATA BLOCKS ==========".to_string(),
+                    op: Op::Inserted,
+                },
+                Banner {
+                    marker: "========== HIT DATA BLOCKS ==========".to_string(),
+                    op: Op::Hit,
+                },
+            ],
+        }
+    }
+
+    /// Whether records are in the built-in `SystemTime` layout, which
+    /// [`entries`] scans for without the regex.
+    fn is_systime(&self) -> bool {
+        self.pattern.as_str() == SYSTIME_PATTERN
+            && matches!(
+                self.fields,
+                Fields {
+                    sst: 1,
+                    blk: Some(2),
+                    time: 3,
+                    nanos: Some(4)
+                }
+            )
+            && matches!(self.time_unit, TimeUnit::S)
+            && self.kind.is_none()
+    }
+
+    /// Loads a schema from a JSON file, or TOML for any other extension, and
+    /// registers its kinds of keys in `context`.
+    pub fn load(path: &Path, context: &Context) -> anyhow::Result<Self> {
+        let content = read_text(path).with_context(|| format!("can't read {}", path.display()))?;
+        let schema: Self = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::from_str(&content)
+                .with_context(|| format!("invalid schema {}", path.display()))?
+        } else {
+            toml::from_str(&content)
+                .with_context(|| format!("invalid schema {}", path.display()))?
+        };
+        // In schema order rather than whichever parsing thread finds one
+        // first, so every run numbers them the same.
+        for kind in schema
+            .kind
+            .iter()
+            .chain(schema.keys.iter().map(|key| &key.kind))
+        {
+            context.kind_id(kind);
+        }
+        Ok(schema)
+    }
+}
+
+/// Extracts the events of one log section, or why an entry is invalid. The
+/// kinds of their keys are registered in `context`.
+pub fn parse(s: &str, schema: &Schema, context: &Context) -> Result<Vec<Event>, String> {
+    let Some(op) = banner(s, schema) else {
+        return Ok(vec![]);
+    };
+    entries(s, schema, op, context).collect()
+}
+
+/// The op of the first section banner found in `s`.
+fn banner(s: &str, schema: &Schema) -> Option<Op> {
+    schema
+        .banners
+        .iter()
+        .find(|banner| s.contains(&banner.marker))
+        .map(|banner| banner.op)
+}
+
+/// The `op` events of every entry in `s`.
+fn entries<'a>(
+    s: &'a str,
+    schema: &'a Schema,
+    op: Op,
+    context: &'a Context,
+) -> impl Iterator<Item = Result<Event, String>> + 'a {
+    let fast = schema
+        .is_systime()
+        .then(|| systime_entries(s, op))
+        .flatten();
+    let slow = fast.is_none().then(|| {
+        let kind = schema.kind.as_deref();
+        let kind = kind.map_or(0, |kind| context.kind_id(kind));
+        matched_entries(
+            s,
+            &schema.pattern,
+            &schema.fields,
+            schema.time_unit,
+            kind,
+            op,
+        )
+    });
+    let keys = schema.keys.iter().flat_map(move |key| {
+        let time_unit = key.time_unit.unwrap_or(schema.time_unit);
+        let kind = context.kind_id(&key.kind);
+        matched_entries(s, &key.pattern, &key.fields, time_unit, kind, op)
+    });
+    (fast.into_iter().flatten().map(Ok))
+        .chain(slow.into_iter().flatten())
+        .chain(keys)
+}
+
+/// The `op` events of every entry of [`SYSTIME_PATTERN`] in `s`, scanned for
+/// by hand, which is several times faster than the regex. `None` if any entry
+/// isn't exactly in the layout, to leave matching and reporting it to the
+/// regex.
+fn systime_entries(s: &str, op: Op) -> Option<Vec<Event>> {
+    static ENTRY: LazyLock<Finder> = LazyLock::new(|| Finder::new("SstableBlockIndex { sst_id: "));
+    let bytes = s.as_bytes();
+    let mut events = vec![];
+    let mut at = 0;
+    while let Some(found) = ENTRY.find(&bytes[at..]) {
+        let mut rest = &bytes[at + found + ENTRY.needle().len()..];
+        let sst = number(&mut rest)?;
+        literal(&mut rest, b", block_idx: ")?;
+        let blk = number(&mut rest)?;
+        literal(&mut rest, b" }, SystemTime { tv_sec: ")?;
+        let secs = number(&mut rest)?;
+        literal(&mut rest, b", tv_nsec: ")?;
+        let nanos = number(&mut rest)?;
+        literal(&mut rest, b" }")?;
+        events.push(Event {
+            data: Data::new(sst, blk),
+            time: (UNIX_EPOCH.checked_add(Duration::from_secs(secs)))?
+                .checked_add(Duration::from_nanos(nanos))?,
+            op,
+            origin: Origin::default(),
+        });
+        at = bytes.len() - rest.len();
+    }
+    Some(events)
+}
+
+/// Takes the ASCII digits at the start of `rest` as a number, if there are
+/// any and they fit.
+fn number(rest: &mut &[u8]) -> Option<u64> {
+    let digits = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &digit in &rest[..digits] {
+        value = value
+            .checked_mul(10)?
+            .checked_add(u64::from(digit - b'0'))?;
+    }
+    *rest = &rest[digits..];
+    Some(value)
+}
+
+/// Takes `expected` from the start of `rest`.
+fn literal(rest: &mut &[u8], expected: &[u8]) -> Option<()> {
+    *rest = rest.strip_prefix(expected)?;
+    Some(())
+}
+
+/// [`entries`] through the regex of the key kind with id `kind`.
+fn matched_entries<'a>(
+    s: &'a str,
+    pattern: &'a Regex,
+    fields: &'a Fields,
+    time_unit: TimeUnit,
+    kind: u32,
+    op: Op,
+) -> impl Iterator<Item = Result<Event, String>> + 'a {
+    let id = |value: &str, name| {
+        value
+            .parse::<u64>()
+            .map_err(|e| format!("{name} {value}: {e}"))
+    };
+    pattern.captures_iter(s).map(move |cap| {
+        let sst = id(&cap[fields.sst], "sst id")?;
+        let blk = match fields.blk {
+            Some(blk) => id(&cap[blk], "block index")?,
+            None => 0,
+        };
+        let mut time = time_unit.parse(&cap[fields.time])?;
+        if let Some(nanos) = fields.nanos {
+            let nanos = Duration::from_nanos(id(&cap[nanos], "nanoseconds")?);
+            time = (time.checked_add(nanos))
+                .ok_or_else(|| format!("timestamp {} is out of range", &cap[0]))?;
+        }
+
+        let data = Data {
+            kind,
+            ..Data::new(sst, blk)
+        };
+
+        Ok(Event {
+            data,
+            time,
+            op,
+            origin: Origin::default(),
+        })
+    })
+}
+
+/// Input that was skipped while parsing.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A file or directory couldn't be read, so the rest of it is skipped.
+    #[error("can't read {file}: {source}")]
+    Read {
+        file: String,
+        #[source]
+        source: io::Error,
+    },
+    /// A record couldn't be parsed and is skipped.
+    #[error("{file}, line {line}: {message}")]
+    Malformed {
+        file: String,
+        line: u64,
+        message: String,
+    },
+}
+
+impl Error {
+    /// The input file the error is about.
+    pub fn file(&self) -> &str {
+        match self {
+            Error::Read { file, .. } | Error::Malformed { file, .. } => file,
+        }
+    }
+}
+
+/// Receives progress from [`parse_csv`] and [`parse_dir`].
+pub trait Observer {
+    /// Called with the events parsed from each CSV row or log line.
+    fn on_events(&mut self, _events: &[Event]) {}
+
+    /// Called when a record, or the rest of a file, is skipped. Returning the
+    /// error stops parsing with it instead.
+    fn on_skipped(&mut self, _error: Error) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called with the events parsed since the previous call, once `file` has
+    /// been parsed.
+    fn on_file(&mut self, _file: &str, _events: &[Event]) {}
+
+    /// Called once the input files are known, with their number.
+    fn on_inputs(&mut self, _count: usize) {}
+
+    /// Called after each input file, whether or not it had events.
+    fn on_parsed(&mut self, _file: &str) {}
+
+    /// Called for each CSV row of `file` with the banner of an `op` section
+    /// but no entries, at `line`.
+    fn on_empty_section(&mut self, _file: &str, _line: u64, _op: Op) {}
+}
+
+impl Observer for () {}
+
+/// Parses every CSV row from `reader` and appends the extracted events.
+///
+/// Header rows of coalesced files show up as plain records here, which is
+/// harmless since they never contain a section banner.
+///
+/// Parsed timestamps are shifted by `epoch`, for logs with relative times.
+/// Errors name the input `file`.
+pub fn parse_csv<R: Read>(
+    reader: R,
+    file: &str,
+    schema: &Schema,
+    epoch: Duration,
+    context: &Context,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let filter = Filter::default();
+    let scope = Scope {
+        file,
+        epoch,
+        filter: &filter,
+        discard: false,
+        lines: 0,
+        source: 0,
+        origin: 0,
+        context,
+    };
+    parse_rows(reader, schema, &scope, events, observer)
+}
+
+/// Parses a plain text log from `reader` line by line and appends the
+/// extracted events.
+///
+/// A banner line starts a section, and entries on the following lines belong
+/// to it until the next banner. Lines before the first banner are ignored.
+pub fn parse_log<R: Read>(
+    reader: R,
+    file: &str,
+    schema: &Schema,
+    epoch: Duration,
+    context: &Context,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let filter = Filter::default();
+    let scope = Scope {
+        file,
+        epoch,
+        filter: &filter,
+        discard: false,
+        lines: 0,
+        source: 0,
+        origin: 0,
+        context,
+    };
+    parse_lines(reader, schema, &scope, &mut None, events, observer)
+}
+
+/// What happens to each event extracted from an input file.
+struct Scope<'a> {
+    /// Named in errors.
+    file: &'a str,
+    /// Added to every timestamp.
+    epoch: Duration,
+    /// Only accepted events are kept.
+    filter: &'a Filter,
+    /// Drop each row's events again once observed.
+    discard: bool,
+    /// Lines of the file before the reader starts, when following it.
+    lines: u64,
+    /// Set as the [`Data::source`] of every event, unless it's 0.
+    source: u32,
+    /// Set as the [`Origin::file`] of every event along with its line, unless
+    /// it's 0.
+    origin: u32,
+    /// Registers the names the records give, and has relative timestamps
+    /// count from the oldest event.
+    context: &'a Context,
+}
+
+impl Scope<'_> {
+    /// Appends the accepted `parsed` events of the record at `line` and
+    /// reports them to `observer`, or skips the whole record if an entry is
+    /// invalid.
+    fn extend(
+        &self,
+        line: u64,
+        parsed: Result<Vec<Event>, String>,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let parsed = parsed.and_then(|parsed| {
+            (parsed.into_iter())
+                .map(|event| {
+                    let time = (event.time.checked_add(self.epoch))
+                        .ok_or("timestamp is out of range after adding the epoch")?;
+                    Ok(Event { time, ..event })
+                })
+                .collect::<Result<Vec<_>, String>>()
+        });
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => return observer.on_skipped(self.malformed(line, message)),
+        };
+        let start = events.len();
+        events.extend(
+            parsed
+                .into_iter()
+                .map(|event| Event {
+                    data: Data {
+                        source: if self.source == 0 {
+                            event.data.source
+                        } else {
+                            self.source
+                        },
+                        ..event.data
+                    },
+                    origin: if self.origin == 0 {
+                        event.origin
+                    } else {
+                        Origin {
+                            file: self.origin,
+                            // Unknown past what the field holds.
+                            line: u32::try_from(self.lines + line).unwrap_or(0),
+                        }
+                    },
+                    ..event
+                })
+                .filter(|event| self.filter.accepts(event, self.context)),
+        );
+        self.context.anchor_time(&events[start..]);
+        observer.on_events(&events[start..]);
+        if self.discard {
+            events.truncate(start);
+        }
+        Ok(())
+    }
+
+    /// Tells `observer` about the record `text` at `line` if it's a section
+    /// banner without entries, going by what it `parsed` into.
+    fn check_empty(
+        &self,
+        line: u64,
+        text: &str,
+        parsed: &Result<Vec<Event>, String>,
+        schema: &Schema,
+        observer: &mut impl Observer,
+    ) {
+        if let Ok(parsed) = parsed
+            && parsed.is_empty()
+            && let Some(op) = banner(text, schema)
+        {
+            observer.on_empty_section(self.file, self.lines + line, op);
+        }
+    }
+
+    fn malformed(&self, line: u64, message: String) -> Error {
+        Error::Malformed {
+            file: self.file.to_string(),
+            line: self.lines + line,
+            message,
+        }
+    }
+
+    fn unreadable(&self, source: io::Error) -> Error {
+        Error::Read {
+            file: self.file.to_string(),
+            source,
+        }
+    }
+}
+
+/// [`parse_csv`] within `scope`.
+fn parse_rows<R: Read>(
+    reader: R,
+    schema: &Schema,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    parse_rows_with(reader, schema, scope, events, observer, |_, _, _| {})
+}
+
+/// [`parse_rows`], calling `before_row` with the line each valid record
+/// starts at before adding its events.
+fn parse_rows_with<R: Read, O: Observer>(
+    reader: R,
+    schema: &Schema,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut O,
+    mut before_row: impl FnMut(u64, &[Event], &mut O),
+) -> Result<(), Error> {
+    // Rows are flattened with `as_slice` anyway, so ragged rows are fine.
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(reader);
+
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            // Say a truncated archive: nothing more can be read.
+            Err(e) if e.is_io_error() => {
+                return observer.on_skipped(scope.unreadable(io::Error::from(e)));
+            }
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line());
+                observer.on_skipped(scope.malformed(line, e.to_string()))?;
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line());
+        before_row(line, events, observer);
+        let parsed = parse(record.as_slice(), schema, scope.context);
+        scope.check_empty(line, record.as_slice(), &parsed, schema, observer);
+        scope.extend(line, parsed, events, observer)?;
+    }
+    Ok(())
+}
+
+/// [`parse_rows`] over the bytes of a mapped file, reusing one record
+/// buffer instead of allocating each row.
+fn parse_mapped_rows(
+    bytes: &[u8],
+    schema: &Schema,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(bytes);
+    let mut record = ByteRecord::new();
+
+    loop {
+        match reader.read_byte_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => return Ok(()),
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line());
+                observer.on_skipped(scope.malformed(line, e.to_string()))?;
+                continue;
+            }
+        }
+        let line = record.position().map_or(0, |position| position.line());
+        let parsed = match std::str::from_utf8(record.as_slice()) {
+            Ok(text) => {
+                let parsed = parse(text, schema, scope.context);
+                scope.check_empty(line, text, &parsed, schema, observer);
+                parsed
+            }
+            Err(e) => Err(format!("invalid UTF-8: {e}")),
+        };
+        scope.extend(line, parsed, events, observer)?;
+    }
+}
+
+/// One line of a text log, in the section of `op` unless it's a banner.
+fn log_line(
+    number: u64,
+    line: &[u8],
+    schema: &Schema,
+    scope: &Scope,
+    op: &mut Option<Op>,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    // Pod logs may carry the odd invalid byte, which never matters for the
+    // banners or entries. Valid lines are borrowed, not copied.
+    let text = String::from_utf8_lossy(line);
+    *op = banner(&text, schema).or(*op);
+    if let Some(op) = *op {
+        let parsed = entries(&text, schema, op, scope.context).collect();
+        scope.extend(number, parsed, events, observer)?;
+    }
+    Ok(())
+}
+
+/// [`parse_log`] within `scope`, starting in the section of `op` and
+/// leaving it at the last one seen.
+fn parse_lines<R: Read>(
+    reader: R,
+    schema: &Schema,
+    scope: &Scope,
+    op: &mut Option<Op>,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let mut reader = BufReader::new(reader);
+    let mut line = vec![];
+
+    for number in 1.. {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => return observer.on_skipped(scope.unreadable(e)),
+        }
+        log_line(number, &line, schema, scope, op, events, observer)?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// [`BinaryReader`] within `scope`, handing events to `observer` in batches.
+fn parse_binary<R: Read>(
+    reader: R,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    const BATCH_SIZE: usize = 4096;
+    let reader = match BinaryReader::new(BufReader::new(reader), scope.context) {
+        Ok(reader) => reader,
+        Err(e) => return observer.on_skipped(scope.unreadable(e)),
+    };
+    // Records have no lines, which only parse errors would need.
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for event in reader {
+        match event {
+            Ok(event) => batch.push(event),
+            Err(e) => {
+                scope.extend(0, Ok(batch), events, observer)?;
+                return observer.on_skipped(scope.unreadable(e));
+            }
+        }
+        if batch.len() == BATCH_SIZE {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+            scope.extend(0, Ok(full), events, observer)?;
+        }
+    }
+    if !batch.is_empty() {
+        scope.extend(0, Ok(batch), events, observer)?;
+    }
+    Ok(())
+}
+
+/// How each line of [`InputFormat::Events`] starts.
+const OUTPUT_PREFIX: &str = "Data { sst: ";
+
+/// Reads back the text layout of `blocks parse`, with any field separator.
+/// Grouped lines take their op from the section header above them. A
+/// trailing `file:line` of provenance is ignored.
+fn parse_output<R: Read>(
+    reader: R,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let record = Regex::new(
+        r"^\s*Data \{ sst: (\d+), blk: (\d+)(?:, source: (.*?))?(?:, kind: (.*?))? \}.*?(\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(?:\.\d+)?|\d+)(?:.*?(Evicted|Missed|Inserted|Hit))?(?:.+:\d+|\W+-)?\s*$",
+    )
+    .unwrap();
+    let mut reader = BufReader::new(reader);
+    let mut line = vec![];
+    let mut section = None;
+
+    for number in 1.. {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => return observer.on_skipped(scope.unreadable(e)),
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim();
+        if let Some(op) = Op::ALL.into_iter().find(|op| op.header() == text) {
+            section = Some(op);
+        } else if !text.is_empty() {
+            let parsed =
+                output_record(&record, text, section, scope.context).map(|event| vec![event]);
+            scope.extend(number, parsed, events, observer)?;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+/// One line of [`parse_output`], in the `section` op unless it names its own,
+/// with the names and the time style of `context`.
+fn output_record(
+    record: &Regex,
+    text: &str,
+    section: Option<Op>,
+    context: &Context,
+) -> Result<Event, String> {
+    let captures = record
+        .captures(text)
+        .ok_or("expected `Data { sst: .., blk: .. }`, a time and an op")?;
+    let id = |i: usize| -> Result<u64, String> {
+        captures[i]
+            .parse()
+            .map_err(|e| format!("id {}: {e}", &captures[i]))
+    };
+    let op = match captures.get(6) {
+        Some(name) => Op::ALL
+            .into_iter()
+            .find(|op| format!("{op:?}") == name.as_str())
+            .unwrap(),
+        None => section.ok_or("no op and no section header above")?,
+    };
+    Ok(Event {
+        data: Data {
+            sst: id(1)?,
+            blk: id(2)?,
+            source: (captures.get(3)).map_or(0, |name| context.source_id(name.as_str())),
+            kind: (captures.get(4)).map_or(0, |name| context.kind_id(name.as_str())),
+        },
+        time: (context.parse_formatted_time(&captures[5]))
+            .map_err(|e| format!("timestamp {}: {e}", &captures[5]))?,
+        op,
+        origin: Origin::default(),
+    })
+}
+
+/// Which parsed events are kept.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Drop events before this time.
+    pub since: Option<SystemTime>,
+    /// Drop events at or after this time.
+    pub until: Option<SystemTime>,
+    /// If not empty, only keep events of SSTs in one of these ranges.
+    pub ssts: Vec<RangeInclusive<u64>>,
+    /// If not empty, only keep events of blocks in one of these ranges.
+    pub blocks: Vec<RangeInclusive<u64>>,
+    /// Only keep events of this share of the blocks, see [`Data::sampled`].
+    pub sample: Option<f64>,
+    /// If not empty, only keep events of keys of these kinds, see
+    /// [`Data::kind`].
+    pub kinds: Vec<u32>,
+}
+
+impl Filter {
+    pub fn accepts(&self, event: &Event, context: &Context) -> bool {
+        let within = |ranges: &[RangeInclusive<u64>], id| {
+            ranges.is_empty() || ranges.iter().any(|range| range.contains(&id))
+        };
+        self.since.is_none_or(|since| event.time >= since)
+            && self.until.is_none_or(|until| event.time < until)
+            && within(&self.ssts, event.data.sst)
+            && within(&self.blocks, event.data.blk)
+            && self
+                .sample
+                .is_none_or(|rate| event.data.sampled(rate, context))
+            && (self.kinds.is_empty() || self.kinds.contains(&event.data.kind))
+    }
+}
+
+/// Per-file epoch overrides, matched by regex against the file name.
+#[derive(Debug)]
+pub struct EpochMap(Vec<(Regex, Duration)>);
+
+impl EpochMap {
+    /// Loads a file of `<file name regex> <epoch secs>` lines. Blank lines and
+    /// lines starting with `#` are skipped.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = read_text(path).with_context(|| format!("can't read {}", path.display()))?;
+        let entries = (content.lines().enumerate())
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(number, line)| {
+                let entry = || -> anyhow::Result<_> {
+                    let (pattern, epoch) = (line.rsplit_once(char::is_whitespace))
+                        .context("expected `<file name regex> <epoch secs>`")?;
+                    let epoch = epoch.parse().context("invalid epoch secs")?;
+                    Ok((Regex::new(pattern.trim())?, Duration::from_secs(epoch)))
+                };
+                entry().with_context(|| format!("{}:{number}: {line}", path.display()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self(entries))
+    }
+
+    /// The epoch of the first pattern matching `file_name`, if any.
+    pub fn get(&self, file_name: &str) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(file_name))
+            .map(|(_, epoch)| *epoch)
+    }
+}
+
+/// Compression of the input files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Compression {
+    /// Decided per file: `.gz` is gzip, `.zst` zstd, anything else plain.
+    #[default]
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of `path` if it's an input file of `format`, `None`
+    /// otherwise, along with the file's actual format.
+    fn detect(self, path: &Path, format: InputFormat) -> Option<(Self, InputFormat)> {
+        let file_name = path.file_name()?.to_string_lossy();
+        let (compression, name) = self.strip(&file_name);
+        let found = if name.ends_with(".csv") {
+            InputFormat::Csv
+        } else if name.ends_with(".log") || name.ends_with(".txt") {
+            // Both are text, only the content tells logs from earlier output.
+            if format == InputFormat::Events {
+                InputFormat::Events
+            } else {
+                InputFormat::Text
+            }
+        } else if name.ends_with(".blocks") {
+            InputFormat::Binary
+        } else {
+            return None;
+        };
+        // Binary files can't be mistaken for anything else.
+        if format != InputFormat::Auto && format != found && found != InputFormat::Binary {
+            return None;
+        }
+        Some((compression, found))
+    }
+
+    /// The compression of a file called `name`, and `name` without its
+    /// compression suffix.
+    fn strip(self, name: &str) -> (Self, &str) {
+        let (detected, name) = if let Some(name) = name.strip_suffix(".gz") {
+            (Compression::Gzip, name)
+        } else if let Some(name) = name.strip_suffix(".zst") {
+            (Compression::Zstd, name)
+        } else {
+            (Compression::None, name)
+        };
+        if self == Compression::Auto {
+            (detected, name)
+        } else {
+            (self, name)
+        }
+    }
+
+    /// Opens `path`, decompressing it on the fly.
+    fn open(self, path: &Path) -> io::Result<Box<dyn Read>> {
+        self.decode(BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Wraps `reader` to decompress it on the fly, and to convert it to
+    /// UTF-8 if it starts with a byte order mark of another encoding.
+    fn decode(self, reader: impl BufRead + 'static) -> io::Result<Box<dyn Read>> {
+        let decompressed: Box<dyn Read> = match self {
+            Compression::Auto | Compression::None => Box::new(reader),
+            // Concatenated gzip members are common for appended logs.
+            Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+            Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        };
+        // Without a byte order mark the bytes pass through untouched.
+        let decoded = DecodeReaderBytesBuilder::new()
+            .utf8_passthru(true)
+            .strip_bom(true)
+            .build(decompressed);
+        Ok(Box::new(decoded))
+    }
+}
+
+/// The encoding the byte order mark at the start of `bytes` gives away, along
+/// with the length of the mark. Input without one is taken as UTF-8, such as
+/// the UTF-16 logs of Windows hosts.
+fn encoding_of(bytes: &[u8]) -> (&'static Encoding, usize) {
+    Encoding::for_bom(bytes).unwrap_or((UTF_8, 0))
+}
+
+/// `bytes` of `encoding`, without a byte order mark, in UTF-8. UTF-8 is left
+/// as it is, valid or not.
+fn to_utf8<'a>(encoding: &'static Encoding, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+    if encoding == UTF_8 {
+        return Cow::Borrowed(bytes);
+    }
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    Cow::Owned(text.into_owned().into_bytes())
+}
+
+/// `bytes` in UTF-8, without the byte order mark they may start with.
+fn utf8(bytes: &[u8]) -> Cow<'_, [u8]> {
+    let (encoding, bom) = encoding_of(bytes);
+    to_utf8(encoding, &bytes[bom..])
+}
+
+/// Reads the text file at `path`, which may be UTF-16 with a byte order mark.
+fn read_text(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    String::from_utf8(utf8(&bytes).into_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Format of the input files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum InputFormat {
+    /// `.csv` files with one log section per cell.
+    #[default]
+    Csv,
+    /// `.log` and `.txt` files with the sections written line by line.
+    Text,
+    /// Only `.blocks` files written by `blocks convert`, which are read in
+    /// any input format.
+    Binary,
+    /// `.log` and `.txt` files written by `blocks parse` or `blocks merge`
+    /// in the text layout. Input files named directly and stdin are read
+    /// like this in any input format if they start like it.
+    Events,
+    /// Decided per file by its extension.
+    Auto,
+}
+
+impl InputFormat {
+    fn parse<R: Read>(
+        self,
+        reader: R,
+        schema: &Schema,
+        scope: &Scope,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        match self {
+            InputFormat::Csv | InputFormat::Auto => {
+                parse_rows(reader, schema, scope, events, observer)
+            }
+            InputFormat::Text => parse_lines(reader, schema, scope, &mut None, events, observer),
+            InputFormat::Binary => parse_binary(reader, scope, events, observer),
+            InputFormat::Events => parse_output(reader, scope, events, observer),
+        }
+    }
+
+    /// [`InputFormat::parse`] over the bytes of a mapped file.
+    fn parse_mapped(
+        self,
+        bytes: &[u8],
+        schema: &Schema,
+        scope: &Scope,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let bytes = &*utf8(bytes);
+        match self {
+            InputFormat::Csv | InputFormat::Auto => {
+                parse_mapped_rows(bytes, schema, scope, events, observer)
+            }
+            InputFormat::Text => {
+                let mut op = None;
+                for (number, line) in (1..).zip(bytes.split_inclusive(|&byte| byte == b'\n')) {
+                    log_line(number, line, schema, scope, &mut op, events, observer)?;
+                }
+                Ok(())
+            }
+            InputFormat::Binary | InputFormat::Events => {
+                self.parse(bytes, schema, scope, events, observer)
+            }
+        }
+    }
+
+    /// [`InputFormat::Binary`] or [`InputFormat::Events`] if the start of
+    /// `reader` gives either away, `self` otherwise.
+    fn sniff(self, reader: &mut impl BufRead) -> io::Result<Self> {
+        let start = reader.fill_buf()?;
+        let mut output = Op::ALL.iter().map(Op::header).chain([OUTPUT_PREFIX]);
+        Ok(if start.starts_with(MAGIC) {
+            InputFormat::Binary
+        } else if output.any(|prefix| start.starts_with(prefix.as_bytes())) {
+            InputFormat::Events
+        } else {
+            self
+        })
+    }
+}
+
+/// How [`parse_dir`] reads its input files.
+#[derive(Debug, Default)]
+pub struct ParseOptions {
+    /// Offset added to every parsed timestamp.
+    pub epoch: Duration,
+    /// Per-file overrides of `epoch`.
+    pub epoch_map: Option<EpochMap>,
+    /// Concatenate small files into larger buffers before parsing.
+    pub coalesce_files: bool,
+    /// Only hand events to [`Observer::on_events`] instead of collecting them
+    /// in [`ParsedDir::events`], which keeps memory bounded. `on_file` gets no
+    /// events in this mode.
+    pub discard_events: bool,
+    /// Number of files parsed in parallel, or all cores if `None`.
+    pub threads: Option<usize>,
+    pub compression: Compression,
+    pub input_format: InputFormat,
+    /// Also look for input files in subdirectories.
+    pub recursive: bool,
+    /// If not empty, only files whose path below the input directory matches
+    /// are parsed.
+    pub include: GlobSet,
+    /// Files whose path below the input directory matches are skipped.
+    pub exclude: GlobSet,
+    pub filter: Filter,
+    /// Map uncompressed input files into memory and parse them in place,
+    /// which saves copying for very large files. They must not be truncated
+    /// while being parsed.
+    pub mmap: bool,
+    /// Tells the sources of events apart, such as cache shards or nodes, by
+    /// the input file paths this matches: the first capture group is the
+    /// [`Data::source`] if there is one, the whole match otherwise. Files it
+    /// doesn't match are a source of their own, named by their path.
+    pub source: Option<Regex>,
+    /// List directories and read whole files with up to this many at once,
+    /// see [`crate::async_io`], instead of each parsing thread opening its
+    /// own. Ignored with `coalesce_files`, and files aren't mapped.
+    pub async_reads: Option<usize>,
+}
+
+impl ParseOptions {
+    /// The [`Data::source`] of the input file at `path`, registered in
+    /// `context`.
+    fn source_of(&self, path: &str, context: &Context) -> u32 {
+        let Some(pattern) = &self.source else {
+            return 0;
+        };
+        let name = pattern.captures(path).map_or(path, |captures| {
+            captures.get(1).unwrap_or(captures.get(0).unwrap()).as_str()
+        });
+        context.source_id(name)
+    }
+}
+
+/// The result of [`parse_dir`].
+#[derive(Debug, Default)]
+pub struct ParsedDir {
+    pub events: Vec<Event>,
+    /// The range of `events` each file produced, only tracked when files are
+    /// not coalesced.
+    pub files: Vec<(String, Range<usize>)>,
+    /// Number of directory entries seen.
+    pub entries: usize,
+    /// Number of entries that were parsed as input files: `.csv`, `.log` or
+    /// `.txt` files depending on the input format and `.blocks` files,
+    /// optionally compressed as `.gz` or `.zst`.
+    pub matched: usize,
+}
+
+/// Name of the input that reads from stdin instead of a directory.
+pub const STDIN: &str = "-";
+
+/// Prefix of input directories in S3 compatible object storage, which needs
+/// the `s3` feature.
+pub const S3_SCHEME: &str = "s3://";
+
+/// Parses every input file in `dir`, in file name order, or stdin if `dir` is
+/// [`STDIN`]. `dir` may also be an object storage prefix, see [`S3_SCHEME`].
+///
+/// Without `coalesce_files`, files are parsed in parallel batches and
+/// `observer` then sees each batch's results in directory order, exactly as a
+/// sequential parse would.
+///
+/// The names of sources, kinds and input files are registered in `context`,
+/// which the events must be shown with.
+pub fn parse_dir(
+    dir: &Path,
+    schema: &Schema,
+    options: &ParseOptions,
+    context: &Context,
+    observer: &mut impl Observer,
+) -> Result<ParsedDir, Error> {
+    if dir == Path::new(STDIN) {
+        return parse_stdin(schema, options, context, observer);
+    }
+
+    let mut parsed = ParsedDir::default();
+    let inputs = if let Some(url) = dir.to_str().filter(|dir| dir.starts_with(S3_SCHEME)) {
+        objects(url, options, context, &mut parsed)?
+    } else if dir.is_file() {
+        parsed.entries = 1;
+        vec![Input::named(dir, options, context)]
+    } else {
+        walk(dir, options, context, &mut parsed, observer)?
+    };
+    parsed.matched = inputs.len();
+    observer.on_inputs(inputs.len());
+
+    if options.coalesce_files {
+        parse_coalesced(
+            &inputs,
+            schema,
+            options,
+            context,
+            &mut parsed.events,
+            observer,
+        )?;
+    } else {
+        parse_parallel(&inputs, schema, options, context, &mut parsed, observer)?;
+    }
+
+    Ok(parsed)
+}
+
+/// The input files in `dir`, counting its entries in `parsed`.
+fn walk(
+    dir: &Path,
+    options: &ParseOptions,
+    context: &Context,
+    parsed: &mut ParsedDir,
+    observer: &mut impl Observer,
+) -> Result<Vec<Input>, Error> {
+    let mut inputs = vec![];
+    // Each entry's path and whether it's a regular file.
+    let entries: Box<dyn Iterator<Item = (PathBuf, io::Result<bool>)>> = match options.async_reads {
+        Some(concurrency) => {
+            Box::new(async_io::list(dir.to_path_buf(), options.recursive, concurrency).into_iter())
+        }
+        None => {
+            let max_depth = if options.recursive { usize::MAX } else { 1 };
+            let walk = WalkDir::new(dir)
+                .min_depth(1)
+                .max_depth(max_depth)
+                .sort_by_file_name();
+            Box::new(walk.into_iter().map(|entry| match entry {
+                Ok(entry) => (entry.path().to_path_buf(), Ok(entry.file_type().is_file())),
+                Err(e) => (e.path().unwrap_or(dir).to_path_buf(), Err(e.into())),
+            }))
+        }
+    };
+
+    for (file_path, is_file) in entries {
+        let is_file = match is_file {
+            Ok(is_file) => is_file,
+            Err(source) => {
+                observer.on_skipped(Error::Read {
+                    file: file_path.to_string_lossy().into_owned(),
+                    source,
+                })?;
+                continue;
+            }
+        };
+        parsed.entries += 1;
+        if !is_file {
+            continue;
+        }
+        // Files are named by their path below `dir`, which is also what the
+        // globs and the epoch map see, with `/` between directories on any
+        // platform.
+        let relative = file_path.strip_prefix(dir).unwrap();
+        if (!options.include.is_empty() && !options.include.is_match(relative))
+            || options.exclude.is_match(relative)
+        {
+            continue;
+        }
+
+        let file_name = (relative.components())
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        inputs.extend(Input::listed(file_name, file_path, options, context));
+    }
+    Ok(inputs)
+}
+
+/// The input files below the object storage prefix `dir`, like [`walk`].
+#[cfg(feature = "s3")]
+fn objects(
+    dir: &str,
+    options: &ParseOptions,
+    context: &Context,
+    parsed: &mut ParsedDir,
+) -> Result<Vec<Input>, Error> {
+    let objects = crate::s3::list(dir, options.recursive).map_err(|source| Error::Read {
+        file: dir.to_string(),
+        source,
+    })?;
+    parsed.entries = objects.len();
+    let mut inputs = vec![];
+    for (relative, url) in objects {
+        if (!options.include.is_empty() && !options.include.is_match(&relative))
+            || options.exclude.is_match(&relative)
+        {
+            continue;
+        }
+        if let Some(mut input) = Input::listed(relative, PathBuf::from(url), options, context) {
+            input.mmap = false;
+            inputs.push(input);
+        }
+    }
+    Ok(inputs)
+}
+
+#[cfg(not(feature = "s3"))]
+fn objects(
+    dir: &str,
+    _: &ParseOptions,
+    _: &Context,
+    _: &mut ParsedDir,
+) -> Result<Vec<Input>, Error> {
+    Err(Error::Read {
+        file: dir.to_string(),
+        source: io::Error::other("object storage needs blocks built with the `s3` feature"),
+    })
+}
+
+/// Parses stdin as it arrives, as a single input file named [`STDIN`].
+///
+/// Nothing tells the format of a pipe, so `Auto` reads text logs as written
+/// by `kubectl logs`, and uncompressed unless told otherwise.
+fn parse_stdin(
+    schema: &Schema,
+    options: &ParseOptions,
+    context: &Context,
+    observer: &mut impl Observer,
+) -> Result<ParsedDir, Error> {
+    let format = match options.input_format {
+        InputFormat::Auto => InputFormat::Text,
+        format => format,
+    };
+    let scope = Scope {
+        file: STDIN,
+        epoch: options
+            .epoch_map
+            .as_ref()
+            .and_then(|map| map.get(STDIN))
+            .unwrap_or(options.epoch),
+        filter: &options.filter,
+        discard: options.discard_events,
+        lines: 0,
+        source: options.source_of(STDIN, context),
+        origin: origin_of(STDIN, context),
+        context,
+    };
+
+    let mut parsed = ParsedDir {
+        entries: 1,
+        matched: 1,
+        ..Default::default()
+    };
+    observer.on_inputs(1);
+    let reader = options
+        .compression
+        .decode(io::stdin().lock())
+        .map(BufReader::new)
+        .and_then(|mut reader| Ok((format.sniff(&mut reader)?, reader)));
+    match reader {
+        Ok((format, reader)) => {
+            format.parse(reader, schema, &scope, &mut parsed.events, observer)?
+        }
+        Err(e) => observer.on_skipped(scope.unreadable(e))?,
+    }
+    let events = &parsed.events;
+    parsed.files.push((STDIN.to_string(), 0..events.len()));
+    if !events.is_empty() {
+        observer.on_file(STDIN, events);
+    }
+    observer.on_parsed(STDIN);
+    Ok(parsed)
+}
+
+/// An input file found by [`parse_dir`].
+struct Input {
+    file_name: String,
+    file_path: PathBuf,
+    epoch: Duration,
+    compression: Compression,
+    format: InputFormat,
+    /// Parse the file mapped into memory if it's uncompressed.
+    mmap: bool,
+    /// See [`Scope::source`].
+    source: u32,
+    /// See [`Scope::origin`].
+    origin: u32,
+}
+
+/// The [`Scope::origin`] of the input file `name`, registered in `context`.
+fn origin_of(name: &str, context: &Context) -> u32 {
+    if context.has_provenance() {
+        context.origin_file_id(name)
+    } else {
+        0
+    }
+}
+
+impl Input {
+    /// A file found in a directory as `file_name`, if it's an input file.
+    fn listed(
+        file_name: String,
+        file_path: PathBuf,
+        options: &ParseOptions,
+        context: &Context,
+    ) -> Option<Self> {
+        let (compression, format) = options
+            .compression
+            .detect(&file_path, options.input_format)?;
+        let epoch = options
+            .epoch_map
+            .as_ref()
+            .and_then(|map| map.get(&file_name))
+            .unwrap_or(options.epoch);
+        Some(Input {
+            origin: origin_of(&file_name, context),
+            file_name,
+            source: options.source_of(&file_path.to_string_lossy(), context),
+            file_path,
+            epoch,
+            compression,
+            format,
+            mmap: options.mmap,
+        })
+    }
+
+    /// An input file named directly instead of a directory. Its extension
+    /// decides like in a directory, falling back to the input format, but
+    /// binary traces and earlier output are recognized by their content.
+    fn named(path: &Path, options: &ParseOptions, context: &Context) -> Self {
+        let file_path = path.to_path_buf();
+        let path = &*path.to_string_lossy();
+        let (compression, format) = options
+            .compression
+            .detect(&file_path, options.input_format)
+            .unwrap_or_else(|| (options.compression.strip(path).0, options.input_format));
+        // If it can't be opened, parsing it reports why.
+        let format = compression
+            .open(&file_path)
+            .and_then(|reader| format.sniff(&mut BufReader::new(reader)))
+            .unwrap_or(format);
+        Input {
+            file_name: path.to_string(),
+            epoch: options
+                .epoch_map
+                .as_ref()
+                .and_then(|map| map.get(path))
+                .unwrap_or(options.epoch),
+            source: options.source_of(path, context),
+            origin: origin_of(path, context),
+            file_path,
+            compression,
+            format,
+            mmap: options.mmap,
+        }
+    }
+
+    fn scope<'a>(
+        &'a self,
+        options: &'a ParseOptions,
+        context: &'a Context,
+        discard: bool,
+    ) -> Scope<'a> {
+        Scope {
+            file: &self.file_name,
+            epoch: self.epoch,
+            filter: &options.filter,
+            discard,
+            lines: 0,
+            source: self.source,
+            origin: self.origin,
+            context,
+        }
+    }
+
+    /// Opens the file, decompressing it.
+    fn open(&self) -> io::Result<Box<dyn Read>> {
+        match self.file_path.to_str() {
+            #[cfg(feature = "s3")]
+            Some(url) if url.starts_with(S3_SCHEME) => {
+                (self.compression).decode(BufReader::new(crate::s3::open(url)?))
+            }
+            _ => self.compression.open(&self.file_path),
+        }
+    }
+
+    /// Parses `contents`, the file read whole, reporting it as skipped if it
+    /// couldn't be.
+    fn parse_contents(
+        &self,
+        contents: io::Result<Vec<u8>>,
+        schema: &Schema,
+        scope: &Scope,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let contents = match contents {
+            Ok(contents) => contents,
+            Err(e) => return observer.on_skipped(scope.unreadable(e)),
+        };
+        if matches!(self.compression, Compression::Auto | Compression::None) {
+            return (self.format).parse_mapped(&contents, schema, scope, events, observer);
+        }
+        match self.compression.decode(io::Cursor::new(contents)) {
+            Ok(reader) => self.format.parse(reader, schema, scope, events, observer),
+            Err(e) => observer.on_skipped(scope.unreadable(e)),
+        }
+    }
+
+    /// Parses the file, reporting it as skipped if it can't be opened.
+    fn parse(
+        &self,
+        schema: &Schema,
+        scope: &Scope,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        if self.mmap && matches!(self.compression, Compression::Auto | Compression::None) {
+            return match map(&self.file_path) {
+                Ok(bytes) => self
+                    .format
+                    .parse_mapped(&bytes, schema, scope, events, observer),
+                Err(e) => observer.on_skipped(scope.unreadable(e)),
+            };
+        }
+        match self.open() {
+            Ok(reader) => self.format.parse(reader, schema, scope, events, observer),
+            Err(e) => observer.on_skipped(scope.unreadable(e)),
+        }
+    }
+}
+
+/// Maps the file at `path` into memory, read-only.
+fn map(path: &Path) -> io::Result<Mmap> {
+    let file = fs::File::open(path)?;
+    // SAFETY: the mapping is only read, and `ParseOptions::mmap` documents
+    // that input files must not be truncated meanwhile.
+    unsafe { Mmap::map(&file) }
+}
+
+/// Parses the input files of a directory that keeps growing: like
+/// [`parse_dir`], but each [`Follower::poll`] only parses what was added
+/// since the previous one. Plain text logs are read as lines are appended to
+/// them, any other input file once whole when it shows up, so those should be
+/// moved in complete.
+pub struct Follower<'a> {
+    dir: &'a Path,
+    schema: &'a Schema,
+    options: &'a ParseOptions,
+    context: &'a Context,
+    offsets: Offsets,
+}
+
+/// How far a [`Follower`] got in each file, which can be saved to resume from
+/// in a later run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Offsets {
+    files: HashMap<PathBuf, Tail>,
+}
+
+/// How much of a followed file has been parsed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tail {
+    offset: u64,
+    lines: u64,
+    /// The section the last line was in.
+    op: Option<Op>,
+}
+
+impl<'a> Follower<'a> {
+    pub fn new(
+        dir: &'a Path,
+        schema: &'a Schema,
+        options: &'a ParseOptions,
+        context: &'a Context,
+    ) -> Self {
+        Self {
+            dir,
+            schema,
+            options,
+            context,
+            offsets: Offsets::default(),
+        }
+    }
+
+    /// Continues where the follower that left `offsets` stopped.
+    pub fn with_offsets(self, offsets: Offsets) -> Self {
+        Self { offsets, ..self }
+    }
+
+    pub fn into_offsets(self) -> Offsets {
+        self.offsets
+    }
+
+    /// Parses everything added since the last call, the whole directory on
+    /// the first one. `observer` sees the new events of each file, in file
+    /// name order, and they are returned unless `discard_events` is set. A
+    /// log that got shorter is parsed again from the start.
+    pub fn poll(&mut self, observer: &mut impl Observer) -> Result<Vec<Event>, Error> {
+        let mut parsed = ParsedDir::default();
+        let context = self.context;
+        for input in walk(self.dir, self.options, context, &mut parsed, observer)? {
+            let files = &mut self.offsets.files;
+            let seen = files.contains_key(&input.file_path);
+            let tail = files.entry(input.file_path.clone()).or_default();
+            let start = parsed.events.len();
+            let events = &mut parsed.events;
+            if input.compression == Compression::None && input.format == InputFormat::Text {
+                Self::tail(
+                    &input,
+                    self.schema,
+                    self.options,
+                    context,
+                    tail,
+                    events,
+                    observer,
+                )?;
+            } else if !seen {
+                let scope = input.scope(self.options, context, self.options.discard_events);
+                input.parse(self.schema, &scope, events, observer)?;
+            }
+            if events.len() > start {
+                observer.on_file(&input.file_name, &events[start..]);
+            }
+            observer.on_parsed(&input.file_name);
+        }
+        Ok(parsed.events)
+    }
+
+    /// Parses the lines appended to `input` since `tail`, converted to UTF-8
+    /// if the file starts with a byte order mark of another encoding.
+    fn tail(
+        input: &Input,
+        schema: &Schema,
+        options: &ParseOptions,
+        context: &Context,
+        tail: &mut Tail,
+        events: &mut Vec<Event>,
+        observer: &mut impl Observer,
+    ) -> Result<(), Error> {
+        let read = fs::File::open(&input.file_path).and_then(|mut file| {
+            let len = file.metadata()?.len();
+            if len < tail.offset {
+                *tail = Tail::default();
+            }
+            let mut start = vec![];
+            (&mut file).take(3).read_to_end(&mut start)?;
+            let (encoding, bom) = encoding_of(&start);
+            tail.offset = tail.offset.max(bom as u64);
+            file.seek(SeekFrom::Start(tail.offset))?;
+            let mut appended = vec![];
+            file.take(len - tail.offset).read_to_end(&mut appended)?;
+            Ok((encoding, appended))
+        });
+        let scope = Scope {
+            lines: tail.lines,
+            ..input.scope(options, context, options.discard_events)
+        };
+        let (encoding, appended) = match read {
+            Ok(read) => read,
+            Err(e) => return observer.on_skipped(scope.unreadable(e)),
+        };
+        // A line still being written is parsed once it's complete. UTF-16
+        // ends lines with two bytes, in the byte order of the encoding.
+        let newline: &[u8] = if encoding == UTF_16LE {
+            b"\n\0"
+        } else if encoding == UTF_16BE {
+            b"\0\n"
+        } else {
+            b"\n"
+        };
+        let complete = (appended.chunks_exact(newline.len()))
+            .rposition(|unit| unit == newline)
+            .map_or(0, |i| (i + 1) * newline.len());
+        tail.offset += complete as u64;
+        let appended = to_utf8(encoding, &appended[..complete]);
+        tail.lines += appended.iter().filter(|&&b| b == b'\n').count() as u64;
+        parse_lines(&*appended, schema, &scope, &mut tail.op, events, observer)
+    }
+}
+
+/// Only CSV files are coalesced. Text logs and binary files are parsed one by
+/// one in between, so that no section runs on into the next file. Errors in coalesced files
+/// name `all files` and the line in the buffer.
+fn parse_coalesced(
+    inputs: &[Input],
+    schema: &Schema,
+    options: &ParseOptions,
+    context: &Context,
+    events: &mut Vec<Event>,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let mut coalesced = vec![];
+    // The files in the buffer, each with the last line of it they fill.
+    let mut pending = vec![];
+    let mut lines = 0;
+    let mut coalesced_epoch = Duration::ZERO;
+    let mut coalesced_source = 0;
+    let mut coalesced_origin = 0;
+    let scope = |epoch, source, origin| Scope {
+        file: "all files",
+        epoch,
+        filter: &options.filter,
+        discard: options.discard_events,
+        lines: 0,
+        source,
+        origin,
+        context,
+    };
+
+    for input in inputs {
+        let (epoch, source, origin) = (input.epoch, input.source, input.origin);
+        // A buffer must only hold files sharing the same epoch and source,
+        // and with provenance only one file.
+        if (epoch != coalesced_epoch
+            || source != coalesced_source
+            || origin != coalesced_origin
+            || input.format != InputFormat::Csv)
+            && !coalesced.is_empty()
+        {
+            parse_pending(
+                &coalesced,
+                &mut pending,
+                schema,
+                &scope(coalesced_epoch, coalesced_source, coalesced_origin),
+                events,
+                observer,
+            )?;
+            coalesced.clear();
+            lines = 0;
+        }
+        if input.format != InputFormat::Csv {
+            let scope = input.scope(options, context, options.discard_events);
+            let start = events.len();
+            input.parse(schema, &scope, events, observer)?;
+            if events.len() > start {
+                observer.on_file(&input.file_name, &events[start..]);
+            }
+        } else {
+            coalesced_epoch = epoch;
+            coalesced_source = source;
+            coalesced_origin = origin;
+            let start = coalesced.len();
+            let read = input
+                .open()
+                .and_then(|mut reader| reader.read_to_end(&mut coalesced));
+            if let Err(e) = read {
+                // Drop what was read, parsing half a file would only add
+                // malformed rows.
+                coalesced.truncate(start);
+                let scope = input.scope(options, context, options.discard_events);
+                observer.on_skipped(scope.unreadable(e))?;
+            }
+            if coalesced.last().is_some_and(|&b| b != b'\n') {
+                coalesced.push(b'\n');
+            }
+            lines += coalesced[start..].iter().filter(|&&b| b == b'\n').count() as u64;
+            pending.push((input.file_name.as_str(), lines));
+            if coalesced.len() >= COALESCE_BUFFER_SIZE {
+                parse_pending(
+                    &coalesced,
+                    &mut pending,
+                    schema,
+                    &scope(epoch, source, origin),
+                    events,
+                    observer,
+                )?;
+                coalesced.clear();
+                lines = 0;
+            }
+        }
+        observer.on_parsed(&input.file_name);
+    }
+    if !coalesced.is_empty() {
+        parse_pending(
+            &coalesced,
+            &mut pending,
+            schema,
+            &scope(coalesced_epoch, coalesced_source, coalesced_origin),
+            events,
+            observer,
+        )?;
+    }
+    Ok(())
+}
+
+/// Parses the `coalesced` rows of the `pending` files, each given with the
+/// last line of the buffer it fills, and reports the events of each under
+/// its own name.
+fn parse_pending<O: Observer>(
+    coalesced: &[u8],
+    pending: &mut Vec<(&str, u64)>,
+    schema: &Schema,
+    scope: &Scope,
+    events: &mut Vec<Event>,
+    observer: &mut O,
+) -> Result<(), Error> {
+    let mut files = pending.drain(..).peekable();
+    let mut seen = events.len();
+    // Reports the files ending before `line`.
+    let mut report = |line, events: &[Event], observer: &mut O| {
+        while let Some((file, _)) = files.next_if(|&(_, end)| end < line) {
+            if events.len() > seen {
+                observer.on_file(file, &events[seen..]);
+            }
+            seen = events.len();
+        }
+    };
+    parse_rows_with(coalesced, schema, scope, events, observer, &mut report)?;
+    report(u64::MAX, events, observer);
+    Ok(())
+}
+
+/// Observer calls made while parsing one file on a worker thread.
+#[derive(Default)]
+struct Recorder {
+    events: usize,
+    calls: Vec<Call>,
+}
+
+enum Call {
+    /// A row's events, ending at this index of the file's events.
+    Events(usize),
+    Skipped(Error),
+    EmptySection(u64, Op),
+}
+
+impl Observer for Recorder {
+    fn on_events(&mut self, events: &[Event]) {
+        self.events += events.len();
+        self.calls.push(Call::Events(self.events));
+    }
+
+    fn on_skipped(&mut self, error: Error) -> Result<(), Error> {
+        // Whether to go on is up to the real observer during the replay.
+        self.calls.push(Call::Skipped(error));
+        Ok(())
+    }
+
+    fn on_empty_section(&mut self, _file: &str, line: u64, op: Op) {
+        self.calls.push(Call::EmptySection(line, op));
+    }
+}
+
+/// Events a worker holds before handing them on, when events are discarded.
+const CHUNK_EVENTS: usize = 16 * 1024;
+
+/// Chunks in flight per file before its worker waits for the replay.
+const CHUNKS_IN_FLIGHT: usize = 2;
+
+/// Part of a file's events, and the calls indexing into them.
+struct Chunk {
+    events: Vec<Event>,
+    calls: Vec<Call>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            // A row rarely has many events, so this is about what it holds.
+            events: Vec::with_capacity(CHUNK_EVENTS),
+            calls: vec![],
+        }
+    }
+}
+
+/// Like [`Recorder`], but sends the events along with the calls in chunks, so
+/// that no file's events are held whole.
+struct Forwarder {
+    chunk: Chunk,
+    chunks: Sender<Chunk>,
+}
+
+impl Forwarder {
+    fn flush(&mut self) {
+        // The replay only goes away when it stopped on an error.
+        let _ = self
+            .chunks
+            .send(std::mem::replace(&mut self.chunk, Chunk::new()));
+    }
+}
+
+impl Observer for Forwarder {
+    fn on_events(&mut self, events: &[Event]) {
+        self.chunk.events.extend_from_slice(events);
+        self.chunk.calls.push(Call::Events(self.chunk.events.len()));
+        if self.chunk.events.len() >= CHUNK_EVENTS {
+            self.flush();
+        }
+    }
+
+    fn on_skipped(&mut self, error: Error) -> Result<(), Error> {
+        self.chunk.calls.push(Call::Skipped(error));
+        Ok(())
+    }
+
+    fn on_empty_section(&mut self, _file: &str, line: u64, op: Op) {
+        self.chunk.calls.push(Call::EmptySection(line, op));
+    }
+}
+
+impl Drop for Forwarder {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+fn parse_parallel(
+    inputs: &[Input],
+    schema: &Schema,
+    options: &ParseOptions,
+    context: &Context,
+    parsed: &mut ParsedDir,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(threads) = options.threads {
+        builder = builder.num_threads(threads);
+    }
+    let pool = builder.build().unwrap();
+    let mut reads = options.async_reads.map(|concurrency| {
+        let paths = inputs.iter().map(|input| input.file_path.clone()).collect();
+        async_io::read_ahead(paths, concurrency)
+    });
+
+    // Only one batch of per-file results is held at a time, so memory stays
+    // bounded when events are discarded.
+    for batch in inputs.chunks(pool.current_num_threads()) {
+        // The batch's files read whole ahead of time, if they are.
+        let contents: Vec<_> = match &mut reads {
+            Some(reads) => reads.by_ref().take(batch.len()).map(Some).collect(),
+            None => batch.iter().map(|_| None).collect(),
+        };
+        if !options.discard_events {
+            let results: Vec<_> = pool.install(|| {
+                (batch.par_iter().zip(contents))
+                    .map(|(input, contents)| {
+                        let mut recorder = Recorder::default();
+                        let file_events =
+                            parse_file(input, contents, schema, options, context, &mut recorder);
+                        (file_events, recorder.calls)
+                    })
+                    .collect()
+            });
+            for (input, (file_events, calls)) in batch.iter().zip(results) {
+                let start = parsed.events.len();
+                replay(input, &file_events, calls, options, parsed, observer)?;
+                finish_file(input, start, parsed, observer);
+            }
+            continue;
+        }
+
+        // Each file is replayed while it's parsed, the later ones of the batch
+        // waiting once they have parsed a few chunks ahead. Every worker has a
+        // thread of its own, since the batch is as large as the pool.
+        pool.in_place_scope(|scope| {
+            let mut replays = vec![];
+            for (input, contents) in batch.iter().zip(contents) {
+                let (chunks, replay) = bounded(CHUNKS_IN_FLIGHT);
+                replays.push(replay);
+                scope.spawn(move |_| {
+                    let mut forwarder = Forwarder {
+                        chunk: Chunk::new(),
+                        chunks,
+                    };
+                    parse_file(input, contents, schema, options, context, &mut forwarder);
+                });
+            }
+            for (input, chunks) in batch.iter().zip(replays) {
+                let start = parsed.events.len();
+                for Chunk { events, calls } in chunks {
+                    replay(input, &events, calls, options, parsed, observer)?;
+                }
+                finish_file(input, start, parsed, observer);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Parses `input`, or its `contents` if read ahead, on a worker thread. The
+/// events are kept and returned unless they are discarded, in which case
+/// `observer` keeps what it needs of them.
+fn parse_file(
+    input: &Input,
+    contents: Option<io::Result<Vec<u8>>>,
+    schema: &Schema,
+    options: &ParseOptions,
+    context: &Context,
+    observer: &mut impl Observer,
+) -> Vec<Event> {
+    let mut file_events = vec![];
+    let scope = input.scope(options, context, options.discard_events);
+    // The worker's observer never stops the parse.
+    let _ = match contents {
+        Some(contents) => {
+            input.parse_contents(contents, schema, &scope, &mut file_events, observer)
+        }
+        None => input.parse(schema, &scope, &mut file_events, observer),
+    };
+    file_events
+}
+
+/// Makes the `calls` recorded while parsing `file_events` of `input` on
+/// `observer`, keeping the events in `parsed` unless they are discarded.
+fn replay(
+    input: &Input,
+    file_events: &[Event],
+    calls: Vec<Call>,
+    options: &ParseOptions,
+    parsed: &mut ParsedDir,
+    observer: &mut impl Observer,
+) -> Result<(), Error> {
+    let events = &mut parsed.events;
+    let mut row_start = 0;
+    for call in calls {
+        match call {
+            Call::Events(row_end) => {
+                let row = events.len();
+                events.extend_from_slice(&file_events[row_start..row_end]);
+                row_start = row_end;
+                observer.on_events(&events[row..]);
+                if options.discard_events {
+                    events.truncate(row);
+                }
+            }
+            Call::Skipped(error) => observer.on_skipped(error)?,
+            Call::EmptySection(line, op) => observer.on_empty_section(&input.file_name, line, op),
+        }
+    }
+    Ok(())
+}
+
+/// Records the events `input` added to `parsed` from `start` on and tells
+/// `observer` the file is done.
+fn finish_file(input: &Input, start: usize, parsed: &mut ParsedDir, observer: &mut impl Observer) {
+    let file_name = &input.file_name;
+    parsed
+        .files
+        .push((file_name.clone(), start..parsed.events.len()));
+    if parsed.events.len() > start {
+        observer.on_file(file_name, &parsed.events[start..]);
+    }
+    observer.on_parsed(file_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the skipped records.
+    #[derive(Default)]
+    struct Skips(Vec<Error>);
+
+    impl Observer for Skips {
+        fn on_skipped(&mut self, error: Error) -> Result<(), Error> {
+            self.0.push(error);
+            Ok(())
+        }
+    }
+
+    fn entry(sst: u64, blk: u64, secs: &str) -> String {
+        format!(
+            "(SstableBlockIndex {{ sst_id: {sst}, block_idx: {blk} }}, \
+             SystemTime {{ tv_sec: {secs}, tv_nsec: 5 }})"
+        )
+    }
+
+    /// Rows of the built-in layout with two valid ones among ragged and
+    /// broken ones, then a last valid one.
+    fn ragged_csv() -> Vec<u8> {
+        let evicted = "========== EVICTED DATA BLOCKS ==========";
+        let missed = "========== MISSED DATA BLOCKS ==========";
+        let mut csv = b"timestamp,message\n".to_vec();
+        csv.extend(format!("t0,\"{evicted}\n[{}]\"\n", entry(3, 0, "1700000003")).as_bytes());
+        // More fields than the header.
+        csv.extend(
+            format!(
+                "t1,\"{missed}\n[{}]\",extra,more\n",
+                entry(4, 6, "1700000004")
+            )
+            .as_bytes(),
+        );
+        // Fewer fields than the header.
+        csv.extend(b"t2\n");
+        // Not UTF-8.
+        csv.extend(b"t3,\"\xff\xfe\"\n");
+        // A time that doesn't fit.
+        csv.extend(
+            format!(
+                "t4,\"{missed}\n[{}]\"\n",
+                entry(5, 1, "18446744073709551615")
+            )
+            .as_bytes(),
+        );
+        csv.extend(format!("t5,\"{evicted}\n[{}]\"\n", entry(4, 6, "1700000005")).as_bytes());
+        csv
+    }
+
+    #[test]
+    fn ragged_csv_keeps_valid_rows() {
+        let csv = ragged_csv();
+        let schema = Schema::builtin(TimeField::Systime);
+        let mut events = vec![];
+        let mut skips = Skips::default();
+        parse_csv(
+            &csv[..],
+            "ragged.csv",
+            &schema,
+            Duration::ZERO,
+            &Context::default(),
+            &mut events,
+            &mut skips,
+        )
+        .unwrap();
+
+        let parsed: Vec<_> = (events.iter())
+            .map(|event| (event.op, event.data.sst, event.data.blk))
+            .collect();
+        assert_eq!(
+            parsed,
+            [(Op::Evicted, 3, 0), (Op::Missed, 4, 6), (Op::Evicted, 4, 6)]
+        );
+        assert_eq!(events[1].time, UNIX_EPOCH + Duration::new(1700000004, 5));
+        let lines: Vec<_> = (skips.0.iter())
+            .map(|error| match error {
+                Error::Malformed { file, line, .. } => (file.as_str(), *line),
+                Error::Read { .. } => panic!("unexpected {error}"),
+            })
+            .collect();
+        assert_eq!(lines, [("ragged.csv", 7), ("ragged.csv", 8)]);
+    }
+
+    #[test]
+    fn ragged_csv_parses_the_same_mapped() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("ragged.csv"), ragged_csv()).unwrap();
+        let schema = Schema::builtin(TimeField::Systime);
+        let parse = |mmap| {
+            let options = ParseOptions {
+                mmap,
+                ..ParseOptions::default()
+            };
+            let mut skips = Skips::default();
+            let context = Context::default();
+            let events = parse_dir(dir.path(), &schema, &options, &context, &mut skips)
+                .unwrap()
+                .events;
+            (events, skips.0.len())
+        };
+        let (read, read_skips) = parse(false);
+        let (mapped, mapped_skips) = parse(true);
+        assert_eq!(read.len(), 3);
+        assert_eq!(read, mapped);
+        assert_eq!((read_skips, mapped_skips), (2, 2));
+    }
+}
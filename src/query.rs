@@ -0,0 +1,380 @@
+//! Filter expressions over events, such as
+//! `op == miss && sst == 1234 && ts > '2024-05-01 12:00'`.
+//!
+//! A comparison has a field on the left and a value on the right:
+//!
+//! - `op`: `evicted`, `missed`, `inserted` or `hit`, or `evict`, `miss` and
+//!   `insert`, with `==` and `!=` only.
+//! - `sst`, `blk` (or `block`): integers.
+//! - `ts` (or `time`): a quoted RFC3339 or `YYYY-MM-DD HH:MM[:SS]` local
+//!   time, or nanoseconds since the Unix epoch.
+//! - `source`: a name, quoted unless it's made of letters, digits, `_`, `-`
+//!   and `.`, with `-` for events without one, with `==` and `!=` only.
+//! - `kind`: the name of a kind of key, see [`crate::model::Data::kind`],
+//!   `data` for data blocks, like `source`.
+//! - `level`, `table`: integers from [`crate::sst_meta`]. Comparisons with
+//!   them never hold for SSTs without the field.
+//!
+//! Comparisons combine with `&&`, `||`, `!` and parentheses, `&&` binding
+//! tighter than `||`.
+
+use crate::model::{Context, Event, Op, parse_time};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Op(Cmp, Op),
+    Int(IntField, Cmp, u64),
+    Time(Cmp, SystemTime),
+    Source(Cmp, String),
+    Kind(Cmp, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntField {
+    Sst,
+    Blk,
+    Level,
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Cmp::Eq => left == right,
+            Cmp::Ne => left != right,
+            Cmp::Lt => left < right,
+            Cmp::Le => left <= right,
+            Cmp::Gt => left > right,
+            Cmp::Ge => left >= right,
+        }
+    }
+}
+
+impl Query {
+    /// Parses an expression, for use as a clap value parser.
+    pub fn parse(s: &str) -> Result<Query, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            at: 0,
+        };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.at) {
+            None => Ok(query),
+            Some((token, at)) => Err(format!("unexpected {} at {at}", token.describe())),
+        }
+    }
+
+    /// Whether `event` satisfies the expression, with the names and SST
+    /// metadata of `context`.
+    pub fn matches(&self, event: &Event, context: &Context) -> bool {
+        match self {
+            Query::And(left, right) => {
+                left.matches(event, context) && right.matches(event, context)
+            }
+            Query::Or(left, right) => left.matches(event, context) || right.matches(event, context),
+            Query::Not(query) => !query.matches(event, context),
+            Query::Op(cmp, op) => cmp.holds(event.op, *op),
+            Query::Int(field, cmp, value) => {
+                let data = &event.data;
+                let left = match field {
+                    IntField::Sst => Some(data.sst),
+                    IntField::Blk => Some(data.blk),
+                    IntField::Level => {
+                        (context.sst_meta(data.sst)).and_then(|meta| meta.level.map(u64::from))
+                    }
+                    IntField::Table => context.sst_meta(data.sst).and_then(|meta| meta.table_id),
+                };
+                left.is_some_and(|left| cmp.holds(left, *value))
+            }
+            Query::Time(cmp, time) => cmp.holds(event.time, *time),
+            Query::Source(cmp, name) => {
+                let source = context.source_name(event.data.source);
+                cmp.holds(source.as_deref().unwrap_or("-"), name.as_str())
+            }
+            Query::Kind(cmp, name) => {
+                cmp.holds(&*context.kind_label(event.data.kind), name.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Quoted(String),
+    Cmp(Cmp),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("`{name}`"),
+            Token::Number(n) => format!("`{n}`"),
+            Token::Quoted(s) => format!("'{s}'"),
+            Token::Cmp(_) => "comparison".to_string(),
+            Token::And => "`&&`".to_string(),
+            Token::Or => "`||`".to_string(),
+            Token::Not => "`!`".to_string(),
+            Token::Open => "`(`".to_string(),
+            Token::Close => "`)`".to_string(),
+        }
+    }
+}
+
+/// Splits `s` into tokens and their byte offsets.
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let token = match (c, next) {
+            _ if c.is_whitespace() => continue,
+            ('=', Some('=')) => Token::Cmp(Cmp::Eq),
+            ('!', Some('=')) => Token::Cmp(Cmp::Ne),
+            ('<', Some('=')) => Token::Cmp(Cmp::Le),
+            ('>', Some('=')) => Token::Cmp(Cmp::Ge),
+            ('&', Some('&')) => Token::And,
+            ('|', Some('|')) => Token::Or,
+            ('<', _) => Token::Cmp(Cmp::Lt),
+            ('>', _) => Token::Cmp(Cmp::Gt),
+            ('!', _) => Token::Not,
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            ('\'' | '"', _) => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, end)) if end == c => break,
+                        Some((_, c)) => quoted.push(c),
+                        None => return Err(format!("unterminated quote at {at}")),
+                    }
+                }
+                tokens.push((Token::Quoted(quoted), at));
+                continue;
+            }
+            _ if is_word(c) => {
+                let mut word = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !is_word(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let token = match word.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => Token::Ident(word),
+                };
+                tokens.push((token, at));
+                continue;
+            }
+            _ => return Err(format!("unexpected `{c}` at {at}")),
+        };
+        // Two character tokens.
+        if matches!(
+            token,
+            Token::Cmp(Cmp::Eq | Cmp::Ne | Cmp::Le | Cmp::Ge) | Token::And | Token::Or
+        ) {
+            chars.next();
+        }
+        tokens.push((token, at));
+    }
+    Ok(tokens)
+}
+
+/// Whether `c` belongs in a field name, bare value or number.
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Recursive descent over the tokens, one method per precedence level.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    at: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.at).is_some_and(|(t, _)| t == token);
+        self.at += found as usize;
+        found
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        while self.eat(&Token::Or) {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.not()?;
+        while self.eat(&Token::And) {
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+        if self.eat(&Token::Open) {
+            let query = self.or()?;
+            return match self.next() {
+                Some((Token::Close, _)) => Ok(query),
+                Some((token, at)) => {
+                    Err(format!("expected `)`, found {} at {at}", token.describe()))
+                }
+                None => Err("expected `)` at the end".to_string()),
+            };
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Query, String> {
+        let (field, at) = match self.next() {
+            Some((Token::Ident(field), at)) => (field, at),
+            Some((token, at)) => {
+                return Err(format!(
+                    "expected a field, found {} at {at}",
+                    token.describe()
+                ));
+            }
+            None => return Err("expected a field at the end".to_string()),
+        };
+        let cmp = match self.next() {
+            Some((Token::Cmp(cmp), _)) => cmp,
+            Some((token, at)) => {
+                return Err(format!(
+                    "expected a comparison, found {} at {at}",
+                    token.describe()
+                ));
+            }
+            None => return Err(format!("expected a comparison after `{field}`")),
+        };
+        let (value, value_at) = self
+            .next()
+            .ok_or_else(|| format!("expected a value after `{field}`"))?;
+        let equality = |what: &str| match cmp {
+            Cmp::Eq | Cmp::Ne => Ok(()),
+            _ => Err(format!(
+                "{what} at {at} can only be compared with `==` or `!=`"
+            )),
+        };
+        let int = |field| match &value {
+            Token::Number(n) => Ok(Query::Int(field, cmp, *n)),
+            token => Err(format!(
+                "expected an integer, found {} at {value_at}",
+                token.describe()
+            )),
+        };
+        match field.as_str() {
+            "op" => {
+                equality("op")?;
+                let op = match &value {
+                    Token::Ident(name) | Token::Quoted(name) => parse_op(name),
+                    _ => None,
+                };
+                let op =
+                    op.ok_or_else(|| format!("unknown op {} at {value_at}", value.describe()))?;
+                Ok(Query::Op(cmp, op))
+            }
+            "sst" => int(IntField::Sst),
+            "blk" | "block" => int(IntField::Blk),
+            "level" => int(IntField::Level),
+            "table" => int(IntField::Table),
+            "ts" | "time" => {
+                let time = match &value {
+                    Token::Number(nanos) => Ok(UNIX_EPOCH + Duration::from_nanos(*nanos)),
+                    Token::Quoted(s) => parse_query_time(s),
+                    token => Err(format!(
+                        "expected a quoted time, found {}",
+                        token.describe()
+                    )),
+                };
+                Ok(Query::Time(
+                    cmp,
+                    time.map_err(|e| format!("{e} at {value_at}"))?,
+                ))
+            }
+            "source" => {
+                equality("source")?;
+                match value {
+                    Token::Ident(name) | Token::Quoted(name) => Ok(Query::Source(cmp, name)),
+                    Token::Number(n) => Ok(Query::Source(cmp, n.to_string())),
+                    token => Err(format!(
+                        "expected a source, found {} at {value_at}",
+                        token.describe()
+                    )),
+                }
+            }
+            "kind" => {
+                equality("kind")?;
+                match value {
+                    Token::Ident(name) | Token::Quoted(name) => Ok(Query::Kind(cmp, name)),
+                    token => Err(format!(
+                        "expected a kind, found {} at {value_at}",
+                        token.describe()
+                    )),
+                }
+            }
+            _ => Err(format!("unknown field `{field}` at {at}")),
+        }
+    }
+}
+
+fn parse_op(name: &str) -> Option<Op> {
+    match name {
+        "evict" => Some(Op::Evicted),
+        "miss" => Some(Op::Missed),
+        "insert" => Some(Op::Inserted),
+        _ => Op::ALL.into_iter().find(|op| op.name() == name),
+    }
+}
+
+/// Like [`parse_time`], also taking local times without seconds or without
+/// a time of day.
+fn parse_query_time(s: &str) -> Result<SystemTime, String> {
+    let local = |naive: NaiveDateTime| {
+        (Local.from_local_datetime(&naive).earliest())
+            .map(SystemTime::from)
+            .ok_or_else(|| format!("{naive} doesn't exist in the local time zone"))
+    };
+    if let Ok(naive) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        return local(naive);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return local(date.and_hms_opt(0, 0, 0).unwrap());
+    }
+    parse_time(s)
+}
@@ -0,0 +1,113 @@
+//! Input files in S3 compatible object storage, listed and streamed instead
+//! of downloaded first. Credentials, the region and the endpoint come from
+//! the usual `AWS_*` environment variables.
+
+use crate::async_io::runtime;
+use crate::parse::S3_SCHEME;
+use bytes::Bytes;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use object_store::ObjectStore;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path as ObjectPath;
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// The client of `bucket`, built once.
+fn store(bucket: &str) -> io::Result<Arc<AmazonS3>> {
+    static STORES: LazyLock<Mutex<HashMap<String, Arc<AmazonS3>>>> =
+        LazyLock::new(Default::default);
+    let mut stores = STORES.lock().unwrap();
+    if let Some(store) = stores.get(bucket) {
+        return Ok(store.clone());
+    }
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .build()
+        .map_err(io::Error::other)?;
+    let store = Arc::new(store);
+    stores.insert(bucket.to_string(), store.clone());
+    Ok(store)
+}
+
+/// Splits `s3://bucket/key` into the bucket and the key.
+fn split(url: &str) -> io::Result<(&str, &str)> {
+    let rest = (url.strip_prefix(S3_SCHEME))
+        .ok_or_else(|| io::Error::other(format!("{url} is not an {S3_SCHEME} URL")))?;
+    let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return Err(io::Error::other(format!("{url} has no bucket")));
+    }
+    Ok((bucket, key))
+}
+
+/// The objects below the prefix `url` names, like the files of a
+/// directory: their keys relative to it and their URLs, in key order. Only
+/// those right below it unless `recursive`.
+pub fn list(url: &str, recursive: bool) -> io::Result<Vec<(String, String)>> {
+    let (bucket, prefix) = split(url)?;
+    let store = store(bucket)?;
+    let prefix = ObjectPath::from(prefix);
+    let objects = runtime().block_on(async {
+        if recursive {
+            let listed: Vec<_> = store.list(Some(&prefix)).collect().await;
+            listed.into_iter().collect::<Result<Vec<_>, _>>()
+        } else {
+            Ok(store.list_with_delimiter(Some(&prefix)).await?.objects)
+        }
+    });
+    let mut objects: Vec<(String, String)> = (objects.map_err(io::Error::other)?.into_iter())
+        .map(|object| {
+            let key = object.location.as_ref();
+            let relative = key.strip_prefix(prefix.as_ref()).unwrap_or(key);
+            let relative = relative.trim_start_matches('/').to_string();
+            (relative, format!("{S3_SCHEME}{bucket}/{key}"))
+        })
+        .collect();
+    objects.sort();
+    Ok(objects)
+}
+
+/// Streams the object at `url`.
+pub fn open(url: &str) -> io::Result<impl Read + Send + 'static> {
+    let (bucket, key) = split(url)?;
+    let store = store(bucket)?;
+    let path = ObjectPath::from(key);
+    let stream = runtime()
+        .block_on(async move { store.get(&path).await })
+        .map_err(io::Error::other)?
+        .into_stream();
+    Ok(ObjectReader {
+        stream,
+        chunk: Bytes::new(),
+    })
+}
+
+/// The whole object at `url`, for [`crate::async_io::read_ahead`].
+pub async fn read(url: &str) -> io::Result<Vec<u8>> {
+    let (bucket, key) = split(url)?;
+    let store = store(bucket)?;
+    let object = (store.get(&ObjectPath::from(key)).await).map_err(io::Error::other)?;
+    Ok(object.bytes().await.map_err(io::Error::other)?.to_vec())
+}
+
+/// Reads an object's body chunk by chunk as it arrives.
+struct ObjectReader {
+    stream: BoxStream<'static, object_store::Result<Bytes>>,
+    chunk: Bytes,
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match runtime().block_on(self.stream.next()) {
+                Some(chunk) => self.chunk = chunk.map_err(io::Error::other)?,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        Ok(n)
+    }
+}
@@ -0,0 +1,218 @@
+//! External merge sort of events, for traces that don't fit in memory.
+
+use crate::model::{Data, Event, Op, Origin};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, Write};
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Size of an encoded event in a spill file.
+const RECORD_SIZE: usize = 8 + 4 + 8 + 8 + 4 + 1 + 4 + 4 + 4;
+
+/// Memory an event of a chunk takes, with the buffer sorting it needs.
+const CHUNK_EVENT_SIZE: usize = size_of::<Event>() * 3 / 2;
+
+/// The [`ExternalSort`] chunk size keeping the memory a chunk takes within
+/// about `bytes`.
+pub fn chunk_size_for(bytes: u64) -> usize {
+    (bytes / CHUNK_EVENT_SIZE as u64)
+        .try_into()
+        .unwrap_or(usize::MAX)
+}
+
+/// Sorts events by `key`, holding at most `chunk_size` of them in memory and
+/// spilling sorted chunks to temporary files. Equal keys keep their push
+/// order, like a stable in-memory sort.
+pub struct ExternalSort<K> {
+    key: Box<dyn Fn(&Event) -> K>,
+    chunk: Vec<Event>,
+    chunk_size: usize,
+    dir: PathBuf,
+    spills: Vec<File>,
+    len: usize,
+}
+
+impl<K: Ord> ExternalSort<K> {
+    /// Spill files are created in `dir` and removed once dropped.
+    pub fn new(key: impl Fn(&Event) -> K + 'static, chunk_size: usize, dir: PathBuf) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            key: Box::new(key),
+            // Grown as needed, the chunk size may be far more than there are.
+            chunk: Vec::with_capacity(chunk_size.min(1 << 20)),
+            chunk_size,
+            dir,
+            spills: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Event) -> io::Result<()> {
+        self.chunk.push(event);
+        self.len += 1;
+        if self.chunk.len() >= self.chunk_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Number of events pushed so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.chunk.sort_by_key(|event| (self.key)(event));
+        let mut writer = BufWriter::new(tempfile::tempfile_in(&self.dir)?);
+        for event in self.chunk.drain(..) {
+            writer.write_all(&encode(&event))?;
+        }
+        let mut file = writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.rewind()?;
+        self.spills.push(file);
+        Ok(())
+    }
+
+    /// Merges the spilled chunks and the last one, which stays in memory,
+    /// into a single sorted stream. Nothing is spilled if all events fit in
+    /// one chunk.
+    pub fn finish(mut self) -> io::Result<Merge<K>> {
+        self.chunk.sort_by_key(|event| (self.key)(event));
+        let mut merge = Merge {
+            key: self.key,
+            readers: self.spills.into_iter().map(BufReader::new).collect(),
+            last: self.chunk.into_iter(),
+            heap: BinaryHeap::new(),
+        };
+        for index in 0..=merge.readers.len() {
+            merge.advance(index)?;
+        }
+        Ok(merge)
+    }
+}
+
+/// The sorted events of an [`ExternalSort`].
+pub struct Merge<K> {
+    key: Box<dyn Fn(&Event) -> K>,
+    readers: Vec<BufReader<File>>,
+    /// The chunk that wasn't spilled, after all of `readers`.
+    last: std::vec::IntoIter<Event>,
+    heap: BinaryHeap<Reverse<Head<K>>>,
+}
+
+/// The next event of the spill file at `index`.
+struct Head<K> {
+    key: K,
+    index: usize,
+    event: Event,
+}
+
+impl<K: Ord> Ord for Head<K> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Earlier spill files hold earlier pushes, which keeps the merge stable.
+        (&self.key, self.index).cmp(&(&other.key, other.index))
+    }
+}
+
+impl<K: Ord> PartialOrd for Head<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for Head<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<K: Ord> Eq for Head<K> {}
+
+impl<K: Ord> Merge<K> {
+    fn advance(&mut self, index: usize) -> io::Result<()> {
+        let event = match self.readers.get_mut(index) {
+            Some(reader) => {
+                let mut buf = [0; RECORD_SIZE];
+                match reader.read_exact(&mut buf) {
+                    Ok(()) => Some(decode(&buf)?),
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+                    Err(e) => return Err(e),
+                }
+            }
+            None => self.last.next(),
+        };
+        if let Some(event) = event {
+            self.heap.push(Reverse(Head {
+                key: (self.key)(&event),
+                index,
+                event,
+            }));
+        }
+        Ok(())
+    }
+}
+
+impl<K: Ord> Iterator for Merge<K> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(Head { index, event, .. }) = self.heap.pop()?;
+        Some(self.advance(index).map(|()| event))
+    }
+}
+
+fn encode(event: &Event) -> [u8; RECORD_SIZE] {
+    let since_epoch = event.time.duration_since(UNIX_EPOCH).unwrap();
+    let mut buf = [0; RECORD_SIZE];
+    buf[0..8].copy_from_slice(&since_epoch.as_secs().to_le_bytes());
+    buf[8..12].copy_from_slice(&since_epoch.subsec_nanos().to_le_bytes());
+    buf[12..20].copy_from_slice(&event.data.sst.to_le_bytes());
+    buf[20..28].copy_from_slice(&event.data.blk.to_le_bytes());
+    buf[28..32].copy_from_slice(&event.data.source.to_le_bytes());
+    buf[32] = event.op as u8;
+    buf[33..37].copy_from_slice(&event.origin.file.to_le_bytes());
+    buf[37..41].copy_from_slice(&event.origin.line.to_le_bytes());
+    buf[41..45].copy_from_slice(&event.data.kind.to_le_bytes());
+    buf
+}
+
+fn decode(buf: &[u8; RECORD_SIZE]) -> io::Result<Event> {
+    let u64_at = |at: usize| u64::from_le_bytes(buf[at..at + 8].try_into().unwrap());
+    let secs = u64_at(0);
+    let u32_at = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+    let nanos = u32_at(8);
+    let op = match buf[32] {
+        0 => Op::Evicted,
+        1 => Op::Missed,
+        2 => Op::Inserted,
+        3 => Op::Hit,
+        op => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid op {op} in spill file"),
+            ));
+        }
+    };
+    Ok(Event {
+        data: Data {
+            sst: u64_at(12),
+            blk: u64_at(20),
+            source: u32_at(28),
+            kind: u32_at(41),
+        },
+        time: UNIX_EPOCH + Duration::new(secs, nanos),
+        op,
+        origin: Origin {
+            file: u32_at(33),
+            line: u32_at(37),
+        },
+    })
+}
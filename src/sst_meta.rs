@@ -0,0 +1,62 @@
+//! Metadata of the SSTs of a trace, such as their LSM level, joined to the
+//! events by SST id through [`crate::model::Context::sst_meta`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+/// What is known about one SST. Every field is optional in the input.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SstMeta {
+    /// The table the SST belongs to.
+    pub table_id: Option<u64>,
+    /// LSM level, 0 for the newest.
+    pub level: Option<u32>,
+    /// Size of the SST file in bytes.
+    pub file_size: Option<u64>,
+    /// Number of blocks in the SST.
+    pub block_count: Option<u64>,
+}
+
+/// One entry of an SST metadata file.
+#[derive(Debug, Deserialize)]
+struct Row {
+    sst_id: u64,
+    #[serde(default)]
+    table_id: Option<u64>,
+    #[serde(default)]
+    level: Option<u32>,
+    #[serde(default)]
+    file_size: Option<u64>,
+    #[serde(default)]
+    block_count: Option<u64>,
+}
+
+/// SST metadata by SST id.
+pub type SstMetas = HashMap<u64, SstMeta>;
+
+/// Loads SST metadata from a JSON array of objects, or a CSV file with a
+/// header row for any other extension. Either has an `sst_id` and any of
+/// the [`SstMeta`] fields per entry.
+pub fn load_sst_meta(path: &str) -> Result<SstMetas, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let rows: Vec<Row> = if path.ends_with(".json") {
+        serde_json::from_str(&content).map_err(|e| format!("{path}: {e}"))?
+    } else {
+        (csv::Reader::from_reader(content.as_bytes()).deserialize())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{path}: {e}"))?
+    };
+    Ok((rows.into_iter())
+        .map(|row| {
+            let meta = SstMeta {
+                table_id: row.table_id,
+                level: row.level,
+                file_size: row.file_size,
+                block_count: row.block_count,
+            };
+            (row.sst_id, meta)
+        })
+        .collect())
+}
@@ -0,0 +1,201 @@
+//! User defined layouts of the text output lines, such as
+//! `{sst},{blk},{ts_epoch}`.
+
+use crate::format::ts_nanos;
+use crate::model::{Context, Event};
+use std::io::Write;
+
+/// A field of an event a template can show.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Column {
+    Sst,
+    Blk,
+    /// The time as the text output shows it, see [`Context::format_time`].
+    Ts,
+    /// Seconds since the Unix epoch, with nanoseconds.
+    #[value(name = "ts_epoch")]
+    TsEpoch,
+    #[value(name = "ts_nanos")]
+    TsNanos,
+    /// The lowercase op name, see [`crate::model::Op::name`].
+    Op,
+    /// Empty without sources told apart.
+    Source,
+    /// The kind of key, see [`crate::model::Data::kind`], `data` for data
+    /// blocks.
+    Kind,
+    /// The block as the default text layout shows it.
+    Data,
+    /// The rest from [`crate::sst_meta`], empty without.
+    #[value(name = "table_id")]
+    TableId,
+    Level,
+    #[value(name = "file_size")]
+    FileSize,
+    #[value(name = "block_count")]
+    BlockCount,
+    /// The input file and line of the record, empty without provenance, see
+    /// [`Context::with_provenance`].
+    File,
+    Line,
+}
+
+impl Column {
+    pub const ALL: [Column; 15] = [
+        Column::Sst,
+        Column::Blk,
+        Column::Ts,
+        Column::TsEpoch,
+        Column::TsNanos,
+        Column::Op,
+        Column::Source,
+        Column::Kind,
+        Column::Data,
+        Column::TableId,
+        Column::Level,
+        Column::FileSize,
+        Column::BlockCount,
+        Column::File,
+        Column::Line,
+    ];
+
+    /// The name of the column in `--columns` and templates.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Sst => "sst",
+            Column::Blk => "blk",
+            Column::Ts => "ts",
+            Column::TsEpoch => "ts_epoch",
+            Column::TsNanos => "ts_nanos",
+            Column::Op => "op",
+            Column::Source => "source",
+            Column::Kind => "kind",
+            Column::Data => "data",
+            Column::TableId => "table_id",
+            Column::Level => "level",
+            Column::FileSize => "file_size",
+            Column::BlockCount => "block_count",
+            Column::File => "file",
+            Column::Line => "line",
+        }
+    }
+
+    fn write(self, event: &Event, context: &Context, line: &mut Vec<u8>) {
+        let data = &event.data;
+        let meta = || context.sst_meta(data.sst).cloned().unwrap_or_default();
+        let optional = |line: &mut Vec<u8>, value: Option<u64>| {
+            if let Some(value) = value {
+                write!(line, "{value}").unwrap();
+            }
+        };
+        match self {
+            Column::Sst => write!(line, "{}", data.sst).unwrap(),
+            Column::Blk => write!(line, "{}", data.blk).unwrap(),
+            Column::Ts => write!(line, "{}", context.format_time(event.time)).unwrap(),
+            Column::TsEpoch => {
+                let nanos = ts_nanos(event.time);
+                write!(
+                    line,
+                    "{}.{:09}",
+                    nanos / 1_000_000_000,
+                    nanos % 1_000_000_000
+                )
+                .unwrap();
+            }
+            Column::TsNanos => write!(line, "{}", ts_nanos(event.time)).unwrap(),
+            Column::Op => line.extend_from_slice(event.op.name().as_bytes()),
+            Column::Source => {
+                if let Some(source) = context.source_name(data.source) {
+                    line.extend_from_slice(source.as_bytes())
+                }
+            }
+            Column::Kind => line.extend_from_slice(context.kind_label(data.kind).as_bytes()),
+            Column::Data => write!(line, "{}", context.data(*data)).unwrap(),
+            Column::TableId => optional(line, meta().table_id),
+            Column::Level => optional(line, meta().level.map(u64::from)),
+            Column::FileSize => optional(line, meta().file_size),
+            Column::BlockCount => optional(line, meta().block_count),
+            Column::File => {
+                if let Some(name) = context.file_name(event.origin) {
+                    line.extend_from_slice(name.as_bytes())
+                }
+            }
+            Column::Line => {
+                let known = context.file_name(event.origin).is_some();
+                optional(line, known.then_some(u64::from(event.origin.line)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Column(Column),
+}
+
+/// The layout of one output line: text with `{column}` placeholders, and
+/// `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses a template, for use as a clap value parser.
+    pub fn parse(s: &str) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut rest = s;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = (rest.strip_prefix("{{")).or_else(|| rest.strip_prefix("}}")) {
+                literal.push(c);
+                rest = after;
+            } else if c == '{' {
+                let end = rest.find('}').ok_or("unclosed `{` in template")?;
+                let name = &rest[1..end];
+                let column = (Column::ALL.into_iter())
+                    .find(|column| column.name() == name)
+                    .ok_or_else(|| format!("unknown column `{name}` in template"))?;
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Column(column));
+                rest = &rest[end + 1..];
+            } else if c == '}' {
+                return Err("unmatched `}` in template, use `}}` for a brace".to_string());
+            } else {
+                literal.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// `columns` separated by `sep`.
+    pub fn columns(columns: &[Column], sep: &str) -> Template {
+        let mut parts = vec![];
+        for (i, column) in columns.iter().enumerate() {
+            if i > 0 {
+                parts.push(Part::Literal(sep.to_string()));
+            }
+            parts.push(Part::Column(*column));
+        }
+        Template { parts }
+    }
+
+    /// Appends the line of `event`, with a newline, showing it as `context`
+    /// does.
+    pub fn write(&self, event: &Event, context: &Context, line: &mut Vec<u8>) {
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => line.extend_from_slice(literal.as_bytes()),
+                Part::Column(column) => column.write(event, context, line),
+            }
+        }
+        line.push(b'\n');
+    }
+}